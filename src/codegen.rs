@@ -1,18 +1,23 @@
 //! Intermediate code generation from the AST.
 use sqlparser::{
-    ast::{self, SelectItem, SetExpr, Statement, TableFactor, TableWithJoins},
+    ast::{
+        self, AlterTableOperation, JoinConstraint, JoinOperator, SelectItem, SetExpr, Statement,
+        TableFactor, TableWithJoins,
+    },
     parser::ParserError,
 };
 
-use std::{error::Error, fmt::Display};
+use std::{collections::HashMap, error::Error, fmt::Display};
 
 use crate::{
-    expr::{Expr, ExprError},
-    ic::{Instruction, IntermediateCode},
-    identifier::IdentifierError,
-    parser::parse,
+    expr::{agg::AggregateFunction, BinOp, Expr, ExprError},
+    ic::{Assignment, Instruction, IntermediateCode, JoinType, OrderKey},
+    identifier::{identifiers_eq, ColumnRef, IdentifierError, TableRef},
+    parser::{parse, statement_name},
+    table::StorageLayout,
     value::{Value, ValueError},
     vm::RegisterIndex,
+    BoundedString,
 };
 
 /// Represents either a parser error or a codegen error.
@@ -72,11 +77,11 @@ pub fn codegen_ast(ast: &Statement) -> Result<IntermediateCode, CodegenError> {
             name,
             columns,
             // TODO: support table level constraints
-            constraints: _,
+            constraints,
             hive_distribution: _,
             hive_formats: _,
             table_properties: _,
-            with_options: _,
+            with_options,
             file_format: _,
             location: _,
             query: _,
@@ -87,6 +92,55 @@ pub fn codegen_ast(ast: &Statement) -> Result<IntermediateCode, CodegenError> {
             collation: _,
             on_commit: _,
         } => {
+            // a primary key given as a table constraint is the same as a column option, as long
+            // as it is on a single column.
+            let mut columns = columns.clone();
+            for constraint in constraints {
+                if let ast::TableConstraint::Unique {
+                    name,
+                    columns: key_columns,
+                    is_primary: true,
+                } = constraint
+                {
+                    let col = match key_columns.as_slice() {
+                        [key_column] => columns
+                            .iter_mut()
+                            .find(|col| col.name.value == key_column.value),
+                        _ => {
+                            return Err(CodegenError::UnsupportedStatementForm(
+                                "Composite primary keys are not supported",
+                                constraint.to_string(),
+                            ))
+                        }
+                    };
+                    match col {
+                        Some(col) => col.options.push(ast::ColumnOptionDef {
+                            name: name.clone(),
+                            option: ast::ColumnOption::Unique { is_primary: true },
+                        }),
+                        None => {
+                            return Err(CodegenError::UnsupportedStatementForm(
+                                "Primary key on a column which is not in the table",
+                                constraint.to_string(),
+                            ))
+                        }
+                    }
+                }
+            }
+            let num_primary_keys = columns
+                .iter()
+                .flat_map(|col| col.options.iter())
+                .filter(|option| {
+                    matches!(
+                        option.option,
+                        ast::ColumnOption::Unique { is_primary: true }
+                    )
+                })
+                .count();
+            if num_primary_keys > 1 {
+                return Err(CodegenError::MultiplePrimaryKeys(name.to_string()));
+            }
+
             let table_reg_index = current_reg;
             instrs.push(Instruction::Empty {
                 index: table_reg_index,
@@ -115,10 +169,32 @@ pub fn codegen_ast(ast: &Statement) -> Result<IntermediateCode, CodegenError> {
                 });
             }
 
+            // other options are ignored.
+            let mut layout = StorageLayout::default();
+            for option in with_options {
+                if option.name.value.eq_ignore_ascii_case("storage") {
+                    layout = match &option.value {
+                        ast::Value::SingleQuotedString(s) if s.eq_ignore_ascii_case("rows") => {
+                            StorageLayout::Rows
+                        }
+                        ast::Value::SingleQuotedString(s) if s.eq_ignore_ascii_case("columnar") => {
+                            StorageLayout::Columnar
+                        }
+                        _ => {
+                            return Err(CodegenError::UnsupportedStatementForm(
+                                "Storage must be either 'rows' or 'columnar'",
+                                option.to_string(),
+                            ))
+                        }
+                    };
+                }
+            }
+
             instrs.push(Instruction::NewTable {
                 index: table_reg_index,
                 name: name.0.clone().try_into()?,
                 exists_ok: *if_not_exists,
+                layout,
             });
             Ok(())
         }
@@ -196,223 +272,214 @@ pub fn codegen_ast(ast: &Statement) -> Result<IntermediateCode, CodegenError> {
             let mut table_reg_index = current_reg;
             current_reg = current_reg.next_index();
 
-            match &query.body {
-                SetExpr::Select(select) => {
-                    match select.from.as_slice() {
-                        [TableWithJoins { relation, joins }] => {
-                            if !joins.is_empty() {
-                                // TODO: joins
-                                return Err(CodegenError::UnsupportedStatementForm(
-                                    "Joins are not supported yet",
-                                    select.to_string(),
-                                ));
-                            }
-
-                            match relation {
-                                TableFactor::Table {
-                                    name,
-                                    // TODO: support table alias
-                                    alias: _,
-                                    args: _,
-                                    with_hints: _,
-                                } => instrs.push(Instruction::Source {
-                                    index: table_reg_index,
-                                    name: name.0.clone().try_into()?,
-                                }),
-                                TableFactor::Derived { .. } => {
-                                    // TODO: support tables derived from a query
-                                    return Err(CodegenError::UnsupportedStatementForm(
-                                        "Derived tables are not supportd yet",
-                                        relation.to_string(),
-                                    ));
-                                }
-                                TableFactor::NestedJoin(_) => {
-                                    // TODO: support nested joins
-                                    return Err(CodegenError::UnsupportedStatementForm(
-                                        "Nested JOINs are not supportd yet",
-                                        relation.to_string(),
-                                    ));
-                                }
-                                TableFactor::TableFunction { .. } => {
-                                    // no plans to support these yet
-                                    return Err(CodegenError::UnsupportedStatementForm(
-                                        "Table functions are not supportd yet",
-                                        relation.to_string(),
-                                    ));
-                                }
-                                TableFactor::UNNEST { .. } => {
-                                    // no plans to support these yet
-                                    return Err(CodegenError::UnsupportedStatementForm(
-                                        "UNNEST are not supportd yet",
-                                        relation.to_string(),
-                                    ));
-                                }
-                            }
-                        }
-                        &[] => instrs.push(Instruction::NonExistent {
-                            index: table_reg_index,
-                        }),
-                        _ => {
-                            // TODO: multiple tables
-                            return Err(CodegenError::UnsupportedStatementForm(
-                                "Only select from a single table is supported for now",
-                                format!("{:?}", select.from),
-                            ));
-                        }
-                    }
-
-                    if let Some(expr) = select.selection.clone() {
-                        instrs.push(Instruction::Filter {
-                            index: table_reg_index,
-                            expr: expr.try_into()?,
-                        })
-                    }
-
-                    for group_by in select.group_by.clone() {
-                        instrs.push(Instruction::GroupBy {
-                            index: table_reg_index,
-                            expr: group_by.try_into()?,
-                        });
-                    }
+            // aliases given to projected expressions, for `HAVING` and `ORDER BY` to refer to.
+            let mut aliases = Vec::<(BoundedString, Expr)>::new();
 
-                    if let Some(expr) = select.having.clone() {
-                        instrs.push(Instruction::Filter {
-                            index: table_reg_index,
-                            expr: expr.try_into()?,
-                        })
-                    }
+            table_reg_index = codegen_set_expr(
+                &query.body,
+                table_reg_index,
+                &mut current_reg,
+                &mut instrs,
+                &mut aliases,
+            )?;
 
-                    if !select.projection.is_empty() {
-                        let original_table_reg_index = table_reg_index;
-                        table_reg_index = current_reg;
-                        current_reg = current_reg.next_index();
+            if !query.order_by.is_empty() {
+                let keys = query
+                    .order_by
+                    .iter()
+                    .map(|order_by| {
+                        let mut key = OrderKey::new(
+                            collapse_aliases(order_by.expr.clone().try_into()?, &aliases),
+                            order_by.asc.unwrap_or(true),
+                        );
+                        if let Some(nulls_first) = order_by.nulls_first {
+                            key.nulls_first = nulls_first;
+                        }
+                        Ok(key)
+                    })
+                    .collect::<Result<_, CodegenError>>()?;
+                instrs.push(Instruction::Order {
+                    index: table_reg_index,
+                    keys,
+                });
+            }
 
-                        instrs.push(Instruction::Empty {
-                            index: table_reg_index,
-                        });
+            // rows are skipped before the limit is applied.
+            if let Some(offset) = query.offset.clone() {
+                instrs.push(Instruction::Offset {
+                    index: table_reg_index,
+                    offset: constant_row_count(
+                        offset.value,
+                        "OFFSET must not be negative",
+                        "Only constant integer OFFSETs are supported",
+                    )?,
+                });
+            }
 
-                        for projection in select.projection.clone() {
-                            instrs.push(Instruction::Project {
-                                input: original_table_reg_index,
-                                output: table_reg_index,
-                                expr: match projection {
-                                    SelectItem::UnnamedExpr(ref expr) => expr.clone().try_into()?,
-                                    SelectItem::ExprWithAlias { ref expr, .. } => {
-                                        expr.clone().try_into()?
-                                    }
-                                    SelectItem::QualifiedWildcard(_) => Expr::Wildcard,
-                                    SelectItem::Wildcard => Expr::Wildcard,
-                                },
-                                alias: match projection {
-                                    SelectItem::UnnamedExpr(_) => None,
-                                    SelectItem::ExprWithAlias { alias, .. } => {
-                                        Some(alias.value.as_str().into())
-                                    }
-                                    SelectItem::QualifiedWildcard(name) => {
-                                        return Err(CodegenError::UnsupportedStatementForm(
-                                            "Qualified wildcards are not supported yet",
-                                            name.to_string(),
-                                        ))
-                                    }
-                                    SelectItem::Wildcard => None,
-                                },
-                            })
-                        }
+            // `LIMIT ALL` is parsed as no limit at all.
+            if let Some(limit) = query.limit.clone() {
+                instrs.push(Instruction::Limit {
+                    index: table_reg_index,
+                    limit: constant_row_count(
+                        limit,
+                        "LIMIT must not be negative",
+                        "Only constant integer LIMITs are supported",
+                    )?,
+                });
+            }
 
-                        if select.distinct {
-                            return Err(CodegenError::UnsupportedStatementForm(
-                                "DISTINCT is not supported yet",
-                                select.to_string(),
-                            ));
-                        }
-                    }
-                }
-                SetExpr::Values(exprs) => {
-                    if exprs.0.len() == 1 && exprs.0[0].len() == 1 {
-                        let expr: Expr = exprs.0[0][0].clone().try_into()?;
-                        instrs.push(Instruction::Expr {
-                            index: table_reg_index,
-                            expr,
-                        });
-                    } else {
-                        // TODO: selecting multiple values.
-                        //       the problem here is creating a temp table
-                        //       without information about column names
-                        //       and (more importantly) types.
-                        return Err(CodegenError::UnsupportedStatementForm(
-                            "Selecting more than one value is not supported yet",
-                            exprs.to_string(),
-                        ));
-                    }
-                }
-                SetExpr::Query(query) => {
-                    // TODO: figure out what syntax this corresponds to
-                    //       and implement it if necessary
-                    return Err(CodegenError::UnsupportedStatementForm(
-                        "Query within a query not supported yet",
-                        query.to_string(),
-                    ));
-                }
-                SetExpr::SetOperation {
-                    op: _,
-                    all: _,
-                    left: _,
-                    right: _,
-                } => {
-                    // TODO: set operations
-                    return Err(CodegenError::UnsupportedStatementForm(
-                        "Set operations are not supported yet",
-                        query.to_string(),
-                    ));
-                }
-                SetExpr::Insert(insert) => {
-                    // TODO: figure out what syntax this corresponds to
-                    //       and implement it if necessary
+            // `FETCH FIRST n ROWS ONLY` is the standard form of `LIMIT n`.
+            if let Some(fetch) = query.fetch.clone() {
+                if fetch.with_ties || fetch.percent {
                     return Err(CodegenError::UnsupportedStatementForm(
-                        "Insert within query not supported yet",
-                        insert.to_string(),
+                        "FETCH with PERCENT or WITH TIES is not supported yet",
+                        fetch.to_string(),
                     ));
                 }
-            };
 
-            for order_by in query.order_by.clone() {
-                instrs.push(Instruction::Order {
+                instrs.push(Instruction::Limit {
                     index: table_reg_index,
-                    expr: order_by.expr.try_into()?,
-                    ascending: order_by.asc.unwrap_or(true),
+                    limit: match fetch.quantity {
+                        Some(quantity) => constant_row_count(
+                            quantity,
+                            "FETCH must not be negative",
+                            "Only constant integer FETCH counts are supported",
+                        )?,
+                        // `FETCH NEXT ROW ONLY`
+                        None => 1,
+                    },
                 });
-                // TODO: support NULLS FIRST/NULLS LAST
             }
 
-            if let Some(limit) = query.limit.clone() {
-                if let ast::Expr::Value(val) = limit.clone() {
-                    if let Value::Int64(limit) = val.clone().try_into()? {
-                        instrs.push(Instruction::Limit {
-                            index: table_reg_index,
-                            limit: limit as u64,
-                        });
-                    } else {
-                        // TODO: what are non constant limits anyway?
-                        return Err(CodegenError::Expr(ExprError::Value(ValueError {
-                            reason: "Only constant integer LIMITs are supported",
-                            value: val,
-                        })));
-                    }
-                } else {
-                    // TODO: what are non constant limits anyway?
-                    return Err(CodegenError::Expr(ExprError::Expr {
-                        reason: "Only constant integer LIMITs are supported",
-                        expr: limit,
-                    }));
-                }
+            instrs.push(Instruction::Return {
+                index: table_reg_index,
+            });
+
+            Ok(())
+        }
+        Statement::Update {
+            table,
+            assignments,
+            from,
+            selection,
+        } => {
+            if !table.joins.is_empty() || from.is_some() {
+                // TODO: updates using other tables
+                return Err(CodegenError::UnsupportedStatementForm(
+                    "Only updating a single table is supported for now",
+                    ast.to_string(),
+                ));
             }
 
-            instrs.push(Instruction::Return {
+            let table_reg_index = current_reg;
+            instrs.push(codegen_table_factor(&table.relation, table_reg_index)?);
+
+            if let Some(expr) = selection {
+                instrs.push(Instruction::Filter {
+                    index: table_reg_index,
+                    expr: expr.clone().try_into()?,
+                });
+            }
+
+            instrs.push(Instruction::Update {
+                index: table_reg_index,
+                assignments: assignments
+                    .iter()
+                    .map(|assignment| {
+                        Ok(Assignment {
+                            col: Expr::ColumnRef(assignment.id.clone().try_into()?),
+                            expr: assignment.value.clone().try_into()?,
+                        })
+                    })
+                    .collect::<Result<_, CodegenError>>()?,
+            });
+
+            Ok(())
+        }
+        Statement::AlterTable { name, operation } => {
+            let table_reg_index = current_reg;
+            instrs.push(Instruction::Source {
                 index: table_reg_index,
+                name: name.0.clone().try_into()?,
             });
 
+            match operation {
+                AlterTableOperation::DropColumn {
+                    column_name,
+                    if_exists: false,
+                    // TODO: support `CASCADE`
+                    cascade: _,
+                } => instrs.push(Instruction::RemoveColumn {
+                    index: table_reg_index,
+                    col_name: column_name.value.as_str().into(),
+                }),
+                AlterTableOperation::RenameColumn {
+                    old_column_name,
+                    new_column_name,
+                } => instrs.push(Instruction::RenameColumn {
+                    index: table_reg_index,
+                    old_name: old_column_name.value.as_str().into(),
+                    new_name: new_column_name.value.as_str().into(),
+                }),
+                _ => {
+                    // TODO: other alterations
+                    return Err(CodegenError::UnsupportedStatementForm(
+                        "Only dropping and renaming columns is supported for now",
+                        ast.to_string(),
+                    ));
+                }
+            }
+
+            Ok(())
+        }
+        Statement::Drop {
+            object_type: ast::ObjectType::Table,
+            if_exists,
+            names,
+            // TODO: support `CASCADE`
+            cascade: _,
+            purge: _,
+        } => {
+            for name in names {
+                instrs.push(Instruction::DropTable {
+                    name: name.0.clone().try_into()?,
+                    if_exists: *if_exists,
+                });
+            }
             Ok(())
         }
+        Statement::Drop { object_type, .. } => Err(CodegenError::UnsupportedStatementForm(
+            "Only DROP TABLE is supported for now",
+            format!("DROP {}", object_type),
+        )),
+        Statement::CreateIndex {
+            // indexes are not named, so they can not be dropped yet.
+            name: _,
+            table_name,
+            columns,
+            unique: false,
+            // an index on a column which is indexed already is never an error.
+            if_not_exists: _,
+        } => match columns.as_slice() {
+            [ast::OrderByExpr {
+                expr: ast::Expr::Identifier(col_name),
+                ..
+            }] => {
+                instrs.push(Instruction::NewIndex {
+                    table: table_name.0.clone().try_into()?,
+                    col_name: col_name.value.as_str().into(),
+                });
+                Ok(())
+            }
+            _ => Err(CodegenError::UnsupportedStatementForm(
+                "Only indexes on a single column are supported",
+                ast.to_string(),
+            )),
+        },
+        Statement::CreateIndex { .. } => Err(CodegenError::UnsupportedStatementForm(
+            "Unique indexes are not supported",
+            ast.to_string(),
+        )),
         Statement::CreateSchema {
             schema_name,
             if_not_exists,
@@ -423,68 +490,749 @@ pub fn codegen_ast(ast: &Statement) -> Result<IntermediateCode, CodegenError> {
             });
             Ok(())
         }
-        _ => Err(CodegenError::UnsupportedStatement(ast.to_string())),
+        Statement::StartTransaction { modes } if modes.is_empty() => {
+            instrs.push(Instruction::Begin);
+            Ok(())
+        }
+        Statement::StartTransaction { .. } => Err(CodegenError::UnsupportedStatementForm(
+            "Transaction modes are not supported",
+            ast.to_string(),
+        )),
+        Statement::Explain {
+            analyze: false,
+            statement,
+            ..
+        } => {
+            instrs.push(Instruction::Explain {
+                index: current_reg,
+                code: codegen_ast(statement)?,
+            });
+            instrs.push(Instruction::Return { index: current_reg });
+            Ok(())
+        }
+        Statement::Explain { .. } => Err(CodegenError::UnsupportedStatementForm(
+            "EXPLAIN ANALYZE is not supported",
+            ast.to_string(),
+        )),
+        Statement::Commit { chain: false } => {
+            instrs.push(Instruction::Commit);
+            Ok(())
+        }
+        Statement::Rollback { chain: false } => {
+            instrs.push(Instruction::Rollback);
+            Ok(())
+        }
+        _ => Err(CodegenError::UnsupportedStatement(
+            statement_name(ast),
+            ast.to_string(),
+        )),
     }?;
 
     Ok(IntermediateCode { instrs })
 }
 
-/// Error while generating an intermediate code from the AST.
-#[derive(Debug)]
-pub enum CodegenError {
-    UnsupportedStatement(String),
-    UnsupportedStatementForm(&'static str, String),
-    InvalidIdentifier(IdentifierError),
-    Expr(ExprError),
-}
+/// Generate code for the body of a query, i.e. a `SELECT`, `VALUES` or a set operation.
+///
+/// The result starts in the register `index`. Returns the register which holds the result at
+/// the end. Aliases of projected expressions are added to `aliases`.
+fn codegen_set_expr(
+    body: &SetExpr,
+    index: RegisterIndex,
+    current_reg: &mut RegisterIndex,
+    instrs: &mut Vec<Instruction>,
+    aliases: &mut Vec<(BoundedString, Expr)>,
+) -> Result<RegisterIndex, CodegenError> {
+    let mut table_reg_index = index;
 
-impl Display for CodegenError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            CodegenError::UnsupportedStatement(s) => write!(f, "Unsupported statement: {}", s),
-            CodegenError::InvalidIdentifier(i) => {
-                write!(f, "{}", i)
+    match body {
+        SetExpr::Select(select) => {
+            let mut from = select.from.iter();
+            match from.next() {
+                Some(table_with_joins) => {
+                    table_reg_index =
+                        codegen_from(table_with_joins, table_reg_index, current_reg, instrs)?;
+
+                    // `FROM table1, table2` is the same as a cross join.
+                    for table_with_joins in from {
+                        let right_reg_index = *current_reg;
+                        *current_reg = current_reg.next_index();
+                        let right_reg_index =
+                            codegen_from(table_with_joins, right_reg_index, current_reg, instrs)?;
+
+                        let output_reg_index = *current_reg;
+                        *current_reg = current_reg.next_index();
+                        instrs.push(Instruction::CrossJoin {
+                            input1: table_reg_index,
+                            input2: right_reg_index,
+                            output: output_reg_index,
+                        });
+                        table_reg_index = output_reg_index;
+                    }
+                }
+                None => instrs.push(Instruction::NonExistent {
+                    index: table_reg_index,
+                }),
             }
-            CodegenError::UnsupportedStatementForm(reason, statement) => {
-                write!(f, "Unsupported: {} (Got: '{}')", reason, statement)
+
+            if let Some(expr) = select.selection.clone() {
+                instrs.push(Instruction::Filter {
+                    index: table_reg_index,
+                    expr: expr.try_into()?,
+                })
             }
-            CodegenError::Expr(e) => write!(f, "{}", e),
+
+            for group_by in select.group_by.clone() {
+                let expr: Expr = group_by.clone().try_into()?;
+                if expr.contains_aggregate() {
+                    return Err(CodegenError::Expr(ExprError::Expr {
+                        reason: "Aggregate functions are not allowed in GROUP BY",
+                        expr: group_by,
+                    }));
+                }
+                instrs.push(Instruction::GroupBy {
+                    index: table_reg_index,
+                    expr,
+                });
+            }
+
+            for projection in &select.projection {
+                if let SelectItem::ExprWithAlias { expr, alias } = projection {
+                    aliases.push((alias.value.as_str().into(), expr.clone().try_into()?));
+                }
+            }
+
+            // without `GROUP BY`, aggregates turn the whole table into a single row, so there is
+            // no one value for a column outside of them.
+            if select.group_by.is_empty() {
+                let mut exprs = Vec::with_capacity(select.projection.len() + 1);
+                for projection in &select.projection {
+                    match projection {
+                        SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                            exprs.push(Expr::try_from(expr.clone())?)
+                        }
+                        SelectItem::QualifiedWildcard(_) | SelectItem::Wildcard => {
+                            exprs.push(Expr::Wildcard)
+                        }
+                    }
+                }
+                if let Some(having) = &select.having {
+                    exprs.push(having.clone().try_into()?);
+                }
+
+                if select.having.is_some() || exprs.iter().any(Expr::contains_aggregate) {
+                    for expr in &exprs {
+                        if let Expr::Wildcard = expr {
+                            return Err(CodegenError::UngroupedColumn("*".to_owned()));
+                        }
+                        if let Some(col_ref) = expr.column_outside_aggregates() {
+                            return Err(CodegenError::UngroupedColumn(col_ref.to_string()));
+                        }
+                    }
+                }
+            }
+
+            if let Some(expr) = select.having.clone() {
+                let mut grouped = Vec::with_capacity(select.group_by.len());
+                for group_by in select.group_by.clone() {
+                    grouped.push(group_by.try_into()?);
+                }
+                if select.group_by.is_empty() {
+                    instrs.push(Instruction::GroupAll {
+                        index: table_reg_index,
+                    });
+                }
+                instrs.push(Instruction::Filter {
+                    index: table_reg_index,
+                    expr: expand_having_aliases(expr.try_into()?, aliases, &grouped),
+                })
+            }
+
+            if !select.projection.is_empty() {
+                let original_table_reg_index = table_reg_index;
+                table_reg_index = *current_reg;
+                *current_reg = current_reg.next_index();
+
+                instrs.push(Instruction::Empty {
+                    index: table_reg_index,
+                });
+
+                for projection in select.projection.clone() {
+                    instrs.push(Instruction::Project {
+                        input: original_table_reg_index,
+                        output: table_reg_index,
+                        expr: match projection {
+                            SelectItem::UnnamedExpr(ref expr) => expr.clone().try_into()?,
+                            SelectItem::ExprWithAlias { ref expr, .. } => {
+                                expr.clone().try_into()?
+                            }
+                            SelectItem::QualifiedWildcard(_) => Expr::Wildcard,
+                            SelectItem::Wildcard => Expr::Wildcard,
+                        },
+                        alias: match projection {
+                            SelectItem::UnnamedExpr(_) => None,
+                            SelectItem::ExprWithAlias { alias, .. } => {
+                                Some(alias.value.as_str().into())
+                            }
+                            SelectItem::QualifiedWildcard(name) => {
+                                return Err(CodegenError::UnsupportedStatementForm(
+                                    "Qualified wildcards are not supported yet",
+                                    name.to_string(),
+                                ))
+                            }
+                            SelectItem::Wildcard => None,
+                        },
+                    })
+                }
+
+                if select.distinct {
+                    instrs.push(Instruction::Distinct {
+                        index: table_reg_index,
+                    });
+                }
+            }
+        }
+        SetExpr::Values(exprs) => {
+            if exprs.0.len() == 1 && exprs.0[0].len() == 1 {
+                let expr: Expr = exprs.0[0][0].clone().try_into()?;
+                instrs.push(Instruction::Expr {
+                    index: table_reg_index,
+                    expr,
+                });
+            } else {
+                // TODO: selecting multiple values.
+                //       the problem here is creating a temp table
+                //       without information about column names
+                //       and (more importantly) types.
+                return Err(CodegenError::UnsupportedStatementForm(
+                    "Selecting more than one value is not supported yet",
+                    exprs.to_string(),
+                ));
+            }
+        }
+        SetExpr::Query(query) => {
+            // TODO: figure out what syntax this corresponds to
+            //       and implement it if necessary
+            return Err(CodegenError::UnsupportedStatementForm(
+                "Query within a query not supported yet",
+                query.to_string(),
+            ));
+        }
+        SetExpr::SetOperation {
+            op: ast::SetOperator::Union,
+            all,
+            left,
+            right,
+        } => {
+            // column names and aliases come from the first query.
+            let left_reg_index =
+                codegen_set_expr(left, table_reg_index, current_reg, instrs, aliases)?;
+
+            let right_reg_index = *current_reg;
+            *current_reg = current_reg.next_index();
+            let right_reg_index =
+                codegen_set_expr(right, right_reg_index, current_reg, instrs, &mut Vec::new())?;
+
+            table_reg_index = *current_reg;
+            *current_reg = current_reg.next_index();
+            instrs.push(Instruction::Union {
+                input1: left_reg_index,
+                input2: right_reg_index,
+                output: table_reg_index,
+                all: *all,
+            });
+        }
+        SetExpr::SetOperation { op, .. } => {
+            // TODO: other set operations
+            return Err(CodegenError::UnsupportedStatementForm(
+                "Only UNION is supported for now",
+                format!("{} ({})", op, body),
+            ));
+        }
+        SetExpr::Insert(insert) => {
+            // TODO: figure out what syntax this corresponds to
+            //       and implement it if necessary
+            return Err(CodegenError::UnsupportedStatementForm(
+                "Insert within query not supported yet",
+                insert.to_string(),
+            ));
         }
     }
+
+    Ok(table_reg_index)
 }
 
-impl From<IdentifierError> for CodegenError {
-    fn from(i: IdentifierError) -> Self {
-        CodegenError::InvalidIdentifier(i)
+/// The number of rows given by a constant, non-negative integer, as in `LIMIT` and `OFFSET`.
+fn constant_row_count(
+    expr: ast::Expr,
+    negative_reason: &'static str,
+    non_constant_reason: &'static str,
+) -> Result<u64, CodegenError> {
+    if let ast::Expr::Value(val) = expr {
+        match val.clone().try_into()? {
+            Value::Int64(count) if count >= 0 => Ok(count as u64),
+            Value::Int64(_) => Err(CodegenError::Expr(ExprError::Value(ValueError {
+                reason: negative_reason,
+                value: val,
+            }))),
+            // TODO: what are non constant limits anyway?
+            _ => Err(CodegenError::Expr(ExprError::Value(ValueError {
+                reason: non_constant_reason,
+                value: val,
+            }))),
+        }
+    } else {
+        Err(CodegenError::Expr(ExprError::Expr {
+            reason: non_constant_reason,
+            expr,
+        }))
     }
 }
 
-impl From<ExprError> for CodegenError {
-    fn from(e: ExprError) -> Self {
-        CodegenError::Expr(e)
+/// Generate code to load a table in `FROM`, along with its joins.
+///
+/// The table is loaded into the register `index`. Returns the register which holds the joined
+/// table at the end.
+fn codegen_from(
+    table_with_joins: &TableWithJoins,
+    index: RegisterIndex,
+    current_reg: &mut RegisterIndex,
+    instrs: &mut Vec<Instruction>,
+) -> Result<RegisterIndex, CodegenError> {
+    instrs.push(codegen_table_factor(&table_with_joins.relation, index)?);
+
+    let mut table_reg_index = index;
+    for join in &table_with_joins.joins {
+        let right_reg_index = *current_reg;
+        *current_reg = current_reg.next_index();
+        let output_reg_index = *current_reg;
+        *current_reg = current_reg.next_index();
+
+        let join_instr = match join.join_operator {
+            JoinOperator::CrossJoin => Instruction::CrossJoin {
+                input1: table_reg_index,
+                input2: right_reg_index,
+                output: output_reg_index,
+            },
+            JoinOperator::Inner(JoinConstraint::On(ref on))
+            | JoinOperator::LeftOuter(JoinConstraint::On(ref on))
+            | JoinOperator::RightOuter(JoinConstraint::On(ref on)) => Instruction::Join {
+                input1: table_reg_index,
+                input2: right_reg_index,
+                output: output_reg_index,
+                on: on.clone().try_into()?,
+                join_type: match join.join_operator {
+                    JoinOperator::LeftOuter(_) => JoinType::Left,
+                    JoinOperator::RightOuter(_) => JoinType::Right,
+                    _ => JoinType::Inner,
+                },
+            },
+            JoinOperator::Inner(JoinConstraint::Natural) => Instruction::NaturalJoin {
+                input1: table_reg_index,
+                input2: right_reg_index,
+                output: output_reg_index,
+            },
+            _ => {
+                // TODO: other joins
+                return Err(CodegenError::UnsupportedStatementForm(
+                    "Only CROSS and NATURAL JOINs, and INNER, LEFT and RIGHT JOINs with ON are supported for now",
+                    join.to_string(),
+                ));
+            }
+        };
+
+        instrs.push(codegen_table_factor(&join.relation, right_reg_index)?);
+        instrs.push(join_instr);
+        table_reg_index = output_reg_index;
     }
+
+    Ok(table_reg_index)
 }
 
-impl From<ValueError> for CodegenError {
-    fn from(v: ValueError) -> Self {
-        CodegenError::Expr(v.into())
+/// Generate the instruction to load a single table in `FROM` into the register `index`.
+fn codegen_table_factor(
+    relation: &TableFactor,
+    index: RegisterIndex,
+) -> Result<Instruction, CodegenError> {
+    match relation {
+        TableFactor::Table {
+            name,
+            // TODO: support table alias
+            alias: _,
+            args: _,
+            with_hints: _,
+        } => Ok(Instruction::Source {
+            index,
+            name: name.0.clone().try_into()?,
+        }),
+        TableFactor::Derived { .. } => {
+            // TODO: support tables derived from a query
+            Err(CodegenError::UnsupportedStatementForm(
+                "Derived tables are not supportd yet",
+                relation.to_string(),
+            ))
+        }
+        TableFactor::NestedJoin(_) => {
+            // TODO: support nested joins
+            Err(CodegenError::UnsupportedStatementForm(
+                "Nested JOINs are not supportd yet",
+                relation.to_string(),
+            ))
+        }
+        TableFactor::TableFunction { .. } => {
+            // no plans to support these yet
+            Err(CodegenError::UnsupportedStatementForm(
+                "Table functions are not supportd yet",
+                relation.to_string(),
+            ))
+        }
+        TableFactor::UNNEST { .. } => {
+            // no plans to support these yet
+            Err(CodegenError::UnsupportedStatementForm(
+                "UNNEST are not supportd yet",
+                relation.to_string(),
+            ))
+        }
     }
 }
 
-impl Error for CodegenError {}
-
-#[cfg(test)]
-mod tests {
-    use sqlparser::ast::{ColumnOption, ColumnOptionDef, DataType};
+/// Replace references to aliases of projected expressions with the expressions themselves.
+///
+/// Used for clauses like `HAVING` which are evaluated before the projection and hence can not
+/// see the aliased columns.
+fn expand_aliases(expr: Expr, aliases: &[(BoundedString, Expr)]) -> Expr {
+    expr.replace_with(&|e| match e {
+        Expr::ColumnRef(ColumnRef {
+            schema_name: None,
+            table_name: None,
+            col_name,
+        }) => aliases
+            .iter()
+            .find(|(alias, _)| alias == col_name)
+            .map(|(_, aliased)| aliased.clone()),
+        _ => None,
+    })
+}
 
-    use pretty_assertions::assert_eq;
+/// Replace references to aliases of projected expressions in a `HAVING` clause with the
+/// expressions themselves.
+///
+/// A name which is also a grouped column refers to that column, and the arguments of aggregate
+/// calls always refer to the source columns, so an alias only fills in for names that would not
+/// resolve to anything otherwise.
+fn expand_having_aliases(expr: Expr, aliases: &[(BoundedString, Expr)], grouped: &[Expr]) -> Expr {
+    expr.replace_with(&|e| match e {
+        Expr::Function { name, .. } if AggregateFunction::from_name(name).is_some() => {
+            Some(e.clone())
+        }
+        Expr::ColumnRef(ColumnRef {
+            schema_name: None,
+            table_name: None,
+            col_name,
+        }) => {
+            let is_grouped = grouped.iter().any(|grouped| {
+                matches!(grouped, Expr::ColumnRef(col_ref) if col_ref.col_name == *col_name)
+            });
+            if is_grouped {
+                None
+            } else {
+                aliases
+                    .iter()
+                    .find(|(alias, _)| alias == col_name)
+                    .map(|(_, aliased)| aliased.clone())
+            }
+        }
+        _ => None,
+    })
+}
 
-    use crate::{
-        codegen::codegen_ast,
-        expr::{BinOp, Expr},
-        ic::Instruction,
-        identifier::{ColumnRef, SchemaRef, TableRef},
+/// Replace references to the aliases of earlier projections with the aliased expressions, in
+/// each projection of the code.
+///
+/// Only the projections into the same output table can see each other's aliases.
+pub(crate) fn expand_lateral_aliases(ic: &mut IntermediateCode) {
+    let mut aliases = HashMap::<RegisterIndex, Vec<(BoundedString, Expr)>>::new();
+    for instr in ic.instrs.iter_mut() {
+        if let Instruction::Explain { code, .. } = instr {
+            expand_lateral_aliases(code);
+        } else if let Instruction::Project {
+            output,
+            expr,
+            alias,
+            ..
+        } = instr
+        {
+            let earlier = aliases.entry(*output).or_default();
+            *expr = expand_aliases(expr.clone(), earlier);
+            if let Some(alias) = alias {
+                earlier.push((*alias, expr.clone()));
+            }
+        }
+    }
+}
+
+/// Move the parts of filters which only refer to one input of a join below the join, so that
+/// fewer rows are joined.
+///
+/// This is conservative: only filters directly on the output of cross and inner joins are
+/// considered, and a part of an `AND` is only moved if all of its column references are
+/// qualified with the name of a table which is on one side of the join only.
+pub(crate) fn push_down_filters(ic: &mut IntermediateCode) {
+    let mut position = 0;
+    while position < ic.instrs.len() {
+        if let Instruction::Explain { code, .. } = &mut ic.instrs[position] {
+            push_down_filters(code);
+        }
+
+        // a moved filter may be movable further down, so start over.
+        if push_down_filter(&mut ic.instrs, position) {
+            position = 0;
+        } else {
+            position += 1;
+        }
+    }
+}
+
+/// Move what can be moved of the filter at `position` below the join right before it.
+///
+/// Returns whether anything was moved.
+fn push_down_filter(instrs: &mut Vec<Instruction>, position: usize) -> bool {
+    let (index, expr) = match &instrs[position] {
+        Instruction::Filter { index, expr } if position > 0 && !expr.contains_aggregate() => {
+            (*index, expr.clone())
+        }
+        _ => return false,
+    };
+    let (input1, input2) = match &instrs[position - 1] {
+        Instruction::CrossJoin {
+            input1,
+            input2,
+            output,
+        }
+        | Instruction::Join {
+            input1,
+            input2,
+            output,
+            join_type: JoinType::Inner,
+            ..
+        } if *output == index => (*input1, *input2),
+        _ => return false,
+    };
+
+    let (tables1, tables2) = match (
+        source_tables(&instrs[..position - 1], input1),
+        source_tables(&instrs[..position - 1], input2),
+    ) {
+        (Some(tables1), Some(tables2)) => (tables1, tables2),
+        _ => return false,
+    };
+
+    let (mut left, mut right, mut rest) = (Vec::new(), Vec::new(), Vec::new());
+    for conjunct in conjuncts(expr) {
+        let in1 = refers_only_to(&conjunct, &tables1);
+        let in2 = refers_only_to(&conjunct, &tables2);
+        match (in1, in2) {
+            (true, false) => left.push(conjunct),
+            (false, true) => right.push(conjunct),
+            _ => rest.push(conjunct),
+        }
+    }
+    if left.is_empty() && right.is_empty() {
+        return false;
+    }
+
+    match conjunction(rest) {
+        Some(expr) => instrs[position] = Instruction::Filter { index, expr },
+        None => {
+            instrs.remove(position);
+        }
+    }
+
+    // inserting the later input first keeps the position of the earlier one valid.
+    for (input, conjuncts) in [(input2, right), (input1, left)] {
+        if let Some(expr) = conjunction(conjuncts) {
+            let produced_at = producer(&instrs[..position - 1], input).unwrap();
+            instrs.insert(produced_at + 1, Instruction::Filter { index: input, expr });
+        }
+    }
+    true
+}
+
+/// Position of the last instruction before the end of `instrs` which loads or joins tables into
+/// the register.
+fn producer(instrs: &[Instruction], index: RegisterIndex) -> Option<usize> {
+    instrs.iter().rposition(|instr| match instr {
+        Instruction::Source { index: i, .. } => *i == index,
+        Instruction::CrossJoin { output, .. }
+        | Instruction::Join { output, .. }
+        | Instruction::NaturalJoin { output, .. } => *output == index,
+        _ => false,
+    })
+}
+
+/// The source tables whose rows end up in the register, if that is known.
+fn source_tables(instrs: &[Instruction], index: RegisterIndex) -> Option<Vec<TableRef>> {
+    let position = producer(instrs, index)?;
+    match &instrs[position] {
+        Instruction::Source { name, .. } => Some(vec![*name]),
+        Instruction::CrossJoin { input1, input2, .. }
+        | Instruction::Join { input1, input2, .. }
+        | Instruction::NaturalJoin { input1, input2, .. } => {
+            let mut tables = source_tables(&instrs[..position], *input1)?;
+            tables.extend(source_tables(&instrs[..position], *input2)?);
+            Some(tables)
+        }
+        _ => None,
+    }
+}
+
+/// Split an expression into the parts of its top-level `AND`s.
+fn conjuncts(expr: Expr) -> Vec<Expr> {
+    match expr {
+        Expr::Binary {
+            left,
+            op: BinOp::And,
+            right,
+        } => {
+            let mut parts = conjuncts(*left);
+            parts.extend(conjuncts(*right));
+            parts
+        }
+        expr => vec![expr],
+    }
+}
+
+/// Join expressions with `AND`, if there are any.
+fn conjunction(exprs: Vec<Expr>) -> Option<Expr> {
+    exprs.into_iter().reduce(|left, right| Expr::Binary {
+        left: Box::new(left),
+        op: BinOp::And,
+        right: Box::new(right),
+    })
+}
+
+/// Whether the expression refers to columns, all of which are qualified with one of the tables.
+fn refers_only_to(expr: &Expr, tables: &[TableRef]) -> bool {
+    let col_refs = expr.column_refs();
+    !col_refs.is_empty()
+        && col_refs
+            .into_iter()
+            .all(|col_ref| match col_ref.table_name {
+                Some(table_name) => tables.iter().any(|table| {
+                    identifiers_eq(&table.table_name, &table_name)
+                        && (col_ref.schema_name.is_none()
+                            || col_ref.schema_name == table.schema_name)
+                }),
+                None => false,
+            })
+}
+
+/// Replace projected expressions which have an alias with a reference to the alias.
+///
+/// Used for clauses like `ORDER BY` which are evaluated after the projection and hence can only
+/// see the projected columns.
+///
+/// A reference that already names an alias is left as is, since aliases take precedence over
+/// source columns of the same name.
+fn collapse_aliases(expr: Expr, aliases: &[(BoundedString, Expr)]) -> Expr {
+    expr.replace_with(&|e| {
+        if let Expr::ColumnRef(ColumnRef {
+            schema_name: None,
+            table_name: None,
+            col_name,
+        }) = e
+        {
+            if aliases.iter().any(|(alias, _)| alias == col_name) {
+                return Some(e.clone());
+            }
+        }
+
+        aliases
+            .iter()
+            .find(|(_, aliased)| aliased == e)
+            .map(|(alias, _)| {
+                Expr::ColumnRef(ColumnRef {
+                    schema_name: None,
+                    table_name: None,
+                    col_name: *alias,
+                })
+            })
+    })
+}
+
+/// Error while generating an intermediate code from the AST.
+#[derive(Debug)]
+pub enum CodegenError {
+    /// A kind of statement that is not supported at all, along with the statement itself.
+    UnsupportedStatement(&'static str, String),
+    UnsupportedStatementForm(&'static str, String),
+    InvalidIdentifier(IdentifierError),
+    Expr(ExprError),
+    /// More than one primary key for the named table.
+    MultiplePrimaryKeys(String),
+    /// A column selected alongside aggregates without being grouped by or aggregated itself.
+    UngroupedColumn(String),
+}
+
+impl Display for CodegenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CodegenError::UnsupportedStatement(kind, statement) => {
+                write!(f, "Unsupported statement: {} (Got: '{}')", kind, statement)
+            }
+            CodegenError::InvalidIdentifier(i) => {
+                write!(f, "{}", i)
+            }
+            CodegenError::UnsupportedStatementForm(reason, statement) => {
+                write!(f, "Unsupported: {} (Got: '{}')", reason, statement)
+            }
+            CodegenError::Expr(e) => write!(f, "{}", e),
+            CodegenError::MultiplePrimaryKeys(table_name) => write!(
+                f,
+                "Multiple primary keys for table '{}' are not allowed",
+                table_name
+            ),
+            CodegenError::UngroupedColumn(col_name) => write!(
+                f,
+                "Column '{}' must appear in the GROUP BY clause or be used in an aggregate function",
+                col_name
+            ),
+        }
+    }
+}
+
+impl From<IdentifierError> for CodegenError {
+    fn from(i: IdentifierError) -> Self {
+        CodegenError::InvalidIdentifier(i)
+    }
+}
+
+impl From<ExprError> for CodegenError {
+    fn from(e: ExprError) -> Self {
+        CodegenError::Expr(e)
+    }
+}
+
+impl From<ValueError> for CodegenError {
+    fn from(v: ValueError) -> Self {
+        CodegenError::Expr(v.into())
+    }
+}
+
+impl Error for CodegenError {}
+
+#[cfg(test)]
+mod tests {
+    use sqlparser::ast::{ColumnOption, ColumnOptionDef, DataType};
+
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        codegen::{codegen_ast, CodegenError},
+        expr::{BinOp, Expr, ExprError},
+        ic::{Assignment, Instruction, JoinType, OrderKey},
+        identifier::{ColumnRef, SchemaRef, TableRef},
         parser::parse,
+        table::StorageLayout,
         value::Value,
         vm::RegisterIndex,
     };
@@ -600,11 +1348,32 @@ mod tests {
                                 table_name: "table1".into()
                             },
                             exists_ok: true,
+                            layout: StorageLayout::Rows,
                         }
                     ]
                 )
             },
         );
+
+        check_single_statement(
+            "CREATE TABLE table1 (col1 INTEGER) WITH (storage = 'columnar')",
+            |instrs| {
+                assert!(matches!(
+                    instrs.last(),
+                    Some(Instruction::NewTable {
+                        layout: StorageLayout::Columnar,
+                        ..
+                    })
+                ))
+            },
+        );
+
+        let parsed =
+            parse("CREATE TABLE table1 (col1 INTEGER) WITH (storage = 'sideways')").unwrap();
+        assert!(matches!(
+            codegen_ast(&parsed[0]),
+            Err(CodegenError::UnsupportedStatementForm(_, _))
+        ));
     }
 
     #[test]
@@ -1039,7 +1808,7 @@ mod tests {
             FROM table1
             WHERE col1 = 1
             ORDER BY col2
-            LIMIT 100
+            LIMIT 100 OFFSET 20
             ",
             |instrs| {
                 assert_eq!(
@@ -1089,12 +1858,18 @@ mod tests {
                         },
                         Instruction::Order {
                             index: RegisterIndex::default().next_index(),
-                            expr: Expr::ColumnRef(ColumnRef {
-                                schema_name: None,
-                                table_name: None,
-                                col_name: "col2".into(),
-                            }),
-                            ascending: true
+                            keys: vec![OrderKey::new(
+                                Expr::ColumnRef(ColumnRef {
+                                    schema_name: None,
+                                    table_name: None,
+                                    col_name: "col2".into(),
+                                }),
+                                true
+                            )],
+                        },
+                        Instruction::Offset {
+                            index: RegisterIndex::default().next_index(),
+                            offset: 20,
                         },
                         Instruction::Limit {
                             index: RegisterIndex::default().next_index(),
@@ -1155,7 +1930,8 @@ mod tests {
                                         schema_name: None,
                                         table_name: None,
                                         col_name: "col3".into(),
-                                    })]
+                                    })],
+                                    distinct: false
                                 }),
                                 op: BinOp::GreaterThan,
                                 right: Box::new(Expr::Value(Value::Int64(10)))
@@ -1183,10 +1959,96 @@ mod tests {
                                     schema_name: None,
                                     table_name: None,
                                     col_name: "col3".into(),
-                                })]
+                                })],
+                                distinct: false
+                            },
+                            alias: Some("max_col3".into())
+                        },
+                        Instruction::Return {
+                            index: RegisterIndex::default().next_index(),
+                        }
+                    ]
+                )
+            },
+        );
+    }
+
+    #[test]
+    fn aggregate_aliases() {
+        let max_col3 = Expr::Function {
+            name: "MAX".into(),
+            args: vec![Expr::ColumnRef(ColumnRef {
+                schema_name: None,
+                table_name: None,
+                col_name: "col3".into(),
+            })],
+            distinct: false,
+        };
+
+        check_single_statement(
+            "SELECT col2, MAX(col3) AS max_col3
+            FROM table1
+            GROUP BY col2
+            HAVING max_col3 > 10
+            ORDER BY max_col3 DESC
+            ",
+            |instrs| {
+                assert_eq!(
+                    instrs,
+                    &[
+                        Instruction::Source {
+                            index: RegisterIndex::default(),
+                            name: TableRef {
+                                schema_name: None,
+                                table_name: "table1".into()
+                            }
+                        },
+                        Instruction::GroupBy {
+                            index: RegisterIndex::default(),
+                            expr: Expr::ColumnRef(ColumnRef {
+                                schema_name: None,
+                                table_name: None,
+                                col_name: "col2".into(),
+                            })
+                        },
+                        Instruction::Filter {
+                            index: RegisterIndex::default(),
+                            expr: Expr::Binary {
+                                left: Box::new(max_col3.clone()),
+                                op: BinOp::GreaterThan,
+                                right: Box::new(Expr::Value(Value::Int64(10)))
                             },
+                        },
+                        Instruction::Empty {
+                            index: RegisterIndex::default().next_index()
+                        },
+                        Instruction::Project {
+                            input: RegisterIndex::default(),
+                            output: RegisterIndex::default().next_index(),
+                            expr: Expr::ColumnRef(ColumnRef {
+                                schema_name: None,
+                                table_name: None,
+                                col_name: "col2".into(),
+                            }),
+                            alias: None
+                        },
+                        Instruction::Project {
+                            input: RegisterIndex::default(),
+                            output: RegisterIndex::default().next_index(),
+                            expr: max_col3.clone(),
                             alias: Some("max_col3".into())
                         },
+                        Instruction::Order {
+                            index: RegisterIndex::default().next_index(),
+                            keys: vec![OrderKey::new(
+                                Expr::ColumnRef(ColumnRef {
+                                    schema_name: None,
+                                    table_name: None,
+                                    col_name: "max_col3".into(),
+                                }),
+                                false
+                            )],
+                        },
                         Instruction::Return {
                             index: RegisterIndex::default().next_index(),
                         }
@@ -1195,4 +2057,492 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn unsupported_statement() {
+        // `CREATE VIEW` stands in for the statements which are parsed but not supported.
+        // `CREATE FUNCTION` would not even be parsed by the generic dialect.
+        assert!(parse("CREATE FUNCTION f AS 'f'").is_err());
+        let parsed = parse("CREATE VIEW view1 AS SELECT 1").unwrap();
+        let err = codegen_ast(&parsed[0]).unwrap_err();
+
+        assert!(matches!(
+            err,
+            CodegenError::UnsupportedStatement("CREATE VIEW", _)
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Unsupported statement: CREATE VIEW (Got: 'CREATE VIEW view1 AS SELECT 1')"
+        );
+    }
+
+    #[test]
+    fn cross_join() {
+        let table_reg_index = RegisterIndex::default();
+        let table_reg_index_2 = table_reg_index.next_index();
+        let joined_reg_index = table_reg_index_2.next_index();
+        let output_reg_index = joined_reg_index.next_index();
+        let expected = [
+            Instruction::Source {
+                index: table_reg_index,
+                name: TableRef {
+                    schema_name: None,
+                    table_name: "table1".into(),
+                },
+            },
+            Instruction::Source {
+                index: table_reg_index_2,
+                name: TableRef {
+                    schema_name: None,
+                    table_name: "table2".into(),
+                },
+            },
+            Instruction::CrossJoin {
+                input1: table_reg_index,
+                input2: table_reg_index_2,
+                output: joined_reg_index,
+            },
+            Instruction::Empty {
+                index: output_reg_index,
+            },
+            Instruction::Project {
+                input: joined_reg_index,
+                output: output_reg_index,
+                expr: Expr::Wildcard,
+                alias: None,
+            },
+            Instruction::Return {
+                index: output_reg_index,
+            },
+        ];
+
+        check_single_statement("SELECT * FROM table1, table2", |instrs| {
+            assert_eq!(instrs, &expected)
+        });
+        check_single_statement("SELECT * FROM table1 CROSS JOIN table2", |instrs| {
+            assert_eq!(instrs, &expected)
+        });
+
+        let parsed = parse("SELECT * FROM table1 FULL JOIN table2 ON col1 = col2").unwrap();
+        assert!(matches!(
+            codegen_ast(&parsed[0]),
+            Err(CodegenError::UnsupportedStatementForm(_, _))
+        ));
+    }
+
+    #[test]
+    fn natural_join() {
+        check_single_statement("SELECT * FROM table1 NATURAL JOIN table2", |instrs| {
+            assert_eq!(
+                &instrs[..3],
+                &[
+                    Instruction::Source {
+                        index: RegisterIndex::default(),
+                        name: TableRef {
+                            schema_name: None,
+                            table_name: "table1".into(),
+                        },
+                    },
+                    Instruction::Source {
+                        index: RegisterIndex::default().next_index(),
+                        name: TableRef {
+                            schema_name: None,
+                            table_name: "table2".into(),
+                        },
+                    },
+                    Instruction::NaturalJoin {
+                        input1: RegisterIndex::default(),
+                        input2: RegisterIndex::default().next_index(),
+                        output: RegisterIndex::default().next_index().next_index(),
+                    },
+                ]
+            )
+        });
+    }
+
+    #[test]
+    fn inner_join() {
+        check_single_statement(
+            "SELECT * FROM table1 JOIN table2 ON table1.col1 = col2",
+            |instrs| {
+                assert_eq!(
+                    &instrs[2],
+                    &Instruction::Join {
+                        input1: RegisterIndex::default(),
+                        input2: RegisterIndex::default().next_index(),
+                        output: RegisterIndex::default().next_index().next_index(),
+                        on: Expr::Binary {
+                            left: Box::new(Expr::ColumnRef(ColumnRef {
+                                schema_name: None,
+                                table_name: Some("table1".into()),
+                                col_name: "col1".into(),
+                            })),
+                            op: BinOp::Equal,
+                            right: Box::new(Expr::ColumnRef(ColumnRef {
+                                schema_name: None,
+                                table_name: None,
+                                col_name: "col2".into(),
+                            })),
+                        },
+                        join_type: JoinType::Inner,
+                    }
+                )
+            },
+        );
+
+        let parsed = parse("SELECT * FROM table1 JOIN table2 USING (col1)").unwrap();
+        assert!(matches!(
+            codegen_ast(&parsed[0]),
+            Err(CodegenError::UnsupportedStatementForm(_, _))
+        ));
+    }
+
+    #[test]
+    fn push_down_filters() {
+        let col = |table_name: Option<&str>, col_name: &str| {
+            Box::new(Expr::ColumnRef(ColumnRef {
+                schema_name: None,
+                table_name: table_name.map(|name| name.into()),
+                col_name: col_name.into(),
+            }))
+        };
+        let eq = |left, right| Expr::Binary {
+            left,
+            op: BinOp::Equal,
+            right,
+        };
+        let int = |i| Box::new(Expr::Value(Value::Int64(i)));
+        let source = |index, table_name: &str| Instruction::Source {
+            index,
+            name: TableRef {
+                schema_name: None,
+                table_name: table_name.into(),
+            },
+        };
+
+        let reg0 = RegisterIndex::default();
+        let reg1 = reg0.next_index();
+        let reg2 = reg1.next_index();
+        let reg3 = reg2.next_index();
+        let reg4 = reg3.next_index();
+        let reg5 = reg4.next_index();
+
+        let parsed = parse(
+            "SELECT * FROM table1 JOIN table2 ON table1.id = table2.id CROSS JOIN table3 \
+            WHERE table1.a = 1 AND table3.b = 2 AND table1.a = table2.a AND c = 3",
+        )
+        .unwrap();
+        let mut ic = codegen_ast(&parsed[0]).unwrap();
+        super::push_down_filters(&mut ic);
+
+        // parts referring to both sides or to no table in particular stay where they were.
+        assert_eq!(
+            ic.instrs,
+            vec![
+                source(reg0, "table1"),
+                Instruction::Filter {
+                    index: reg0,
+                    expr: eq(col(Some("table1"), "a"), int(1)),
+                },
+                source(reg1, "table2"),
+                Instruction::Join {
+                    input1: reg0,
+                    input2: reg1,
+                    output: reg2,
+                    on: eq(col(Some("table1"), "id"), col(Some("table2"), "id")),
+                    join_type: JoinType::Inner,
+                },
+                Instruction::Filter {
+                    index: reg2,
+                    expr: eq(col(Some("table1"), "a"), col(Some("table2"), "a")),
+                },
+                source(reg3, "table3"),
+                Instruction::Filter {
+                    index: reg3,
+                    expr: eq(col(Some("table3"), "b"), int(2)),
+                },
+                Instruction::CrossJoin {
+                    input1: reg2,
+                    input2: reg3,
+                    output: reg4,
+                },
+                Instruction::Filter {
+                    index: reg4,
+                    expr: eq(col(None, "c"), int(3)),
+                },
+                Instruction::Empty { index: reg5 },
+                Instruction::Project {
+                    input: reg4,
+                    output: reg5,
+                    expr: Expr::Wildcard,
+                    alias: None,
+                },
+                Instruction::Return { index: reg5 },
+            ]
+        );
+
+        // filtering before an outer join would bring back the filtered rows with NULLs.
+        let parsed = parse(
+            "SELECT * FROM table1 LEFT JOIN table2 ON table1.id = table2.id WHERE table1.a = 1",
+        )
+        .unwrap();
+        let ic = codegen_ast(&parsed[0]).unwrap();
+        let mut pushed = ic.clone();
+        super::push_down_filters(&mut pushed);
+        assert_eq!(pushed, ic);
+
+        // a table on both sides makes the reference ambiguous.
+        let parsed =
+            parse("SELECT * FROM table1, table2, table1 WHERE table1.a = 1 AND table2.b = 2")
+                .unwrap();
+        let mut ic = codegen_ast(&parsed[0]).unwrap();
+        super::push_down_filters(&mut ic);
+        assert_eq!(
+            ic.instrs[..7],
+            [
+                source(reg0, "table1"),
+                source(reg1, "table2"),
+                Instruction::Filter {
+                    index: reg1,
+                    expr: eq(col(Some("table2"), "b"), int(2)),
+                },
+                Instruction::CrossJoin {
+                    input1: reg0,
+                    input2: reg1,
+                    output: reg2,
+                },
+                source(reg3, "table1"),
+                Instruction::CrossJoin {
+                    input1: reg2,
+                    input2: reg3,
+                    output: reg4,
+                },
+                Instruction::Filter {
+                    index: reg4,
+                    expr: eq(col(Some("table1"), "a"), int(1)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn distinct() {
+        check_single_statement("SELECT DISTINCT col1 FROM table1 LIMIT 1", |instrs| {
+            assert_eq!(
+                &instrs[3..],
+                &[
+                    Instruction::Distinct {
+                        index: RegisterIndex::default().next_index(),
+                    },
+                    Instruction::Limit {
+                        index: RegisterIndex::default().next_index(),
+                        limit: 1,
+                    },
+                    Instruction::Return {
+                        index: RegisterIndex::default().next_index(),
+                    },
+                ]
+            )
+        });
+    }
+
+    #[test]
+    fn order_by() {
+        let col = |name: &str| {
+            Expr::ColumnRef(ColumnRef {
+                schema_name: None,
+                table_name: None,
+                col_name: name.into(),
+            })
+        };
+
+        check_single_statement(
+            "SELECT * FROM table1 ORDER BY col1, col2 DESC, col3 ASC",
+            |instrs| {
+                assert_eq!(
+                    instrs[3],
+                    Instruction::Order {
+                        index: RegisterIndex::default().next_index(),
+                        keys: vec![
+                            OrderKey::new(col("col1"), true),
+                            OrderKey::new(col("col2"), false),
+                            OrderKey::new(col("col3"), true)
+                        ],
+                    }
+                )
+            },
+        );
+
+        check_single_statement(
+            "SELECT * FROM table1 ORDER BY col1 NULLS FIRST, col2 DESC NULLS LAST",
+            |instrs| {
+                assert_eq!(
+                    instrs[3],
+                    Instruction::Order {
+                        index: RegisterIndex::default().next_index(),
+                        keys: vec![
+                            OrderKey {
+                                expr: col("col1"),
+                                ascending: true,
+                                nulls_first: true,
+                            },
+                            OrderKey {
+                                expr: col("col2"),
+                                ascending: false,
+                                nulls_first: false,
+                            },
+                        ],
+                    }
+                )
+            },
+        );
+    }
+
+    #[test]
+    fn group_by_aggregate() {
+        for query in [
+            "SELECT COUNT(*) AS c FROM table1 GROUP BY COUNT(*)",
+            "SELECT col1 FROM table1 GROUP BY MAX(col2) + 1",
+        ] {
+            let parsed = parse(query).unwrap();
+            let err = codegen_ast(&parsed[0]).unwrap_err();
+            assert!(matches!(
+                err,
+                CodegenError::Expr(ExprError::Expr {
+                    reason: "Aggregate functions are not allowed in GROUP BY",
+                    ..
+                })
+            ));
+        }
+
+        let parsed = parse("SELECT col1 FROM table1 GROUP BY COUNT(*)").unwrap();
+        assert_eq!(
+            codegen_ast(&parsed[0]).unwrap_err().to_string(),
+            "ExprError: Aggregate functions are not allowed in GROUP BY: COUNT(*)"
+        );
+    }
+
+    #[test]
+    fn drop_table() {
+        check_single_statement("DROP TABLE IF EXISTS schema1.table1, table2", |instrs| {
+            assert_eq!(
+                instrs,
+                &[
+                    Instruction::DropTable {
+                        name: TableRef {
+                            schema_name: Some("schema1".into()),
+                            table_name: "table1".into(),
+                        },
+                        if_exists: true,
+                    },
+                    Instruction::DropTable {
+                        name: TableRef {
+                            schema_name: None,
+                            table_name: "table2".into(),
+                        },
+                        if_exists: true,
+                    },
+                ]
+            )
+        });
+    }
+
+    #[test]
+    fn drop_column() {
+        check_single_statement("ALTER TABLE table1 DROP COLUMN col5", |instrs| {
+            assert_eq!(
+                instrs,
+                &[
+                    Instruction::Source {
+                        index: RegisterIndex::default(),
+                        name: TableRef {
+                            schema_name: None,
+                            table_name: "table1".into(),
+                        },
+                    },
+                    Instruction::RemoveColumn {
+                        index: RegisterIndex::default(),
+                        col_name: "col5".into(),
+                    },
+                ]
+            )
+        });
+    }
+
+    #[test]
+    fn rename_column() {
+        check_single_statement("ALTER TABLE table1 RENAME COLUMN col4 TO col5", |instrs| {
+            assert_eq!(
+                instrs,
+                &[
+                    Instruction::Source {
+                        index: RegisterIndex::default(),
+                        name: TableRef {
+                            schema_name: None,
+                            table_name: "table1".into(),
+                        },
+                    },
+                    Instruction::RenameColumn {
+                        index: RegisterIndex::default(),
+                        old_name: "col4".into(),
+                        new_name: "col5".into(),
+                    },
+                ]
+            )
+        });
+    }
+
+    #[test]
+    fn update() {
+        check_single_statement(
+            "UPDATE table1 SET col2 = 'bar', col3 = col3 + 1 WHERE col1 = 1",
+            |instrs| {
+                let table_reg_index = RegisterIndex::default();
+                let col = |name: &str| {
+                    Expr::ColumnRef(ColumnRef {
+                        schema_name: None,
+                        table_name: None,
+                        col_name: name.into(),
+                    })
+                };
+                assert_eq!(
+                    instrs,
+                    &[
+                        Instruction::Source {
+                            index: table_reg_index,
+                            name: TableRef {
+                                schema_name: None,
+                                table_name: "table1".into(),
+                            },
+                        },
+                        Instruction::Filter {
+                            index: table_reg_index,
+                            expr: Expr::Binary {
+                                left: Box::new(col("col1")),
+                                op: BinOp::Equal,
+                                right: Box::new(Expr::Value(Value::Int64(1))),
+                            },
+                        },
+                        Instruction::Update {
+                            index: table_reg_index,
+                            assignments: vec![
+                                Assignment {
+                                    col: col("col2"),
+                                    expr: Expr::Value(Value::String("bar".to_owned())),
+                                },
+                                Assignment {
+                                    col: col("col3"),
+                                    expr: Expr::Binary {
+                                        left: Box::new(col("col3")),
+                                        op: BinOp::Plus,
+                                        right: Box::new(Expr::Value(Value::Int64(1))),
+                                    },
+                                },
+                            ],
+                        },
+                    ]
+                )
+            },
+        );
+    }
 }
@@ -1,11 +1,16 @@
 //! Columns in a table.
-use sqlparser::ast::{ColumnOptionDef, DataType};
+use sqlparser::ast::{ColumnOption, ColumnOptionDef, DataType};
 
 use crate::BoundedString;
 
 /// A column's metadata.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct Column {
+    #[cfg_attr(
+        feature = "persistence",
+        serde(with = "crate::identifier::serde_bounded_string")
+    )]
     name: BoundedString,
     data_type: DataType,
     options: Vec<ColumnOptionDef>,
@@ -33,6 +38,11 @@ impl Column {
         &self.name
     }
 
+    /// Change the name of the column.
+    pub fn rename(&mut self, new_name: BoundedString) {
+        self.name = new_name;
+    }
+
     /// Data type of the column.
     pub fn data_type(&self) -> &DataType {
         &self.data_type
@@ -52,6 +62,20 @@ impl Column {
     pub fn is_internal(&self) -> bool {
         self.internal
     }
+
+    /// Whether the column is (part of) the table's primary key.
+    pub fn is_primary_key(&self) -> bool {
+        self.options
+            .iter()
+            .any(|o| matches!(o.option, ColumnOption::Unique { is_primary: true }))
+    }
+
+    /// Whether the column is declared `NOT NULL`.
+    pub fn is_not_null(&self) -> bool {
+        self.options
+            .iter()
+            .any(|o| matches!(o.option, ColumnOption::NotNull))
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,6 @@
 //! Databases.
 
-use crate::{schema::Schema, BoundedString};
+use crate::{identifier::identifiers_eq, schema::Schema, BoundedString};
 
 const DEFAULT_SCHEMA_NAME: &str = "main";
 
@@ -8,7 +8,13 @@ const DEFAULT_SCHEMA_NAME: &str = "main";
 ///
 /// Note: you will need a [`VirtualMachine`](`crate::vm::VirtualMachine`)
 /// to execute queries on a database.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct Database {
+    #[cfg_attr(
+        feature = "persistence",
+        serde(with = "crate::identifier::serde_bounded_string")
+    )]
     name: BoundedString,
     schemas: Vec<Schema>,
 }
@@ -51,11 +57,15 @@ impl Database {
     }
 
     pub fn schema_by_name(&self, name: &BoundedString) -> Option<&Schema> {
-        self.schemas().iter().find(|s| s.name() == name)
+        self.schemas()
+            .iter()
+            .find(|s| identifiers_eq(s.name(), name))
     }
 
     pub fn schema_by_name_mut(&mut self, name: &BoundedString) -> Option<&mut Schema> {
-        self.schemas.iter_mut().find(|s| s.name() == name)
+        self.schemas
+            .iter_mut()
+            .find(|s| identifiers_eq(s.name(), name))
     }
 }
 
@@ -0,0 +1,518 @@
+//! Aggregate functions, which are evaluated over a group of rows instead of a single row.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+    sync::Arc,
+};
+
+use crate::{
+    expr::{
+        eval::{EvalOptions, ExprExecError},
+        Expr,
+    },
+    identifier::ColumnRef,
+    table::{Row, RowShared, Table},
+    value::Value,
+};
+
+/// A supported aggregate function.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AggregateFunction {
+    /// `COUNT(*)` counts all rows, `COUNT(expr)` counts the rows where `expr` is not `NULL`.
+    Count,
+    /// Sum of the numeric values.
+    Sum,
+    /// Average of the numeric values, always a floating value.
+    Avg,
+    /// Smallest value, by the ordering of [`Value`].
+    Min,
+    /// Largest value, by the ordering of [`Value`].
+    Max,
+    /// `STRING_AGG(expr, separator)`: the strings joined together, with the separator between
+    /// them.
+    StringAgg,
+    /// The values, including `NULL`s, collected into an array in the order of the group's rows.
+    ArrayAgg,
+}
+
+impl AggregateFunction {
+    /// The aggregate function with the given (case-insensitive) name, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "count" => Some(Self::Count),
+            "sum" => Some(Self::Sum),
+            "avg" => Some(Self::Avg),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            "string_agg" => Some(Self::StringAgg),
+            "array_agg" => Some(Self::ArrayAgg),
+            _ => None,
+        }
+    }
+
+    /// Evaluate the aggregate with the given arguments over the rows of a group.
+    ///
+    /// If `distinct` is set, duplicate values are only taken into account once.
+    fn execute(
+        &self,
+        args: &[Expr],
+        distinct: bool,
+        table: &Table,
+        rows: &[RowShared],
+        options: EvalOptions,
+    ) -> Result<Value, ExprExecError> {
+        let (arg, separator) = match (self, args) {
+            (Self::StringAgg, [arg, separator]) => (arg, Some(separator)),
+            (Self::StringAgg, _) => {
+                return Err(ExprExecError::WrongArgumentCount {
+                    name: self.to_string().as_str().into(),
+                    expected: 2,
+                    got: args.len(),
+                })
+            }
+            (_, [arg]) => (arg, None),
+            _ => {
+                return Err(ExprExecError::WrongArgumentCount {
+                    name: self.to_string().as_str().into(),
+                    expected: 1,
+                    got: args.len(),
+                })
+            }
+        };
+
+        if let (Self::Count, Expr::Wildcard) = (self, arg) {
+            return Ok(Value::Int64(rows.len() as i64));
+        }
+
+        let values = self.kept_values(
+            rows.iter()
+                .map(|row| Expr::execute_with(arg, table, row.clone(), options)),
+            distinct,
+        )?;
+
+        match self {
+            Self::StringAgg => {
+                if values.is_empty() {
+                    return Ok(Value::Null);
+                }
+                // the separator is evaluated once, against the first row of the group.
+                let separator = match Expr::execute_with(
+                    separator.unwrap(),
+                    table,
+                    rows[0].clone(),
+                    options,
+                )? {
+                    Value::String(s) => s,
+                    Value::Null => String::new(),
+                    value => return Err(self.wrong_argument_type("a string separator", value)),
+                };
+                let strings = values
+                    .into_iter()
+                    .map(|value| match value {
+                        Value::String(s) => Ok(s),
+                        value => Err(self.wrong_argument_type("string values", value)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::String(strings.join(&separator)))
+            }
+            _ => self.finish(values),
+        }
+    }
+
+    /// Evaluate an aggregate other than `STRING_AGG` over already computed values, e.g. all the
+    /// values of a column.
+    pub(crate) fn execute_on_values(
+        &self,
+        values: Vec<Value>,
+        distinct: bool,
+    ) -> Result<Value, ExprExecError> {
+        let values = self.kept_values(values.into_iter().map(Ok), distinct)?;
+        self.finish(values)
+    }
+
+    /// The values which are taken into account: all aggregates except `ARRAY_AGG` skip `NULL`s,
+    /// and only the first of equal values is kept if `distinct` is set.
+    fn kept_values(
+        &self,
+        values: impl ExactSizeIterator<Item = Result<Value, ExprExecError>>,
+        distinct: bool,
+    ) -> Result<Vec<Value>, ExprExecError> {
+        kept_values(values, distinct, *self == Self::ArrayAgg)
+    }
+
+    /// The result of an aggregate other than `STRING_AGG` over the values it takes into account.
+    fn finish(&self, values: Vec<Value>) -> Result<Value, ExprExecError> {
+        match self {
+            Self::Count => Ok(Value::Int64(values.len() as i64)),
+            Self::Sum => self.sum(&values),
+            Self::Avg => match self.sum(&values)? {
+                Value::Null => Ok(Value::Null),
+                Value::Int64(sum) => Ok(Value::Float64((sum as f64 / values.len() as f64).into())),
+                Value::Float64(sum) => Ok(Value::Float64(
+                    (sum.into_inner() / values.len() as f64).into(),
+                )),
+                _ => unreachable!("sum is always numeric"),
+            },
+            Self::Min => Ok(values.into_iter().min().unwrap_or(Value::Null)),
+            Self::Max => Ok(values.into_iter().max().unwrap_or(Value::Null)),
+            Self::ArrayAgg if values.is_empty() => Ok(Value::Null),
+            Self::ArrayAgg => Ok(Value::Array(values)),
+            Self::StringAgg => unreachable!("STRING_AGG needs a separator"),
+        }
+    }
+
+    fn wrong_argument_type(&self, expected: &'static str, value: Value) -> ExprExecError {
+        ExprExecError::WrongArgumentType {
+            name: self.to_string().as_str().into(),
+            expected,
+            value,
+        }
+    }
+
+    /// Sum of numeric values. Integers are promoted to floats if any float is involved.
+    fn sum(&self, values: &[Value]) -> Result<Value, ExprExecError> {
+        let mut sum = Value::Null;
+        for value in values {
+            sum = match (sum, value) {
+                (Value::Null, Value::Int64(v)) => Value::Int64(*v),
+                (Value::Null, Value::Float64(v)) => Value::Float64(*v),
+                (Value::Int64(s), Value::Int64(v)) => {
+                    Value::Int64(s.checked_add(*v).ok_or_else(|| {
+                        ExprExecError::AggregateOverflow {
+                            name: self.to_string().as_str().into(),
+                        }
+                    })?)
+                }
+                (Value::Int64(s), Value::Float64(v)) => {
+                    Value::Float64((s as f64 + v.into_inner()).into())
+                }
+                (Value::Float64(s), Value::Int64(v)) => {
+                    Value::Float64((s.into_inner() + *v as f64).into())
+                }
+                (Value::Float64(s), Value::Float64(v)) => Value::Float64(s + v),
+                _ => {
+                    return Err(ExprExecError::NonNumericAggregate {
+                        name: self.to_string().as_str().into(),
+                        value: value.clone(),
+                    })
+                }
+            }
+        }
+        Ok(sum)
+    }
+}
+
+impl Display for AggregateFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Count => "COUNT",
+                Self::Sum => "SUM",
+                Self::Avg => "AVG",
+                Self::Min => "MIN",
+                Self::Max => "MAX",
+                Self::StringAgg => "STRING_AGG",
+                Self::ArrayAgg => "ARRAY_AGG",
+            }
+        )
+    }
+}
+
+/// Aggregate functions registered by the user, by lowercase name.
+pub(crate) type CustomAggregates = HashMap<String, CustomAggregate>;
+
+/// An aggregate function registered by the user, see
+/// [`VirtualMachine::register_aggregate`](`crate::vm::VirtualMachine::register_aggregate`).
+///
+/// The state starts out as the value given by `init`. `accumulate` folds each of the values of the
+/// group into it, and `finalize` turns the final state into the result. As with the built-in
+/// aggregates, `NULL`s are skipped.
+#[derive(Clone)]
+pub(crate) struct CustomAggregate {
+    pub(crate) init: Arc<dyn Fn() -> Value + Send + Sync>,
+    pub(crate) accumulate: Arc<dyn Fn(Value, Value) -> Result<Value, ExprExecError> + Send + Sync>,
+    pub(crate) finalize: Arc<dyn Fn(Value) -> Value + Send + Sync>,
+}
+
+impl CustomAggregate {
+    /// Evaluate the aggregate with the given arguments over the rows of a group.
+    fn execute(
+        &self,
+        name: &str,
+        args: &[Expr],
+        distinct: bool,
+        table: &Table,
+        rows: &[RowShared],
+        options: EvalOptions,
+    ) -> Result<Value, ExprExecError> {
+        let arg = match args {
+            [arg] => arg,
+            _ => {
+                return Err(ExprExecError::WrongArgumentCount {
+                    name: name.into(),
+                    expected: 1,
+                    got: args.len(),
+                })
+            }
+        };
+        let values = kept_values(
+            rows.iter()
+                .map(|row| Expr::execute_with(arg, table, row.clone(), options)),
+            distinct,
+            false,
+        )?;
+        self.finish(values)
+    }
+
+    /// Fold the values into the state and finalize it.
+    fn finish(&self, values: Vec<Value>) -> Result<Value, ExprExecError> {
+        let mut state = (self.init)();
+        for value in values {
+            state = (self.accumulate)(state, value)?;
+        }
+        Ok((self.finalize)(state))
+    }
+}
+
+/// The function of an aggregate call, either built in or registered by the user.
+#[derive(Clone, Copy)]
+enum Aggregate<'a> {
+    Builtin(AggregateFunction),
+    Custom(&'a CustomAggregate),
+}
+
+impl<'a> Aggregate<'a> {
+    /// The aggregate with the given (case-insensitive) name, if there is one. Built-in aggregates
+    /// take precedence over registered ones.
+    fn from_name(name: &str, custom: &'a CustomAggregates) -> Option<Self> {
+        match AggregateFunction::from_name(name) {
+            Some(func) => Some(Self::Builtin(func)),
+            None => custom.get(&name.to_lowercase()).map(Self::Custom),
+        }
+    }
+}
+
+/// The values of a group which are taken into account. `NULL`s are skipped unless `keep_nulls` is
+/// set, and only the first of equal values is kept if `distinct` is set.
+fn kept_values(
+    values: impl ExactSizeIterator<Item = Result<Value, ExprExecError>>,
+    distinct: bool,
+    keep_nulls: bool,
+) -> Result<Vec<Value>, ExprExecError> {
+    let mut kept = Vec::with_capacity(values.len());
+    let mut seen = BTreeSet::new();
+    for value in values {
+        match value? {
+            Value::Null if !keep_nulls => {}
+            value if distinct && !seen.insert(value.clone()) => {}
+            value => kept.push(value),
+        }
+    }
+    Ok(kept)
+}
+
+impl Expr {
+    /// Whether the expression calls an aggregate function anywhere.
+    pub fn contains_aggregate(&self) -> bool {
+        self.contains_aggregate_with(&CustomAggregates::new())
+    }
+
+    /// Whether the expression calls an aggregate function anywhere, including the ones
+    /// registered by the user.
+    pub(crate) fn contains_aggregate_with(&self, custom: &CustomAggregates) -> bool {
+        !self.aggregate_calls(custom).is_empty()
+    }
+
+    /// The first column referenced outside of any aggregate function call, like `col1` in
+    /// `COUNT(*) + col1`.
+    pub(crate) fn column_outside_aggregates(&self) -> Option<ColumnRef> {
+        self.clone()
+            .replace_with(&|expr| match expr {
+                Self::Function { name, .. } if AggregateFunction::from_name(name).is_some() => {
+                    Some(Self::Value(Value::Null))
+                }
+                _ => None,
+            })
+            .column_refs()
+            .first()
+            .map(|col_ref| **col_ref)
+    }
+
+    /// All the outermost aggregate function calls in the expression.
+    fn aggregate_calls(&self, custom: &CustomAggregates) -> Vec<&Expr> {
+        match self {
+            Self::Function { name, .. } if Aggregate::from_name(name, custom).is_some() => {
+                vec![self]
+            }
+            Self::Binary { left, right, .. } => {
+                let mut calls = left.aggregate_calls(custom);
+                calls.extend(right.aggregate_calls(custom));
+                calls
+            }
+            Self::Unary { operand, .. } | Self::Cast { expr: operand, .. } => {
+                operand.aggregate_calls(custom)
+            }
+            Self::Function { args, .. } => args
+                .iter()
+                .flat_map(|a| a.aggregate_calls(custom))
+                .collect(),
+            Self::Any { left, list, .. } => {
+                let mut calls = left.aggregate_calls(custom);
+                calls.extend(list.iter().flat_map(|item| item.aggregate_calls(custom)));
+                calls
+            }
+            Self::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => operand
+                .as_deref()
+                .into_iter()
+                .chain(conditions)
+                .chain(results)
+                .chain(else_result.as_deref())
+                .flat_map(|e| e.aggregate_calls(custom))
+                .collect(),
+            Self::Value(_)
+            | Self::ColumnRef(_)
+            | Self::Wildcard
+            | Self::Default
+            | Self::Placeholder(_) => vec![],
+        }
+    }
+
+    /// Evaluate an expression over a group of rows, with the default [`EvalOptions`].
+    ///
+    /// Aggregate calls are computed over all the rows. The rest of the expression is evaluated
+    /// against the first row of the group, or a row of `NULL`s if the group is empty.
+    pub fn execute_aggregate(
+        expr: &Expr,
+        table: &Table,
+        rows: &[RowShared],
+    ) -> Result<Value, ExprExecError> {
+        Expr::execute_aggregate_with(expr, table, rows, EvalOptions::default())
+    }
+
+    /// Evaluate an expression over a group of rows.
+    ///
+    /// See [`execute_aggregate`](`Self::execute_aggregate`).
+    pub fn execute_aggregate_with(
+        expr: &Expr,
+        table: &Table,
+        rows: &[RowShared],
+        options: EvalOptions,
+    ) -> Result<Value, ExprExecError> {
+        Self::execute_aggregate_custom(expr, table, rows, options, &CustomAggregates::new())
+    }
+
+    /// Evaluate an expression over a group of rows, with the aggregates registered by the user
+    /// available as well.
+    ///
+    /// See [`execute_aggregate`](`Self::execute_aggregate`).
+    pub(crate) fn execute_aggregate_custom(
+        expr: &Expr,
+        table: &Table,
+        rows: &[RowShared],
+        options: EvalOptions,
+        custom: &CustomAggregates,
+    ) -> Result<Value, ExprExecError> {
+        Self::execute_aggregate_calls(
+            expr,
+            table,
+            rows.first().cloned(),
+            options,
+            custom,
+            |func, name, args, distinct| match func {
+                Aggregate::Builtin(func) => func.execute(args, distinct, table, rows, options),
+                Aggregate::Custom(func) => func.execute(name, args, distinct, table, rows, options),
+            },
+        )
+    }
+
+    /// Evaluate an expression over all the rows of a table, as one group.
+    ///
+    /// Aggregates of a single column are computed from the column's values directly, without
+    /// going through the rows, which is faster for tables stored by column.
+    pub(crate) fn execute_aggregate_on_table(
+        expr: &Expr,
+        table: &Table,
+        options: EvalOptions,
+        custom: &CustomAggregates,
+    ) -> Result<Value, ExprExecError> {
+        let mut rows: Option<Vec<RowShared>> = None;
+        Self::execute_aggregate_calls(
+            expr,
+            table,
+            table.rows_shared().next(),
+            options,
+            custom,
+            |func, name, args, distinct| {
+                let func = match func {
+                    Aggregate::Builtin(func) => func,
+                    Aggregate::Custom(func) => {
+                        let rows = rows.get_or_insert_with(|| table.rows_shared().collect());
+                        return func.execute(name, args, distinct, table, rows, options);
+                    }
+                };
+                if let (AggregateFunction::Count, [Expr::Wildcard]) = (func, args) {
+                    return Ok(Value::Int64(table.num_rows() as i64));
+                }
+                let col_index = match args {
+                    [Expr::ColumnRef(col_ref)] if func != AggregateFunction::StringAgg => {
+                        table.resolve_raw_column(col_ref)
+                    }
+                    _ => None,
+                };
+                match col_index {
+                    Some(col_index) => {
+                        func.execute_on_values(table.raw_column_values(col_index), distinct)
+                    }
+                    None => {
+                        let rows = rows.get_or_insert_with(|| table.rows_shared().collect());
+                        func.execute(args, distinct, table, rows, options)
+                    }
+                }
+            },
+        )
+    }
+
+    /// Replace the aggregate calls in the expression with their results, as given by `compute`,
+    /// and evaluate what is left on the first row of the group, if there is one.
+    fn execute_aggregate_calls(
+        expr: &Expr,
+        table: &Table,
+        first_row: Option<RowShared>,
+        options: EvalOptions,
+        custom: &CustomAggregates,
+        mut compute: impl FnMut(Aggregate, &str, &[Expr], bool) -> Result<Value, ExprExecError>,
+    ) -> Result<Value, ExprExecError> {
+        let mut computed = Vec::new();
+        for call in expr.aggregate_calls(custom) {
+            if let Expr::Function {
+                name,
+                args,
+                distinct,
+            } = call
+            {
+                // `aggregate_calls` only returns calls to known aggregates.
+                let func = Aggregate::from_name(name, custom).unwrap();
+                computed.push((call.clone(), compute(func, name, args, *distinct)?));
+            }
+        }
+
+        let replaced = expr.clone().replace_with(&|e| {
+            computed
+                .iter()
+                .find(|(call, _)| call == e)
+                .map(|(_, value)| Expr::Value(value.clone()))
+        });
+
+        let nulls = Row::new(vec![Value::Null; table.columns().count()]);
+        let row = first_row.unwrap_or_else(|| RowShared::from_row(&nulls));
+        Expr::execute_with(&replaced, table, row, options)
+    }
+}
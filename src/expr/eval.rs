@@ -3,14 +3,33 @@
 use std::{error::Error, fmt::Display};
 
 use crate::{
-    expr::{BinOp, Expr, UnOp},
+    expr::{agg::AggregateFunction, func::ScalarFunction, BinOp, Expr, UnOp},
     identifier::BoundedString,
     table::{RowLike, RowShared, Table},
-    value::{Value, ValueBinaryOpError, ValueUnaryOpError},
+    value::{Value, ValueBinaryOpError, ValueCastError, ValueUnaryOpError},
 };
 
+/// Options that change how expressions are evaluated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EvalOptions {
+    /// Whether a boolean compared with an integer is taken as `1` if true and `0` if false.
+    /// Otherwise, such comparisons are an error.
+    pub bool_int_coercion: bool,
+}
+
 impl Expr {
+    /// Evaluate the expression on a row of the table, with the default [`EvalOptions`].
     pub fn execute(expr: &Expr, table: &Table, row: RowShared) -> Result<Value, ExprExecError> {
+        Expr::execute_with(expr, table, row, EvalOptions::default())
+    }
+
+    /// Evaluate the expression on a row of the table.
+    pub fn execute_with(
+        expr: &Expr,
+        table: &Table,
+        row: RowShared,
+        options: EvalOptions,
+    ) -> Result<Value, ExprExecError> {
         match expr {
             Expr::Value(v) => Ok(v.to_owned()),
             Expr::Binary {
@@ -18,11 +37,15 @@ impl Expr {
                 op: BinOp::And,
                 right,
             } => {
-                let left = Expr::execute(left, table, row.clone())?;
-                let right = Expr::execute(right, table, row)?;
+                let left = Expr::execute_with(left, table, row.clone(), options)?;
+                let right = Expr::execute_with(right, table, row, options)?;
 
+                // `NULL` is unknown: false if the other side is false, unknown otherwise.
                 match (&left, &right) {
-                    (Value::Bool(left), Value::Bool(right)) => Ok(Value::Bool(*left && *right)),
+                    (Value::Bool(false), Value::Bool(_) | Value::Null)
+                    | (Value::Bool(_) | Value::Null, Value::Bool(false)) => Ok(Value::Bool(false)),
+                    (Value::Bool(true), Value::Bool(true)) => Ok(Value::Bool(true)),
+                    (Value::Bool(_) | Value::Null, Value::Bool(_) | Value::Null) => Ok(Value::Null),
                     _ => Err(ExprExecError::ValueBinaryOpError(ValueBinaryOpError {
                         operator: BinOp::And,
                         values: (left, right),
@@ -34,11 +57,15 @@ impl Expr {
                 op: BinOp::Or,
                 right,
             } => {
-                let left = Expr::execute(left, table, row.clone())?;
-                let right = Expr::execute(right, table, row)?;
+                let left = Expr::execute_with(left, table, row.clone(), options)?;
+                let right = Expr::execute_with(right, table, row, options)?;
 
+                // `NULL` is unknown: true if the other side is true, unknown otherwise.
                 match (&left, &right) {
-                    (Value::Bool(left), Value::Bool(right)) => Ok(Value::Bool(*left || *right)),
+                    (Value::Bool(true), Value::Bool(_) | Value::Null)
+                    | (Value::Bool(_) | Value::Null, Value::Bool(true)) => Ok(Value::Bool(true)),
+                    (Value::Bool(false), Value::Bool(false)) => Ok(Value::Bool(false)),
+                    (Value::Bool(_) | Value::Null, Value::Bool(_) | Value::Null) => Ok(Value::Null),
                     _ => Err(ExprExecError::ValueBinaryOpError(ValueBinaryOpError {
                         operator: BinOp::Or,
                         values: (left, right),
@@ -46,29 +73,76 @@ impl Expr {
                 }
             }
             Expr::Binary { left, op, right } => {
-                let left = Expr::execute(left, table, row.clone())?;
-                let right = Expr::execute(right, table, row)?;
-                Ok(match op {
-                    BinOp::Plus => left + right,
-                    BinOp::Minus => left - right,
-                    BinOp::Multiply => left * right,
-                    BinOp::Divide => left / right,
-                    BinOp::Modulo => left % right,
-                    BinOp::Equal => Ok(Value::Bool(left == right)),
-                    BinOp::NotEqual => Ok(Value::Bool(left != right)),
-                    BinOp::LessThan => Ok(Value::Bool(left < right)),
-                    BinOp::LessThanOrEqual => Ok(Value::Bool(left <= right)),
-                    BinOp::GreaterThan => Ok(Value::Bool(left > right)),
-                    BinOp::GreaterThanOrEqual => Ok(Value::Bool(left >= right)),
-                    BinOp::Like => left.like(right),
-                    BinOp::ILike => left.ilike(right),
-                    BinOp::And | BinOp::Or => {
-                        unreachable!("AND and OR should be handled separately")
+                let left = Expr::execute_with(left, table, row.clone(), options)?;
+                let right = Expr::execute_with(right, table, row, options)?;
+                binary_op(left, *op, right, options)
+            }
+            Expr::Any { left, op, list } => {
+                // `NULL` if nothing matched but a comparison involved a `NULL`.
+                let left = Expr::execute_with(left, table, row.clone(), options)?;
+                let mut found_null = false;
+                for item in list {
+                    let item = Expr::execute_with(item, table, row.clone(), options)?;
+                    if left == Value::Null || item == Value::Null {
+                        found_null = true;
+                        continue;
                     }
-                }?)
+
+                    match binary_op(left.clone(), *op, item, options)? {
+                        Value::Bool(true) => return Ok(Value::Bool(true)),
+                        Value::Bool(false) => {}
+                        val => {
+                            return Err(ExprExecError::ValueBinaryOpError(ValueBinaryOpError {
+                                operator: *op,
+                                values: (left, val),
+                            }))
+                        }
+                    }
+                }
+
+                if found_null {
+                    Ok(Value::Null)
+                } else {
+                    Ok(Value::Bool(false))
+                }
+            }
+            Expr::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => {
+                let operand = match operand {
+                    Some(operand) => {
+                        Some(Expr::execute_with(operand, table, row.clone(), options)?)
+                    }
+                    None => None,
+                };
+
+                for (condition, result) in conditions.iter().zip(results) {
+                    let condition = Expr::execute_with(condition, table, row.clone(), options)?;
+                    let matched = match (&operand, condition) {
+                        // NULL is never equal to anything.
+                        (Some(Value::Null), _) | (Some(_), Value::Null) => false,
+                        (Some(operand), condition) => *operand == condition,
+                        (None, Value::Bool(b)) => b,
+                        (None, Value::Null) => false,
+                        (None, condition) => {
+                            return Err(ExprExecError::NonBooleanCondition(condition))
+                        }
+                    };
+                    if matched {
+                        return Expr::execute_with(result, table, row, options);
+                    }
+                }
+
+                match else_result {
+                    Some(else_result) => Expr::execute_with(else_result, table, row, options),
+                    None => Ok(Value::Null),
+                }
             }
             Expr::Unary { op, operand } => {
-                let operand = Expr::execute(operand, table, row)?;
+                let operand = Expr::execute_with(operand, table, row, options)?;
                 Ok(match op {
                     UnOp::Plus => Ok(operand),
                     UnOp::Minus => -operand,
@@ -79,11 +153,13 @@ impl Expr {
                     UnOp::IsNotNull => operand.is_not_null(),
                 }?)
             }
-            Expr::Wildcard => Err(ExprExecError::CannotExecute(expr.to_owned())),
+            Expr::Wildcard | Expr::Default => Err(ExprExecError::CannotExecute(expr.to_owned())),
+            Expr::Cast { expr, data_type } => {
+                Ok(Expr::execute_with(expr, table, row, options)?.cast(data_type)?)
+            }
+            Expr::Placeholder(n) => Err(ExprExecError::UnboundPlaceholder(*n)),
             Expr::ColumnRef(col_ref) => {
-                let col_index = if let Some(col_index) =
-                    table.columns().position(|c| c.name() == &col_ref.col_name)
-                {
+                let col_index = if let Some(col_index) = table.resolve_column(col_ref) {
                     col_index
                 } else {
                     // TODO: show table name here too
@@ -100,23 +176,152 @@ impl Expr {
                     });
                 }
             }
-            // TODO: functions
-            Expr::Function { name: _, args: _ } => todo!(),
+            Expr::Function { name, args, .. } => {
+                if AggregateFunction::from_name(name).is_some() {
+                    Err(ExprExecError::MisplacedAggregate(expr.to_owned()))
+                } else if let Some(func) = ScalarFunction::from_name(name) {
+                    func.execute(args, table, row, options)
+                } else {
+                    Err(ExprExecError::NoSuchFunction(*name))
+                }
+            }
         }
     }
 }
 
+/// Apply a binary operator other than `AND` and `OR` to two values.
+///
+/// Comparisons involving `NULL` are `NULL` (unknown), even when both sides are `NULL`, except for
+/// `IS [NOT] DISTINCT FROM`.
+fn binary_op(
+    left: Value,
+    op: BinOp,
+    right: Value,
+    options: EvalOptions,
+) -> Result<Value, ExprExecError> {
+    Ok(match op {
+        BinOp::Equal
+        | BinOp::NotEqual
+        | BinOp::LessThan
+        | BinOp::LessThanOrEqual
+        | BinOp::GreaterThan
+        | BinOp::GreaterThanOrEqual
+        | BinOp::Like
+        | BinOp::ILike
+            if left == Value::Null || right == Value::Null =>
+        {
+            Ok(Value::Null)
+        }
+        BinOp::IsDistinctFrom | BinOp::IsNotDistinctFrom => {
+            let distinct = match (&left, &right) {
+                (Value::Null, Value::Null) => false,
+                (Value::Null, _) | (_, Value::Null) => true,
+                _ => binary_op(left, BinOp::NotEqual, right, options)? == Value::Bool(true),
+            };
+            Ok(Value::Bool(distinct == (op == BinOp::IsDistinctFrom)))
+        }
+        BinOp::Plus => left + right,
+        BinOp::Minus => left - right,
+        BinOp::Multiply => left * right,
+        BinOp::Divide => left / right,
+        BinOp::Modulo => left % right,
+        BinOp::Equal
+        | BinOp::NotEqual
+        | BinOp::LessThan
+        | BinOp::LessThanOrEqual
+        | BinOp::GreaterThan
+        | BinOp::GreaterThanOrEqual
+            if needs_coercion(&left, &right) =>
+        {
+            let (left, right) = left.coerce_for_comparison(right, op)?;
+            return binary_op(left, op, right, options);
+        }
+        BinOp::Equal
+        | BinOp::NotEqual
+        | BinOp::LessThan
+        | BinOp::LessThanOrEqual
+        | BinOp::GreaterThan
+        | BinOp::GreaterThanOrEqual
+            if matches!(
+                (&left, &right),
+                (Value::Bool(_), Value::Int64(_)) | (Value::Int64(_), Value::Bool(_))
+            ) =>
+        {
+            if !options.bool_int_coercion {
+                return Err(ValueBinaryOpError {
+                    operator: op,
+                    values: (left, right),
+                }
+                .into());
+            }
+            let as_int = |value| match value {
+                Value::Bool(b) => Value::Int64(b as i64),
+                value => value,
+            };
+            return binary_op(as_int(left), op, as_int(right), options);
+        }
+        BinOp::Equal => Ok(Value::Bool(left == right)),
+        BinOp::NotEqual => Ok(Value::Bool(left != right)),
+        BinOp::LessThan => Ok(Value::Bool(left < right)),
+        BinOp::LessThanOrEqual => Ok(Value::Bool(left <= right)),
+        BinOp::GreaterThan => Ok(Value::Bool(left > right)),
+        BinOp::GreaterThanOrEqual => Ok(Value::Bool(left >= right)),
+        BinOp::Like => left.like(right),
+        BinOp::ILike => left.ilike(right),
+        BinOp::Xor => left.xor(right),
+        BinOp::And | BinOp::Or => {
+            unreachable!("AND and OR should be handled separately")
+        }
+    }?)
+}
+
+/// Whether two values must be brought to the same type before comparing them.
+fn needs_coercion(left: &Value, right: &Value) -> bool {
+    matches!(
+        (left, right),
+        (Value::Date(_) | Value::Timestamp(_), Value::String(_))
+            | (Value::String(_), Value::Date(_) | Value::Timestamp(_))
+            | (Value::Date(_), Value::Timestamp(_))
+            | (Value::Timestamp(_), Value::Date(_))
+    )
+}
+
 /// Error in execution of an expression.
 #[derive(Debug, PartialEq)]
 pub enum ExprExecError {
     CannotExecute(Expr),
     ValueBinaryOpError(ValueBinaryOpError),
     ValueUnaryOpError(ValueUnaryOpError),
+    ValueCastError(ValueCastError),
     NoSuchColumn(BoundedString),
     CorruptedData {
         col_name: BoundedString,
         table_name: BoundedString,
     },
+    NoSuchFunction(BoundedString),
+    /// A placeholder was not replaced by a parameter value.
+    UnboundPlaceholder(usize),
+    /// An aggregate function was used where a single row is evaluated.
+    MisplacedAggregate(Expr),
+    WrongArgumentCount {
+        name: BoundedString,
+        expected: usize,
+        got: usize,
+    },
+    /// A condition (e.g. of a `CASE`) evaluated to a non-boolean value.
+    NonBooleanCondition(Value),
+    WrongArgumentType {
+        name: BoundedString,
+        expected: &'static str,
+        value: Value,
+    },
+    NonNumericAggregate {
+        name: BoundedString,
+        value: Value,
+    },
+    AggregateOverflow {
+        name: BoundedString,
+    },
 }
 
 impl From<ValueBinaryOpError> for ExprExecError {
@@ -131,12 +336,19 @@ impl From<ValueUnaryOpError> for ExprExecError {
     }
 }
 
+impl From<ValueCastError> for ExprExecError {
+    fn from(e: ValueCastError) -> Self {
+        Self::ValueCastError(e)
+    }
+}
+
 impl Display for ExprExecError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::CannotExecute(expr) => write!(f, "ExprExecError: cannot execute '{}'", expr),
             Self::ValueBinaryOpError(e) => write!(f, "ExprExecError: {}", e),
             Self::ValueUnaryOpError(e) => write!(f, "ExprExecError: {}", e),
+            Self::ValueCastError(e) => write!(f, "ExprExecError: {}", e),
             Self::NoSuchColumn(col_name) => {
                 write!(f, "ExprExecError: no such column '{}'", col_name)
             }
@@ -148,6 +360,48 @@ impl Display for ExprExecError {
                 "ExprExecError: data is corrupted for column '{}' of table '{}'",
                 col_name, table_name
             ),
+            Self::UnboundPlaceholder(n) => {
+                write!(f, "ExprExecError: no value given for parameter ${}", n)
+            }
+            Self::NoSuchFunction(name) => {
+                write!(f, "ExprExecError: no such function '{}'", name)
+            }
+            Self::MisplacedAggregate(expr) => write!(
+                f,
+                "ExprExecError: aggregate '{}' cannot be evaluated on a single row",
+                expr
+            ),
+            Self::WrongArgumentCount {
+                name,
+                expected,
+                got,
+            } => write!(
+                f,
+                "ExprExecError: function '{}' expects {} argument(s), got {}",
+                name, expected, got
+            ),
+            Self::NonBooleanCondition(value) => write!(
+                f,
+                "ExprExecError: condition must be a boolean, got '{}'",
+                value
+            ),
+            Self::WrongArgumentType {
+                name,
+                expected,
+                value,
+            } => write!(
+                f,
+                "ExprExecError: function '{}' expects {}, got '{}'",
+                name, expected, value
+            ),
+            Self::NonNumericAggregate { name, value } => write!(
+                f,
+                "ExprExecError: '{}' only accepts numeric values, got '{}'",
+                name, value
+            ),
+            Self::AggregateOverflow { name } => {
+                write!(f, "ExprExecError: '{}' overflowed", name)
+            }
         }
     }
 }
@@ -167,7 +421,7 @@ mod test {
         column::Column,
         expr::{BinOp, Expr, UnOp},
         table::{Row, Table},
-        value::{Value, ValueBinaryOpError, ValueUnaryOpError},
+        value::{Value, ValueBinaryOpError, ValueCastError, ValueUnaryOpError},
     };
 
     use super::ExprExecError;
@@ -214,6 +468,27 @@ mod test {
         );
     }
 
+    #[test]
+    fn exec_three_valued_logic() {
+        assert_eq!(exec_str_no_context("null = 1"), Ok(Value::Null));
+        assert_eq!(exec_str_no_context("null = null"), Ok(Value::Null));
+        assert_eq!(exec_str_no_context("1 <> null"), Ok(Value::Null));
+        assert_eq!(exec_str_no_context("null < 1"), Ok(Value::Null));
+        assert_eq!(exec_str_no_context("null like 'a%'"), Ok(Value::Null));
+
+        assert_eq!(exec_str_no_context("null or true"), Ok(Value::Bool(true)));
+        assert_eq!(exec_str_no_context("false or null"), Ok(Value::Null));
+        assert_eq!(exec_str_no_context("null or null"), Ok(Value::Null));
+        assert_eq!(
+            exec_str_no_context("null and false"),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(exec_str_no_context("true and null"), Ok(Value::Null));
+        assert_eq!(exec_str_no_context("not null = 1"), Ok(Value::Null));
+
+        assert!(exec_str_no_context("null and 1").is_err());
+    }
+
     #[test]
     fn exec_logical() {
         assert_eq!(exec_str_no_context("true and true"), Ok(Value::Bool(true)));
@@ -271,6 +546,35 @@ mod test {
         );
     }
 
+    #[test]
+    fn exec_xor() {
+        assert_eq!(exec_str_no_context("true xor true"), Ok(Value::Bool(false)));
+        assert_eq!(exec_str_no_context("true xor false"), Ok(Value::Bool(true)));
+        assert_eq!(exec_str_no_context("false xor true"), Ok(Value::Bool(true)));
+        assert_eq!(
+            exec_str_no_context("false xor false"),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(exec_str_no_context("true xor NULL"), Ok(Value::Null));
+        assert_eq!(exec_str_no_context("NULL xor false"), Ok(Value::Null));
+        assert_eq!(
+            exec_str_no_context("true xor 10"),
+            Err(ValueBinaryOpError {
+                operator: BinOp::Xor,
+                values: (Value::Bool(true), Value::Int64(10))
+            }
+            .into())
+        );
+        assert_eq!(
+            exec_str_no_context("NULL xor 'a'"),
+            Err(ValueBinaryOpError {
+                operator: BinOp::Xor,
+                values: (Value::Null, Value::String("a".to_owned()))
+            }
+            .into())
+        );
+    }
+
     #[test]
     fn exec_arithmetic() {
         assert_eq!(exec_str_no_context("1 + 1"), Ok(Value::Int64(2)));
@@ -341,31 +645,90 @@ mod test {
 
     #[test]
     fn exec_pattern_match() {
+        let like = |expr: &str| exec_str_no_context(expr);
+
+        // the whole string must match, with `%` for any sequence and `_` for any character
         assert_eq!(
-            exec_str_no_context("'my name is yoshikage kira' LIKE 'kira'"),
+            like("'my name is yoshikage kira' LIKE '%kira'"),
             Ok(Value::Bool(true))
         );
         assert_eq!(
-            exec_str_no_context("'my name is yoshikage kira' LIKE 'KIRA'"),
+            like("'my name is yoshikage kira' LIKE 'kira'"),
             Ok(Value::Bool(false))
         );
         assert_eq!(
-            exec_str_no_context("'my name is yoshikage kira' LIKE 'kira yoshikage'"),
+            like("'my name is yoshikage kira' LIKE '%KIRA'"),
             Ok(Value::Bool(false))
         );
+        assert_eq!(
+            like("'my name is yoshikage kira' LIKE 'my%is%kira'"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            like("'my name is yoshikage kira' LIKE 'my%kira%is'"),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(like("'kira' LIKE 'k_ra'"), Ok(Value::Bool(true)));
+        assert_eq!(like("'kira' LIKE 'k_a'"), Ok(Value::Bool(false)));
+        assert_eq!(like("'kira' LIKE '____'"), Ok(Value::Bool(true)));
+        assert_eq!(like("'' LIKE '%'"), Ok(Value::Bool(true)));
+        assert_eq!(like("'' LIKE '_'"), Ok(Value::Bool(false)));
+        assert_eq!(like("'aab' LIKE '%ab'"), Ok(Value::Bool(true)));
+        assert_eq!(like("'über' LIKE '_ber'"), Ok(Value::Bool(true)));
+        // a backslash makes a wildcard match itself
+        assert_eq!(like(r"'100%' LIKE '100\%'"), Ok(Value::Bool(true)));
+        assert_eq!(like(r"'1000' LIKE '100\%'"), Ok(Value::Bool(false)));
+        assert_eq!(like(r"'a_c' LIKE 'a\_c'"), Ok(Value::Bool(true)));
+        assert_eq!(like(r"'abc' LIKE 'a\_c'"), Ok(Value::Bool(false)));
 
         assert_eq!(
-            exec_str_no_context("'my name is Yoshikage Kira' ILIKE 'kira'"),
+            like("'my name is Yoshikage Kira' ILIKE '%kira'"),
             Ok(Value::Bool(true))
         );
         assert_eq!(
-            exec_str_no_context("'my name is Yoshikage Kira' ILIKE 'KIRA'"),
+            like("'my name is Yoshikage Kira' ILIKE 'MY NAME%'"),
             Ok(Value::Bool(true))
         );
         assert_eq!(
-            exec_str_no_context("'my name is Yoshikage Kira' ILIKE 'KIRAA'"),
+            like("'my name is Yoshikage Kira' ILIKE '%KIRAA'"),
             Ok(Value::Bool(false))
         );
+
+        assert_eq!(
+            like("'apple' LIKE ANY (ARRAY['a%', 'b%'])"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            like("'banana' LIKE ANY (ARRAY['a%', 'b%'])"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            like("'cherry' LIKE ANY (ARRAY['a%', 'b%'])"),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            like("'crab' LIKE ANY (ARRAY['a%', 'b%'])"),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            like("'Apple' ILIKE ANY (ARRAY['a%', 'b%'])"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            like("'Apple' LIKE ANY (ARRAY['a%', 'b%'])"),
+            Ok(Value::Bool(false))
+        );
+
+        // a match wins over NULLs, otherwise the result is unknown
+        assert_eq!(
+            like("'apple' LIKE ANY (ARRAY[NULL, 'a%'])"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            like("'cherry' LIKE ANY (ARRAY[NULL, 'a%'])"),
+            Ok(Value::Null)
+        );
+        assert_eq!(like("NULL LIKE ANY (ARRAY['a%', 'b%'])"), Ok(Value::Null));
     }
 
     #[test]
@@ -390,6 +753,17 @@ mod test {
             .into())
         );
 
+        assert_eq!(exec_str_no_context("NULL is true"), Ok(Value::Bool(false)));
+        assert_eq!(exec_str_no_context("NULL is false"), Ok(Value::Bool(false)));
+        assert_eq!(
+            exec_str_no_context("unknown is NULL"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            exec_str_no_context("UNKNOWN is true"),
+            Ok(Value::Bool(false))
+        );
+
         assert_eq!(exec_str_no_context("NULL is NULL"), Ok(Value::Bool(true)));
         assert_eq!(
             exec_str_no_context("NULL is not NULL"),
@@ -484,4 +858,296 @@ mod test {
             Ok(Value::Bool(true))
         );
     }
+
+    #[test]
+    fn exec_case() {
+        assert_eq!(
+            exec_str_no_context("CASE WHEN 1 > 0 THEN 'pos' ELSE 'neg' END"),
+            Ok(Value::String("pos".to_owned()))
+        );
+        assert_eq!(
+            exec_str_no_context("CASE WHEN -1 > 0 THEN 'pos' ELSE 'neg' END"),
+            Ok(Value::String("neg".to_owned()))
+        );
+        assert_eq!(
+            exec_str_no_context("CASE WHEN false THEN 1 WHEN NULL THEN 2 END"),
+            Ok(Value::Null)
+        );
+
+        // the first true condition wins and the rest are not evaluated
+        assert_eq!(
+            exec_str_no_context("CASE WHEN true THEN 1 WHEN true THEN 2 END"),
+            Ok(Value::Int64(1))
+        );
+        assert_eq!(
+            exec_str_no_context("CASE WHEN true THEN 1 WHEN 'a' + 1 THEN 2 ELSE 'a' + 1 END"),
+            Ok(Value::Int64(1))
+        );
+
+        assert_eq!(
+            exec_str_no_context("CASE 2 WHEN 1 THEN 'one' WHEN 2 THEN 'two' END"),
+            Ok(Value::String("two".to_owned()))
+        );
+        assert_eq!(
+            exec_str_no_context("CASE 3 WHEN 1 THEN 'one' ELSE 'many' END"),
+            Ok(Value::String("many".to_owned()))
+        );
+        assert_eq!(
+            exec_str_no_context("CASE NULL WHEN NULL THEN 'null' ELSE 'unknown' END"),
+            Ok(Value::String("unknown".to_owned()))
+        );
+
+        assert_eq!(
+            exec_str_no_context("CASE WHEN 1 THEN 'one' END"),
+            Err(ExprExecError::NonBooleanCondition(Value::Int64(1)))
+        );
+    }
+
+    #[test]
+    fn exec_in_list() {
+        assert_eq!(exec_str_no_context("2 IN (1, 2, 3)"), Ok(Value::Bool(true)));
+        assert_eq!(
+            exec_str_no_context("4 IN (1, 2, 3)"),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            exec_str_no_context("4 NOT IN (1, 2, 3)"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            exec_str_no_context("'a' IN ('a', 'b')"),
+            Ok(Value::Bool(true))
+        );
+
+        // a NULL in the list makes a non-match unknown
+        assert_eq!(
+            exec_str_no_context("2 IN (1, NULL, 2)"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(exec_str_no_context("4 IN (1, NULL, 2)"), Ok(Value::Null));
+        assert_eq!(
+            exec_str_no_context("4 NOT IN (1, NULL, 2)"),
+            Ok(Value::Null)
+        );
+        assert_eq!(exec_str_no_context("NULL IN (1, 2)"), Ok(Value::Null));
+    }
+
+    #[test]
+    fn exec_distinct_from() {
+        assert_eq!(
+            exec_str_no_context("NULL IS NOT DISTINCT FROM NULL"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            exec_str_no_context("NULL IS DISTINCT FROM NULL"),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            exec_str_no_context("1 IS DISTINCT FROM NULL"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            exec_str_no_context("NULL IS NOT DISTINCT FROM 1"),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            exec_str_no_context("1 IS NOT DISTINCT FROM 1"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            exec_str_no_context("1 IS DISTINCT FROM 2"),
+            Ok(Value::Bool(true))
+        );
+    }
+
+    #[test]
+    fn exec_string_functions() {
+        let string = |s: &str| Ok(Value::String(s.to_owned()));
+
+        assert_eq!(exec_str_no_context("UPPER('aBc')"), string("ABC"));
+        assert_eq!(exec_str_no_context("lower('aBc')"), string("abc"));
+        assert_eq!(exec_str_no_context("LENGTH('héllo')"), Ok(Value::Int64(5)));
+        assert_eq!(exec_str_no_context("TRIM('  a b  ')"), string("a b"));
+
+        assert_eq!(
+            exec_str_no_context("SUBSTRING('hello', 2, 3)"),
+            string("ell")
+        );
+        assert_eq!(
+            exec_str_no_context("SUBSTRING('hello' FROM 2)"),
+            string("ello")
+        );
+        assert_eq!(
+            exec_str_no_context("SUBSTRING('hello' FROM 2 FOR 10)"),
+            string("ello")
+        );
+        // characters before the start of the string count towards the length
+        assert_eq!(
+            exec_str_no_context("SUBSTRING('hello', 0, 3)"),
+            string("he")
+        );
+        assert_eq!(exec_str_no_context("SUBSTRING('hello', 10, 3)"), string(""));
+
+        assert_eq!(
+            exec_str_no_context("OVERLAY('hello', 'XY', 2)"),
+            string("hXYlo")
+        );
+        assert_eq!(
+            exec_str_no_context("OVERLAY('hello', 'XY', 1, 1)"),
+            string("XYello")
+        );
+        assert_eq!(
+            exec_str_no_context("OVERLAY('hello', 'XY', 4, 10)"),
+            string("helXY")
+        );
+        assert_eq!(
+            exec_str_no_context("OVERLAY('hello', 'XY', 10)"),
+            string("helloXY")
+        );
+        assert_eq!(
+            exec_str_no_context("OVERLAY('hello', 'XY', 2, 0)"),
+            string("hXYello")
+        );
+
+        assert_eq!(exec_str_no_context("UPPER(NULL)"), Ok(Value::Null));
+        assert_eq!(
+            exec_str_no_context("SUBSTRING('hello', NULL, 3)"),
+            Ok(Value::Null)
+        );
+
+        assert_eq!(
+            exec_str_no_context("UPPER(1)"),
+            Err(ExprExecError::WrongArgumentType {
+                name: "UPPER".into(),
+                expected: "a string",
+                value: Value::Int64(1)
+            })
+        );
+        assert_eq!(
+            exec_str_no_context("SUBSTRING('hello', 'a')"),
+            Err(ExprExecError::WrongArgumentType {
+                name: "SUBSTRING".into(),
+                expected: "an integer",
+                value: Value::String("a".to_owned())
+            })
+        );
+        assert_eq!(
+            exec_str_no_context("SUBSTRING('hello', 1, -1)"),
+            Err(ExprExecError::WrongArgumentType {
+                name: "SUBSTRING".into(),
+                expected: "a non-negative length",
+                value: Value::Int64(-1)
+            })
+        );
+        assert_eq!(
+            exec_str_no_context("LENGTH('a', 'b')"),
+            Err(ExprExecError::WrongArgumentCount {
+                name: "LENGTH".into(),
+                expected: 1,
+                got: 2
+            })
+        );
+        assert_eq!(
+            exec_str_no_context("NO_SUCH_FUNCTION('a')"),
+            Err(ExprExecError::NoSuchFunction("NO_SUCH_FUNCTION".into()))
+        );
+    }
+
+    #[test]
+    fn exec_coalesce() {
+        assert_eq!(exec_str_no_context("COALESCE(1)"), Ok(Value::Int64(1)));
+        assert_eq!(
+            exec_str_no_context("COALESCE(NULL, NULL, 'a', 'b')"),
+            Ok(Value::String("a".to_owned()))
+        );
+        assert_eq!(exec_str_no_context("COALESCE(NULL, NULL)"), Ok(Value::Null));
+
+        // stops at the first non-NULL argument
+        assert_eq!(
+            exec_str_no_context("COALESCE(NULL, 2, 'a' + 1)"),
+            Ok(Value::Int64(2))
+        );
+        assert!(exec_str_no_context("COALESCE(NULL, 'a' + 1, 2)").is_err());
+
+        assert_eq!(
+            exec_str_no_context("COALESCE()"),
+            Err(ExprExecError::WrongArgumentCount {
+                name: "COALESCE".into(),
+                expected: 1,
+                got: 0
+            })
+        );
+    }
+
+    #[test]
+    fn exec_extract() {
+        assert_eq!(
+            exec_str_no_context("EXTRACT(YEAR FROM DATE '2023-03-15')"),
+            Ok(Value::Int64(2023))
+        );
+        assert_eq!(
+            exec_str_no_context("EXTRACT(MONTH FROM DATE '2023-03-15')"),
+            Ok(Value::Int64(3))
+        );
+        assert_eq!(
+            exec_str_no_context("EXTRACT(DAY FROM DATE '2023-03-15')"),
+            Ok(Value::Int64(15))
+        );
+        assert_eq!(
+            exec_str_no_context("EXTRACT(HOUR FROM DATE '2023-03-15')"),
+            Ok(Value::Int64(0))
+        );
+        assert_eq!(
+            exec_str_no_context("EXTRACT(MINUTE FROM TIMESTAMP '2023-03-15 10:20:30')"),
+            Ok(Value::Int64(20))
+        );
+        assert_eq!(
+            exec_str_no_context("DATE_PART('day', TIMESTAMP '2023-03-15 10:20:30')"),
+            Ok(Value::Int64(15))
+        );
+        assert_eq!(
+            exec_str_no_context("EXTRACT(YEAR FROM NULL)"),
+            Ok(Value::Null)
+        );
+
+        assert_eq!(
+            exec_str_no_context("EXTRACT(WEEK FROM DATE '2023-03-15')"),
+            Err(ExprExecError::WrongArgumentType {
+                name: "DATE_PART".into(),
+                expected: "one of YEAR, MONTH, DAY, HOUR, MINUTE or SECOND",
+                value: Value::String("WEEK".to_owned())
+            })
+        );
+        assert_eq!(
+            exec_str_no_context("EXTRACT(YEAR FROM 2023)"),
+            Err(ExprExecError::WrongArgumentType {
+                name: "DATE_PART".into(),
+                expected: "a date or a timestamp",
+                value: Value::Int64(2023)
+            })
+        );
+    }
+
+    #[test]
+    fn exec_cast() {
+        assert_eq!(
+            exec_str_no_context("CAST('12' AS INTEGER) + 1"),
+            Ok(Value::Int64(13))
+        );
+        assert_eq!(
+            exec_str_no_context("CAST(1 AS DOUBLE) + 0.5"),
+            Ok(Value::Float64(1.5.into()))
+        );
+        assert_eq!(
+            exec_str_no_context("CAST(1 + 1 AS VARCHAR(10))"),
+            Ok(Value::String("2".to_owned()))
+        );
+        assert_eq!(
+            exec_str_no_context("CAST('abc' AS INTEGER)"),
+            Err(ExprExecError::ValueCastError(ValueCastError {
+                value: Value::String("abc".to_owned()),
+                data_type: DataType::Int(None)
+            }))
+        );
+    }
 }
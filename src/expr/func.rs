@@ -0,0 +1,245 @@
+//! Scalar functions, which are evaluated on a single row.
+
+use std::fmt::Display;
+
+use chrono::{Datelike, NaiveTime, Timelike};
+
+use crate::{
+    expr::{
+        eval::{EvalOptions, ExprExecError},
+        Expr,
+    },
+    table::{RowShared, Table},
+    value::Value,
+};
+
+/// A supported scalar function.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScalarFunction {
+    Upper,
+    Lower,
+    /// Number of characters in a string.
+    Length,
+    /// Remove spaces from both ends of a string.
+    Trim,
+    /// `SUBSTRING(str, start[, len])`, where `start` is 1-based.
+    Substring,
+    /// `OVERLAY(str, replacement, start[, len])`, where `start` is 1-based and
+    /// `len` defaults to the length of `replacement`.
+    Overlay,
+    /// The first of any number of arguments that is not `NULL`.
+    Coalesce,
+    /// `DATE_PART(field, source)`, a field like `'year'` of a date or timestamp. `EXTRACT(field
+    /// FROM source)` is the same.
+    DatePart,
+}
+
+impl ScalarFunction {
+    /// The scalar function with the given (case-insensitive) name, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "upper" => Some(Self::Upper),
+            "lower" => Some(Self::Lower),
+            "length" => Some(Self::Length),
+            "trim" => Some(Self::Trim),
+            "substring" => Some(Self::Substring),
+            "overlay" => Some(Self::Overlay),
+            "coalesce" => Some(Self::Coalesce),
+            "date_part" => Some(Self::DatePart),
+            _ => None,
+        }
+    }
+
+    /// Evaluate the function with the given arguments on a row.
+    pub(super) fn execute(
+        &self,
+        args: &[Expr],
+        table: &Table,
+        row: RowShared,
+        options: EvalOptions,
+    ) -> Result<Value, ExprExecError> {
+        if let Self::Coalesce = self {
+            if args.is_empty() {
+                return Err(ExprExecError::WrongArgumentCount {
+                    name: self.to_string().as_str().into(),
+                    expected: 1,
+                    got: 0,
+                });
+            }
+
+            // arguments after the first non-NULL one are not evaluated.
+            for arg in args {
+                match Expr::execute_with(arg, table, row.clone(), options)? {
+                    Value::Null => {}
+                    value => return Ok(value),
+                }
+            }
+            return Ok(Value::Null);
+        }
+
+        let args = args
+            .iter()
+            .map(|arg| Expr::execute_with(arg, table, row.clone(), options))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.execute_strict(args)
+    }
+
+    /// Apply a function other than `COALESCE` to already evaluated arguments.
+    ///
+    /// If any of the arguments is `NULL`, so is the result.
+    fn execute_strict(&self, args: Vec<Value>) -> Result<Value, ExprExecError> {
+        let (min_args, max_args) = match self {
+            Self::Upper | Self::Lower | Self::Length | Self::Trim => (1, 1),
+            Self::Substring => (2, 3),
+            Self::DatePart => (2, 2),
+            Self::Overlay => (3, 4),
+            Self::Coalesce => unreachable!("COALESCE is not strict"),
+        };
+        if args.len() < min_args || args.len() > max_args {
+            return Err(ExprExecError::WrongArgumentCount {
+                name: self.to_string().as_str().into(),
+                expected: max_args,
+                got: args.len(),
+            });
+        }
+
+        if args.contains(&Value::Null) {
+            return Ok(Value::Null);
+        }
+
+        let mut args = args.into_iter();
+        let string = self.string_arg(args.next().unwrap())?;
+
+        Ok(match self {
+            Self::Upper => Value::String(string.to_uppercase()),
+            Self::Lower => Value::String(string.to_lowercase()),
+            Self::Length => Value::Int64(string.chars().count() as i64),
+            Self::Trim => Value::String(string.trim_matches(' ').to_owned()),
+            Self::Substring => {
+                let start = self.int_arg(args.next().unwrap())?;
+                // characters before the first one still count towards the length.
+                let end = match args.next() {
+                    Some(len) => match self.int_arg(len)? {
+                        len if len < 0 => {
+                            return Err(ExprExecError::WrongArgumentType {
+                                name: self.to_string().as_str().into(),
+                                expected: "a non-negative length",
+                                value: Value::Int64(len),
+                            })
+                        }
+                        len => Some(start.saturating_add(len)),
+                    },
+                    None => None,
+                };
+
+                let skip = (start.max(1) - 1) as usize;
+                let take = match end {
+                    Some(end) => (end.max(1) - 1) as usize,
+                    None => usize::MAX,
+                };
+                Value::String(string.chars().take(take).skip(skip).collect::<String>())
+            }
+            Self::Overlay => {
+                let replacement = self.string_arg(args.next().unwrap())?;
+                let start = self.int_arg(args.next().unwrap())?;
+                let len = match args.next() {
+                    Some(len) => match self.int_arg(len)? {
+                        len if len < 0 => {
+                            return Err(ExprExecError::WrongArgumentType {
+                                name: self.to_string().as_str().into(),
+                                expected: "a non-negative length",
+                                value: Value::Int64(len),
+                            })
+                        }
+                        len => len,
+                    },
+                    None => replacement.chars().count() as i64,
+                };
+
+                // as with `SUBSTRING`, characters before the first one count
+                // towards the length, and positions past the end are clamped.
+                let chars = string.chars().count();
+                let keep = ((start.max(1) - 1) as usize).min(chars);
+                let resume = ((start.saturating_add(len).max(1) - 1) as usize)
+                    .min(chars)
+                    .max(keep);
+
+                let mut result: String = string.chars().take(keep).collect();
+                result.push_str(&replacement);
+                result.extend(string.chars().skip(resume));
+                Value::String(result)
+            }
+            Self::DatePart => {
+                // a date is the same as a timestamp at midnight.
+                let source = match args.next().unwrap() {
+                    Value::Date(d) => d.and_time(NaiveTime::default()),
+                    Value::Timestamp(t) => t,
+                    value => {
+                        return Err(ExprExecError::WrongArgumentType {
+                            name: self.to_string().as_str().into(),
+                            expected: "a date or a timestamp",
+                            value,
+                        })
+                    }
+                };
+                Value::Int64(match string.to_lowercase().as_str() {
+                    "year" => source.year() as i64,
+                    "month" => source.month() as i64,
+                    "day" => source.day() as i64,
+                    "hour" => source.hour() as i64,
+                    "minute" => source.minute() as i64,
+                    "second" => source.second() as i64,
+                    _ => {
+                        return Err(ExprExecError::WrongArgumentType {
+                            name: self.to_string().as_str().into(),
+                            expected: "one of YEAR, MONTH, DAY, HOUR, MINUTE or SECOND",
+                            value: Value::String(string),
+                        })
+                    }
+                })
+            }
+            Self::Coalesce => unreachable!("COALESCE is not strict"),
+        })
+    }
+
+    fn string_arg(&self, value: Value) -> Result<String, ExprExecError> {
+        match value {
+            Value::String(s) => Ok(s),
+            value => Err(ExprExecError::WrongArgumentType {
+                name: self.to_string().as_str().into(),
+                expected: "a string",
+                value,
+            }),
+        }
+    }
+
+    fn int_arg(&self, value: Value) -> Result<i64, ExprExecError> {
+        match value {
+            Value::Int64(i) => Ok(i),
+            value => Err(ExprExecError::WrongArgumentType {
+                name: self.to_string().as_str().into(),
+                expected: "an integer",
+                value,
+            }),
+        }
+    }
+}
+
+impl Display for ScalarFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Upper => "UPPER",
+                Self::Lower => "LOWER",
+                Self::Length => "LENGTH",
+                Self::Trim => "TRIM",
+                Self::Substring => "SUBSTRING",
+                Self::Overlay => "OVERLAY",
+                Self::Coalesce => "COALESCE",
+                Self::DatePart => "DATE_PART",
+            }
+        )
+    }
+}
@@ -2,15 +2,18 @@
 
 use std::{error::Error, fmt::Display};
 
-use sqlparser::ast;
+use sqlparser::ast::{self, DataType};
 
 use crate::{
+    expr::func::ScalarFunction,
     identifier::{ColumnRef, IdentifierError},
     value::{Value, ValueError},
     BoundedString,
 };
 
+pub mod agg;
 pub mod eval;
+pub mod func;
 
 /// An expression
 #[derive(Debug, Clone, PartialEq)]
@@ -30,6 +33,34 @@ pub enum Expr {
     Function {
         name: BoundedString,
         args: Vec<Expr>,
+        /// Whether an aggregate only considers distinct values, as in `COUNT(DISTINCT col)`.
+        distinct: bool,
+    },
+    /// `left op ANY (list)`: whether `left op item` holds for any item in the list.
+    Any {
+        left: Box<Expr>,
+        op: BinOp,
+        list: Vec<Expr>,
+    },
+    /// `CAST(expr AS data_type)`.
+    Cast {
+        expr: Box<Expr>,
+        data_type: DataType,
+    },
+    /// The `DEFAULT` keyword, standing for the default value of a column in `UPDATE ... SET`.
+    Default,
+    /// A numbered parameter placeholder, `$1`, `$2`, etc. Replaced by a value before execution.
+    ///
+    /// `?` placeholders are numbered in order of appearance by the parser.
+    Placeholder(usize),
+    /// `CASE [operand] WHEN condition THEN result ... [ELSE else_result] END`.
+    ///
+    /// With an operand, each condition is a value compared to it for equality.
+    Case {
+        operand: Option<Box<Expr>>,
+        conditions: Vec<Expr>,
+        results: Vec<Expr>,
+        else_result: Option<Box<Expr>>,
     },
 }
 
@@ -39,17 +70,173 @@ impl Display for Expr {
             Self::Value(v) => write!(f, "{}", v),
             Self::ColumnRef(c) => write!(f, "column '{}'", c),
             Self::Wildcard => write!(f, "*"),
+            Self::Default => write!(f, "DEFAULT"),
+            Self::Placeholder(n) => write!(f, "${}", n),
+            Self::Cast { expr, data_type } => write!(f, "CAST({} AS {})", expr, data_type),
             Self::Binary { left, op, right } => write!(f, "({} {} {})", left, op, right),
             Self::Unary { op, operand } => write!(f, "{}{}", op, operand),
-            Self::Function { name, args } => write!(
+            Self::Function {
+                name,
+                args,
+                distinct,
+            } => write!(
                 f,
-                "{}({})",
+                "{}({}{})",
                 name,
+                if *distinct { "DISTINCT " } else { "" },
                 args.iter()
                     .map(|a| a.to_string())
                     .collect::<Vec<String>>()
                     .join(", ")
             ),
+            Self::Any { left, op, list } => write!(
+                f,
+                "({} {} ANY({}))",
+                left,
+                op,
+                list.iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => {
+                write!(f, "CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", operand)?;
+                }
+                for (condition, result) in conditions.iter().zip(results) {
+                    write!(f, " WHEN {} THEN {}", condition, result)?;
+                }
+                if let Some(else_result) = else_result {
+                    write!(f, " ELSE {}", else_result)?;
+                }
+                write!(f, " END")
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// Rewrite the expression tree top-down.
+    ///
+    /// `f` is called on every sub-expression, starting at the root. If it returns a replacement,
+    /// that replacement is used as is and its children are not visited.
+    pub(crate) fn replace_with(self, f: &impl Fn(&Expr) -> Option<Expr>) -> Expr {
+        if let Some(replacement) = f(&self) {
+            return replacement;
+        }
+
+        match self {
+            Self::Binary { left, op, right } => Self::Binary {
+                left: Box::new(left.replace_with(f)),
+                op,
+                right: Box::new(right.replace_with(f)),
+            },
+            Self::Unary { op, operand } => Self::Unary {
+                op,
+                operand: Box::new(operand.replace_with(f)),
+            },
+            Self::Function {
+                name,
+                args,
+                distinct,
+            } => Self::Function {
+                name,
+                args: args.into_iter().map(|arg| arg.replace_with(f)).collect(),
+                distinct,
+            },
+            Self::Any { left, op, list } => Self::Any {
+                left: Box::new(left.replace_with(f)),
+                op,
+                list: list.into_iter().map(|item| item.replace_with(f)).collect(),
+            },
+            Self::Cast { expr, data_type } => Self::Cast {
+                expr: Box::new(expr.replace_with(f)),
+                data_type,
+            },
+            Self::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => Self::Case {
+                operand: operand.map(|operand| Box::new(operand.replace_with(f))),
+                conditions: conditions.into_iter().map(|c| c.replace_with(f)).collect(),
+                results: results.into_iter().map(|r| r.replace_with(f)).collect(),
+                else_result: else_result.map(|else_result| Box::new(else_result.replace_with(f))),
+            },
+            Self::Value(_)
+            | Self::ColumnRef(_)
+            | Self::Wildcard
+            | Self::Default
+            | Self::Placeholder(_) => self,
+        }
+    }
+
+    /// All the columns referenced in the expression.
+    pub(crate) fn column_refs(&self) -> Vec<&ColumnRef> {
+        match self {
+            Self::ColumnRef(col_ref) => vec![col_ref],
+            Self::Binary { left, right, .. } => {
+                let mut refs = left.column_refs();
+                refs.extend(right.column_refs());
+                refs
+            }
+            Self::Unary { operand, .. } | Self::Cast { expr: operand, .. } => operand.column_refs(),
+            Self::Function { args, .. } => args.iter().flat_map(|a| a.column_refs()).collect(),
+            Self::Any { left, list, .. } => {
+                let mut refs = left.column_refs();
+                refs.extend(list.iter().flat_map(|item| item.column_refs()));
+                refs
+            }
+            Self::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => operand
+                .as_deref()
+                .into_iter()
+                .chain(conditions)
+                .chain(results)
+                .chain(else_result.as_deref())
+                .flat_map(|e| e.column_refs())
+                .collect(),
+            Self::Value(_) | Self::Wildcard | Self::Default | Self::Placeholder(_) => vec![],
+        }
+    }
+
+    /// Number of parameters needed by the expression, i.e. its highest placeholder number.
+    pub(crate) fn num_params(&self) -> usize {
+        match self {
+            Self::Placeholder(n) => *n,
+            Self::Binary { left, right, .. } => left.num_params().max(right.num_params()),
+            Self::Unary { operand, .. } | Self::Cast { expr: operand, .. } => operand.num_params(),
+            Self::Function { args, .. } => args.iter().map(|a| a.num_params()).max().unwrap_or(0),
+            Self::Any { left, list, .. } => list
+                .iter()
+                .map(|item| item.num_params())
+                .fold(left.num_params(), usize::max),
+            Self::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => operand
+                .as_deref()
+                .into_iter()
+                .chain(conditions)
+                .chain(results)
+                .chain(else_result.as_deref())
+                .map(|e| e.num_params())
+                .max()
+                .unwrap_or(0),
+            Self::Value(_) | Self::ColumnRef(_) | Self::Wildcard | Self::Default => 0,
         }
     }
 }
@@ -72,6 +259,11 @@ pub enum BinOp {
     ILike,
     And,
     Or,
+    Xor,
+    /// Like `!=`, except that `NULL` is only distinct from non-`NULL` values.
+    IsDistinctFrom,
+    /// Like `=`, except that `NULL` is not distinct from `NULL`.
+    IsNotDistinctFrom,
 }
 
 impl Display for BinOp {
@@ -95,6 +287,9 @@ impl Display for BinOp {
                 BinOp::ILike => "ILIKE",
                 BinOp::And => "AND",
                 BinOp::Or => "OR",
+                BinOp::Xor => "XOR",
+                BinOp::IsDistinctFrom => "IS DISTINCT FROM",
+                BinOp::IsNotDistinctFrom => "IS NOT DISTINCT FROM",
             }
         )
     }
@@ -134,6 +329,17 @@ impl TryFrom<ast::Expr> for Expr {
     type Error = ExprError;
     fn try_from(expr_ast: ast::Expr) -> Result<Self, Self::Error> {
         match expr_ast {
+            // `UNKNOWN` is the null boolean. It can still be used as a column name by quoting it.
+            ast::Expr::Identifier(i)
+                if i.quote_style.is_none() && i.value.eq_ignore_ascii_case("unknown") =>
+            {
+                Ok(Expr::Value(Value::Null))
+            }
+            ast::Expr::Identifier(i)
+                if i.quote_style.is_none() && i.value.eq_ignore_ascii_case("default") =>
+            {
+                Ok(Expr::Default)
+            }
             ast::Expr::Identifier(i) => Ok(Expr::ColumnRef(vec![i].try_into()?)),
             ast::Expr::CompoundIdentifier(i) => Ok(Expr::ColumnRef(i.try_into()?)),
             ast::Expr::IsFalse(e) => Ok(Expr::Unary {
@@ -152,6 +358,16 @@ impl TryFrom<ast::Expr> for Expr {
                 op: UnOp::IsNotNull,
                 operand: Box::new((*e).try_into()?),
             }),
+            ast::Expr::IsDistinctFrom(left, right) => Ok(Expr::Binary {
+                left: Box::new((*left).try_into()?),
+                op: BinOp::IsDistinctFrom,
+                right: Box::new((*right).try_into()?),
+            }),
+            ast::Expr::IsNotDistinctFrom(left, right) => Ok(Expr::Binary {
+                left: Box::new((*left).try_into()?),
+                op: BinOp::IsNotDistinctFrom,
+                right: Box::new((*right).try_into()?),
+            }),
             ast::Expr::Between {
                 expr,
                 negated,
@@ -183,16 +399,147 @@ impl TryFrom<ast::Expr> for Expr {
                     Ok(between)
                 }
             }
-            ast::Expr::BinaryOp { left, op, right } => Ok(Expr::Binary {
-                left: Box::new((*left).try_into()?),
-                op: op.try_into()?,
-                right: Box::new((*right).try_into()?),
+            ast::Expr::BinaryOp { left, op, right } => match *right {
+                ast::Expr::AnyOp(list) => Ok(Expr::Any {
+                    left: Box::new((*left).try_into()?),
+                    op: op.try_into()?,
+                    list: match *list {
+                        ast::Expr::Array(ast::Array { elem, .. }) => elem,
+                        ast::Expr::Tuple(elem) => elem,
+                        ast::Expr::Nested(e) => vec![*e],
+                        list => {
+                            return Err(ExprError::Expr {
+                                reason: "ANY is only supported with a list of values",
+                                expr: list,
+                            })
+                        }
+                    }
+                    .into_iter()
+                    .map(|item| item.try_into())
+                    .collect::<Result<_, _>>()?,
+                }),
+                right => Ok(Expr::Binary {
+                    left: Box::new((*left).try_into()?),
+                    op: op.try_into()?,
+                    right: Box::new(right.try_into()?),
+                }),
+            },
+            ast::Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                // `x IN (a, b)` is the same as `x = ANY(a, b)`, including when NULLs are involved.
+                let any = Expr::Any {
+                    left: Box::new((*expr).try_into()?),
+                    op: BinOp::Equal,
+                    list: list
+                        .into_iter()
+                        .map(|item| item.try_into())
+                        .collect::<Result<_, _>>()?,
+                };
+                if negated {
+                    Ok(Expr::Unary {
+                        op: UnOp::Not,
+                        operand: Box::new(any),
+                    })
+                } else {
+                    Ok(any)
+                }
+            }
+            ast::Expr::Cast { expr, data_type } => Ok(Expr::Cast {
+                expr: Box::new((*expr).try_into()?),
+                data_type,
+            }),
+            ast::Expr::Substring {
+                expr,
+                substring_from,
+                substring_for,
+            } => {
+                let mut args = vec![(*expr).try_into()?];
+                args.push(match substring_from {
+                    Some(from) => (*from).try_into()?,
+                    None => Expr::Value(Value::Int64(1)),
+                });
+                if let Some(len) = substring_for {
+                    args.push((*len).try_into()?);
+                }
+                Ok(Expr::Function {
+                    name: "SUBSTRING".into(),
+                    args,
+                    distinct: false,
+                })
+            }
+            ast::Expr::Extract { field, expr } => Ok(Expr::Function {
+                name: "DATE_PART".into(),
+                args: vec![
+                    Expr::Value(Value::String(field.to_string())),
+                    (*expr).try_into()?,
+                ],
+                distinct: false,
+            }),
+            ast::Expr::Trim {
+                expr,
+                trim_where: None,
+            } => Ok(Expr::Function {
+                name: "TRIM".into(),
+                args: vec![(*expr).try_into()?],
+                distinct: false,
+            }),
+            ast::Expr::Trim { .. } => Err(ExprError::Expr {
+                reason: "Only TRIM of spaces from both ends is supported",
+                expr: expr_ast,
             }),
+            ast::Expr::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => Ok(Expr::Case {
+                operand: match operand {
+                    Some(operand) => Some(Box::new((*operand).try_into()?)),
+                    None => None,
+                },
+                conditions: conditions
+                    .into_iter()
+                    .map(|c| c.try_into())
+                    .collect::<Result<_, _>>()?,
+                results: results
+                    .into_iter()
+                    .map(|r| r.try_into())
+                    .collect::<Result<_, _>>()?,
+                else_result: match else_result {
+                    Some(else_result) => Some(Box::new((*else_result).try_into()?)),
+                    None => None,
+                },
+            }),
+            ast::Expr::TypedString { data_type, value } => {
+                Ok(Expr::Value(Value::from_typed_string(&data_type, value)?))
+            }
             ast::Expr::UnaryOp { op, expr } => Ok(Expr::Unary {
                 op: op.try_into()?,
                 operand: Box::new((*expr).try_into()?),
             }),
+            ast::Expr::Value(ast::Value::Placeholder(ref p)) => {
+                match p.strip_prefix('$').and_then(|n| n.parse::<usize>().ok()) {
+                    Some(n) if n > 0 => Ok(Expr::Placeholder(n)),
+                    _ => Err(ExprError::Expr {
+                        reason: "Only numbered placeholders like $1 are supported",
+                        expr: expr_ast.clone(),
+                    }),
+                }
+            }
             ast::Expr::Value(v) => Ok(Expr::Value(v.try_into()?)),
+            // aggregates registered with the VM are not known here, so only scalar functions are
+            // ruled out.
+            ast::Expr::Function(ref f)
+                if f.distinct && ScalarFunction::from_name(&f.name.to_string()).is_some() =>
+            {
+                Err(ExprError::Expr {
+                    reason: "DISTINCT is only supported in aggregate functions",
+                    expr: expr_ast.clone(),
+                })
+            }
             ast::Expr::Function(ref f) => Ok(Expr::Function {
                 name: f.name.to_string().as_str().into(),
                 args: f
@@ -213,6 +560,7 @@ impl TryFrom<ast::Expr> for Expr {
                         }),
                     })
                     .collect::<Result<Vec<_>, _>>()?,
+                distinct: f.distinct,
             }),
             _ => Err(ExprError::Expr {
                 reason: "Unsupported expression",
@@ -241,7 +589,7 @@ impl TryFrom<ast::BinaryOperator> for BinOp {
             ast::BinaryOperator::ILike => Ok(BinOp::ILike),
             ast::BinaryOperator::And => Ok(BinOp::And),
             ast::BinaryOperator::Or => Ok(BinOp::Or),
-            // TODO: xor?
+            ast::BinaryOperator::Xor => Ok(BinOp::Xor),
             _ => Err(ExprError::Binary {
                 reason: "Unknown binary operator",
                 op,
@@ -435,7 +783,8 @@ mod tests {
                     schema_name: None,
                     table_name: None,
                     col_name: "col1".into()
-                })]
+                })],
+                distinct: false
             })
         );
 
@@ -451,7 +800,8 @@ mod tests {
                     }),
                     Expr::Value(Value::Int64(1)),
                     Expr::Value(Value::String("abc".to_owned()))
-                ]
+                ],
+                distinct: false
             })
         );
 
@@ -459,7 +809,8 @@ mod tests {
             parse_expr("COUNT(*)").try_into(),
             Ok(Expr::Function {
                 name: "COUNT".into(),
-                args: vec![Expr::Wildcard]
+                args: vec![Expr::Wildcard],
+                distinct: false
             })
         );
     }
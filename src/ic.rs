@@ -7,12 +7,13 @@ use sqlparser::ast::{ColumnOptionDef, DataType};
 use crate::{
     expr::Expr,
     identifier::{SchemaRef, TableRef},
+    table::StorageLayout,
     value::Value,
     vm::RegisterIndex,
     BoundedString,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// The intermediate representation of a query. Made of up [`Instruction`]s.
 pub struct IntermediateCode {
     pub instrs: Vec<Instruction>,
@@ -46,6 +47,10 @@ pub enum Instruction {
     /// Filter the [`Register::TableRef`](`crate::vm::Register::TableRef`) at `index` using the given expression.
     ///
     /// This represents a `WHERE` clause of a `SELECT` statement in SQL.
+    ///
+    /// On a [`Register::GroupedTable`](`crate::vm::Register::GroupedTable`), whole groups are kept
+    /// or dropped instead, with any aggregates in the expression computed over each group. This
+    /// represents a `HAVING` clause.
     Filter { index: RegisterIndex, expr: Expr },
 
     /// Create a projection of the columns of the [`Register::TableRef`](`crate::vm::Register::TableRef`) at `input`.
@@ -71,17 +76,37 @@ pub enum Instruction {
     /// Group the [`Register::TableRef`](`crate::vm::Register::TableRef`) at `index` by the given expression.
     ///
     /// This will result in a [`Register::GroupedTable`](`crate::vm::Register::GroupedTable`) being stored at the `index` register.
+    /// Groups are in order of their first appearance in the table. Grouping an already grouped
+    /// table is not supported.
     ///
     /// Must be added before any projections so as to catch errors in column selections.
     GroupBy { index: RegisterIndex, expr: Expr },
 
-    /// Order the [`Register::TableRef`](`crate::vm::Register::TableRef`) at `index` by the given expression.
+    /// Put all the rows of the [`Register::TableRef`](`crate::vm::Register::TableRef`) at `index`
+    /// in a single group, even if there are none.
+    ///
+    /// This is used for a `HAVING` clause without a `GROUP BY`, so that the one summary row of
+    /// the aggregates can be filtered like a group.
+    GroupAll { index: RegisterIndex },
+
+    /// Order the [`Register::TableRef`](`crate::vm::Register::TableRef`) at `index` by the given keys.
     ///
-    /// This represents the `ORDER BY` clause in SQL.
+    /// This represents the `ORDER BY` clause in SQL. Rows are compared by the first key, then by
+    /// the next one when they are equal and so on. Rows equal by all the keys keep their order.
+    ///
+    /// Ordering happens after the projection, so names are resolved against the output columns
+    /// only: an alias takes precedence over a source column of the same name. A name shared by
+    /// more than one output column is an error.
     Order {
         index: RegisterIndex,
-        expr: Expr,
-        ascending: bool,
+        #[display(
+            "[{}]",
+            keys.iter()
+                .map(|key| key.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )]
+        keys: Vec<OrderKey>,
     },
 
     /// Truncate the [`Register::TableRef`](`crate::vm::Register::TableRef`) at `index` to the given number of rows.
@@ -89,6 +114,18 @@ pub enum Instruction {
     /// This represents the `LIMIT` clause in SQL.
     Limit { index: RegisterIndex, limit: u64 },
 
+    /// Remove duplicate rows from the [`Register::TableRef`](`crate::vm::Register::TableRef`) at `index`.
+    ///
+    /// This represents `SELECT DISTINCT` in SQL. Rows are compared by all of their values, with
+    /// `NULL`s equal to each other. The first of each set of duplicates is kept, in its place.
+    Distinct { index: RegisterIndex },
+
+    /// Drop the given number of rows from the start of the [`Register::TableRef`](`crate::vm::Register::TableRef`) at `index`.
+    ///
+    /// This represents the `OFFSET` clause in SQL. Skipping more rows than there are leaves the
+    /// table empty.
+    Offset { index: RegisterIndex, offset: u64 },
+
     /// Return from register at `index`.
     ///
     /// Some values stored in a register may be intermediate values and cannot be returned.
@@ -104,6 +141,21 @@ pub enum Instruction {
         exists_ok: bool,
     },
 
+    /// Start a transaction. Does nothing if there is one in progress already.
+    ///
+    /// This represents a `BEGIN` or `START TRANSACTION` statement.
+    Begin,
+
+    /// Make the changes of the current transaction permanent.
+    ///
+    /// This represents a `COMMIT` statement. Does nothing if there is no transaction.
+    Commit,
+
+    /// Undo the changes of the current transaction.
+    ///
+    /// This represents a `ROLLBACK` statement. Does nothing if there is no transaction.
+    Rollback,
+
     /// Start defining a  new column and store the temporary metadata in register `index`.
     ///
     /// The value stored in the register will be of type [`Register::Column`](`crate::vm::Register::Column`).
@@ -136,10 +188,26 @@ pub enum Instruction {
         name: TableRef,
         /// If `true`, the table is not created if it exists and no error is returned.
         exists_ok: bool,
+        /// How the table's data is laid out in memory, from `WITH (storage = '...')`.
+        layout: StorageLayout,
     },
 
-    /// Drop the table referenced by the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `index`.
-    DropTable { index: RegisterIndex },
+    /// Drop the table with the given name, removing it from its schema.
+    DropTable {
+        name: TableRef,
+        /// If `true`, no error is returned if the table does not exist.
+        if_exists: bool,
+    },
+
+    /// Index a column of the table with the given name, to speed up looking up rows by the
+    /// column's value.
+    ///
+    /// This represents a `CREATE INDEX` statement on a single column. Indexing a column that is
+    /// indexed already does nothing.
+    NewIndex {
+        table: TableRef,
+        col_name: BoundedString,
+    },
 
     /// Remove the given column from the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `index`.
     RemoveColumn {
@@ -189,43 +257,369 @@ pub enum Instruction {
 
     /// Update values of the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `index`.
     ///
-    /// This represents an `UPDATE` statement.
+    /// This represents an `UPDATE` statement. For each assignment, `expr` is evaluated for each
+    /// row and stored in column `col`. If the table was [filtered](`Self::Filter`), only the rows
+    /// that are left are updated, in the original table as well.
+    ///
+    /// All the assignments see the values from before the update, and nothing is written unless
+    /// every new value fits its column.
     Update {
         index: RegisterIndex,
-        col: Expr,
-        expr: Expr,
+        #[display(
+            "[{}]",
+            assignments.iter()
+                .map(|assignment| assignment.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )]
+        assignments: Vec<Assignment>,
     },
 
     /// Perform a union of the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `input1` and the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `input2`.
     ///
     /// The output is stored as a [`Register::TableRef`](`crate::vm::Register::TableRef`) in register
     /// `output`.
+    ///
+    /// Both inputs must have the same number of columns, with the same types. The column names are
+    /// taken from `input1`. Duplicate rows are removed unless `all` is set (`UNION ALL`).
     Union {
         input1: RegisterIndex,
         input2: RegisterIndex,
         output: RegisterIndex,
+        all: bool,
     },
 
     /// Perform a cartesian join of the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `input1` and the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `input2`.
     ///
     /// The output is stored as a [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `output`.
+    ///
+    /// The output has the columns of `input1` followed by those of `input2`. Columns with the same
+    /// name in both tables are renamed to `table.col`.
     CrossJoin {
         input1: RegisterIndex,
         input2: RegisterIndex,
         output: RegisterIndex,
     },
 
+    /// Perform a join of the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `input1` and the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `input2`.
+    ///
+    /// The output is stored as a [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `output`.
+    ///
+    /// Only the pairs of rows for which `on` is true are kept. The columns are the same as those
+    /// of a [`CrossJoin`](`Self::CrossJoin`), and `on` is evaluated against them. See
+    /// [`JoinType`] for what happens to rows without a match.
+    Join {
+        input1: RegisterIndex,
+        input2: RegisterIndex,
+        output: RegisterIndex,
+        on: Expr,
+        join_type: JoinType,
+    },
+
     /// Perform a natural join of the [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `input1` and the [`Register::TableRef](`crate::vm::Register::TableRef) in register `input2`.
     ///
     /// The output is stored as a [`Register::TableRef`](`crate::vm::Register::TableRef`) in register `output`.
     ///
-    /// Note: this is both a left and a right join i.e., there will be `NULL`s where the common
-    /// columns do not match. The result must be filtered at a later stage.
+    /// Only rows where all the columns with the same name in both tables are equal are kept.
+    /// `NULL`s never match. The shared columns appear once in the output: the columns of `input1`
+    /// are followed by the rest of the columns of `input2`. Without any shared columns, this is
+    /// the same as a [`CrossJoin`](`Self::CrossJoin`).
     NaturalJoin {
         input1: RegisterIndex,
         input2: RegisterIndex,
         output: RegisterIndex,
     },
+
+    /// Store a new table in register `index` which describes the given code instead of running
+    /// it, with one row for each of its instructions.
+    ///
+    /// This represents an `EXPLAIN` statement.
+    Explain {
+        index: RegisterIndex,
+        #[display("{} instructions", code.instrs.len())]
+        code: IntermediateCode,
+    },
+}
+
+/// Which rows without a match are kept by an [`Instruction::Join`].
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinType {
+    /// Rows without a match are dropped.
+    Inner,
+    /// Rows of the first table without a match are kept, with `NULL`s for the columns of the
+    /// second table.
+    Left,
+    /// Rows of the second table without a match are kept, with `NULL`s for the columns of the
+    /// first table.
+    Right,
+}
+
+/// A key to sort rows by in an [`Instruction::Order`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderKey {
+    pub expr: Expr,
+    pub ascending: bool,
+    /// Whether `NULL`s come before all other values, regardless of `ascending`.
+    pub nulls_first: bool,
+}
+
+impl Display for OrderKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} NULLS {}",
+            self.expr,
+            if self.ascending { "ASC" } else { "DESC" },
+            if self.nulls_first { "FIRST" } else { "LAST" }
+        )
+    }
+}
+
+impl OrderKey {
+    /// A key with SQL's default placement of `NULL`s, i.e. as if `NULL` were larger than any
+    /// other value: last when ascending, first when descending.
+    pub fn new(expr: Expr, ascending: bool) -> Self {
+        Self {
+            expr,
+            ascending,
+            nulls_first: !ascending,
+        }
+    }
+}
+
+/// A `col = expr` assignment of an [`Instruction::Update`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub col: Expr,
+    pub expr: Expr,
+}
+
+impl Display for Assignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.col, self.expr)
+    }
+}
+
+impl IntermediateCode {
+    /// Number of parameters needed by the code, i.e. its highest placeholder number.
+    pub fn num_params(&self) -> usize {
+        self.instrs
+            .iter()
+            .flat_map(|instr| instr.exprs())
+            .map(|expr| expr.num_params())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Replace the placeholders `$1`, `$2`, etc. with the values at the same position in
+    /// `params`.
+    ///
+    /// Placeholders without a value are left as they are.
+    pub fn bind(&self, params: &[Value]) -> IntermediateCode {
+        let mut ic = self.clone();
+        for instr in ic.instrs.iter_mut() {
+            for expr in instr.exprs_mut() {
+                let bound = expr.clone().replace_with(&|e| match e {
+                    Expr::Placeholder(n) => params.get(n - 1).cloned().map(Expr::Value),
+                    _ => None,
+                });
+                *expr = bound;
+            }
+        }
+        ic
+    }
+}
+
+impl Instruction {
+    /// All the expressions in the instruction.
+    fn exprs(&self) -> Vec<&Expr> {
+        match self {
+            Self::Expr { expr, .. }
+            | Self::Filter { expr, .. }
+            | Self::Project { expr, .. }
+            | Self::GroupBy { expr, .. }
+            | Self::AddValue { expr, .. } => vec![expr],
+            Self::Update { assignments, .. } => assignments
+                .iter()
+                .flat_map(|assignment| [&assignment.col, &assignment.expr])
+                .collect(),
+            Self::Join { on, .. } => vec![on],
+            Self::Order { keys, .. } => keys.iter().map(|key| &key.expr).collect(),
+            Self::Explain { code, .. } => code.instrs.iter().flat_map(Self::exprs).collect(),
+            _ => vec![],
+        }
+    }
+
+    fn exprs_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Self::Expr { expr, .. }
+            | Self::Filter { expr, .. }
+            | Self::Project { expr, .. }
+            | Self::GroupBy { expr, .. }
+            | Self::AddValue { expr, .. } => vec![expr],
+            Self::Update { assignments, .. } => assignments
+                .iter_mut()
+                .flat_map(|assignment| [&mut assignment.col, &mut assignment.expr])
+                .collect(),
+            Self::Join { on, .. } => vec![on],
+            Self::Order { keys, .. } => keys.iter_mut().map(|key| &mut key.expr).collect(),
+            Self::Explain { code, .. } => {
+                code.instrs.iter_mut().flat_map(Self::exprs_mut).collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl IntermediateCode {
+    /// Whether the two codes are the same up to the numbering of their registers.
+    ///
+    /// Registers are renumbered in the order they are first used, so e.g. two codes which only
+    /// differ by an offset to all of their registers are equivalent.
+    pub fn equivalent(&self, other: &IntermediateCode) -> bool {
+        self.normalized() == other.normalized()
+    }
+
+    fn normalized(&self) -> IntermediateCode {
+        let mut ic = self.clone();
+        let mut renamed = std::collections::HashMap::new();
+        let mut next = RegisterIndex::default();
+        for instr in ic.instrs.iter_mut() {
+            for reg in instr.registers_mut() {
+                *reg = *renamed.entry(*reg).or_insert_with(|| {
+                    let new = next;
+                    next = next.next_index();
+                    new
+                });
+            }
+        }
+        ic
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl Instruction {
+    /// All the registers used by the instruction.
+    fn registers_mut(&mut self) -> Vec<&mut RegisterIndex> {
+        match self {
+            Self::Value { index, .. }
+            | Self::Expr { index, .. }
+            | Self::Source { index, .. }
+            | Self::Empty { index }
+            | Self::NonExistent { index }
+            | Self::Filter { index, .. }
+            | Self::GroupBy { index, .. }
+            | Self::GroupAll { index }
+            | Self::Order { index, .. }
+            | Self::Limit { index, .. }
+            | Self::Distinct { index }
+            | Self::Offset { index, .. }
+            | Self::Return { index }
+            | Self::ColumnDef { index, .. }
+            | Self::AddColumnOption { index, .. }
+            | Self::NewTable { index, .. }
+            | Self::RemoveColumn { index, .. }
+            | Self::RenameColumn { index, .. }
+            | Self::ColumnInsertDef {
+                insert_index: index,
+                ..
+            }
+            | Self::AddValue {
+                row_index: index, ..
+            }
+            | Self::Insert { index }
+            | Self::Update { index, .. }
+            | Self::Explain { index, .. } => vec![index],
+            Self::Project { input, output, .. } => vec![input, output],
+            Self::AddColumn {
+                table_reg_index,
+                col_index,
+            } => vec![table_reg_index, col_index],
+            Self::InsertDef {
+                table_reg_index,
+                index,
+            } => vec![table_reg_index, index],
+            Self::RowDef {
+                insert_index,
+                row_index,
+            } => vec![insert_index, row_index],
+            Self::Union {
+                input1,
+                input2,
+                output,
+                ..
+            }
+            | Self::CrossJoin {
+                input1,
+                input2,
+                output,
+            }
+            | Self::Join {
+                input1,
+                input2,
+                output,
+                ..
+            }
+            | Self::NaturalJoin {
+                input1,
+                input2,
+                output,
+            } => vec![input1, input2, output],
+            Self::NewSchema { .. }
+            | Self::DropTable { .. }
+            | Self::NewIndex { .. }
+            | Self::Begin
+            | Self::Commit
+            | Self::Rollback => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codegen::codegen_str;
+
+    use super::{Instruction, IntermediateCode};
+
+    #[test]
+    fn equivalent() {
+        let ic = codegen_str("SELECT col1 FROM table1 WHERE col2 = 1")
+            .unwrap()
+            .remove(0);
+
+        // the same code, with every register moved up by one
+        let shifted = IntermediateCode {
+            instrs: ic
+                .instrs
+                .iter()
+                .cloned()
+                .map(|mut instr| {
+                    for reg in instr.registers_mut() {
+                        *reg = reg.next_index();
+                    }
+                    instr
+                })
+                .collect(),
+        };
+        assert_ne!(ic, shifted);
+        assert!(ic.equivalent(&shifted));
+        assert!(shifted.equivalent(&ic));
+
+        // the input and output registers of the projection are swapped
+        let mut swapped = ic.clone();
+        for instr in swapped.instrs.iter_mut() {
+            if let Instruction::Project { input, output, .. } = instr {
+                std::mem::swap(input, output);
+            }
+        }
+        assert!(!ic.equivalent(&swapped));
+
+        let other = codegen_str("SELECT col1 FROM table1 WHERE col2 = 2")
+            .unwrap()
+            .remove(0);
+        assert!(!ic.equivalent(&other));
+    }
 }
 
 // TODO: implement these features in the vm and use the SQL statements here to test them.
@@ -1,13 +1,185 @@
 //! Names used for tables, columns, schemas, DBs, etc.
-use std::fmt::Display;
+use std::{cmp::Ordering, fmt::Display};
 
 use arraystring::{typenum::U63, ArrayString};
 
-use sqlparser::ast::Ident;
+use sqlparser::ast::{self, Ident};
 
 /// A fixed capacity copy-able string.
 pub type BoundedString = ArrayString<U63>;
 
+/// (De)serialization of a [`BoundedString`] as a string, for use with `#[serde(with)]`.
+///
+/// Unlike `arraystring`'s own implementation, this does not require the string to be borrowed from
+/// the input, which is not possible with escaped strings or when reading from a file.
+#[cfg(feature = "persistence")]
+pub(crate) mod serde_bounded_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::BoundedString;
+
+    pub fn serialize<S: Serializer>(s: &BoundedString, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(s.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BoundedString, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        BoundedString::try_from_str(&s).map_err(|_| D::Error::custom("identifier is too long"))
+    }
+}
+
+/// Whether two identifiers name the same schema, table or column.
+///
+/// All name lookups go through this so that they follow the same rules. Identifiers are
+/// currently compared exactly (i.e. case-sensitively).
+pub fn identifiers_eq(a: &BoundedString, b: &BoundedString) -> bool {
+    identifiers_cmp(a, b) == Ordering::Equal
+}
+
+/// Canonical ordering of identifiers. Consistent with [`identifiers_eq`].
+pub fn identifiers_cmp(a: &BoundedString, b: &BoundedString) -> Ordering {
+    a.as_str().cmp(b.as_str())
+}
+
+/// How identifiers are quoted when they are rendered back to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// `name`
+    None,
+    /// `"name"`, as in standard SQL and PostgreSQL.
+    DoubleQuote,
+    /// `` `name` ``, as in MySQL.
+    Backtick,
+}
+
+impl QuoteStyle {
+    /// Render a single identifier. Quote characters inside the identifier are escaped by
+    /// doubling them.
+    pub fn quote(&self, ident: &str) -> String {
+        let quote = match self {
+            Self::None => return ident.to_owned(),
+            Self::DoubleQuote => '"',
+            Self::Backtick => '`',
+        };
+        let escaped = ident.replace(quote, &format!("{}{}", quote, quote));
+        format!("{}{}{}", quote, escaped, quote)
+    }
+
+    /// Render a multi-part identifier, e.g. `schema.table`.
+    fn quote_parts(&self, parts: &[&BoundedString]) -> String {
+        parts
+            .iter()
+            .map(|part| self.quote(part))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// Switch an identifier in a parsed expression to this style.
+    fn requote_ident(&self, ident: &mut Ident) {
+        ident.quote_style = match self {
+            Self::None => None,
+            Self::DoubleQuote => Some('"'),
+            Self::Backtick => Some('`'),
+        };
+    }
+
+    /// Switch all the identifiers in a parsed expression to this style, e.g. the expression of a
+    /// `CHECK` constraint, so that it renders the same way as the names around it.
+    ///
+    /// The unquoted keywords `UNKNOWN` and `DEFAULT`, which parse as identifiers, are left as is.
+    pub(crate) fn requote_expr(&self, expr: &mut ast::Expr) {
+        match expr {
+            ast::Expr::Identifier(ident)
+                if ident.quote_style.is_none()
+                    && (ident.value.eq_ignore_ascii_case("unknown")
+                        || ident.value.eq_ignore_ascii_case("default")) => {}
+            ast::Expr::Identifier(ident) => self.requote_ident(ident),
+            ast::Expr::CompoundIdentifier(idents) => idents
+                .iter_mut()
+                .for_each(|ident| self.requote_ident(ident)),
+            ast::Expr::IsFalse(expr)
+            | ast::Expr::IsTrue(expr)
+            | ast::Expr::IsNull(expr)
+            | ast::Expr::IsNotNull(expr)
+            | ast::Expr::AnyOp(expr)
+            | ast::Expr::AllOp(expr)
+            | ast::Expr::Nested(expr)
+            | ast::Expr::UnaryOp { expr, .. }
+            | ast::Expr::Cast { expr, .. }
+            | ast::Expr::TryCast { expr, .. }
+            | ast::Expr::Extract { expr, .. }
+            | ast::Expr::Trim { expr, .. } => self.requote_expr(expr),
+            ast::Expr::IsDistinctFrom(left, right)
+            | ast::Expr::IsNotDistinctFrom(left, right)
+            | ast::Expr::BinaryOp { left, right, .. } => {
+                self.requote_expr(left);
+                self.requote_expr(right);
+            }
+            ast::Expr::Between {
+                expr, low, high, ..
+            } => {
+                self.requote_expr(expr);
+                self.requote_expr(low);
+                self.requote_expr(high);
+            }
+            ast::Expr::InList { expr, list, .. } => {
+                self.requote_expr(expr);
+                list.iter_mut().for_each(|e| self.requote_expr(e));
+            }
+            ast::Expr::Substring {
+                expr,
+                substring_from,
+                substring_for,
+            } => {
+                self.requote_expr(expr);
+                substring_from
+                    .iter_mut()
+                    .chain(substring_for.iter_mut())
+                    .for_each(|e| self.requote_expr(e));
+            }
+            ast::Expr::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => {
+                operand
+                    .iter_mut()
+                    .chain(else_result.iter_mut())
+                    .for_each(|e| self.requote_expr(e));
+                conditions
+                    .iter_mut()
+                    .chain(results.iter_mut())
+                    .for_each(|e| self.requote_expr(e));
+            }
+            ast::Expr::Tuple(elem) | ast::Expr::Array(ast::Array { elem, .. }) => {
+                elem.iter_mut().for_each(|e| self.requote_expr(e))
+            }
+            ast::Expr::Function(f) => {
+                for arg in f.args.iter_mut() {
+                    if let ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(e))
+                    | ast::FunctionArg::Named {
+                        arg: ast::FunctionArgExpr::Expr(e),
+                        ..
+                    } = arg
+                    {
+                        self.requote_expr(e);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for QuoteStyle {
+    fn default() -> Self {
+        Self::DoubleQuote
+    }
+}
+
 /// A name given to a schema. Uniquely identifies a single schema in a database.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct SchemaRef(pub BoundedString);
@@ -63,6 +235,12 @@ impl TryFrom<Vec<Ident>> for TableRef {
     }
 }
 
+impl Display for SchemaRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl Display for TableRef {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -78,6 +256,16 @@ impl Display for TableRef {
     }
 }
 
+impl TableRef {
+    /// Render the name using the given quoting style.
+    pub fn quoted(&self, style: QuoteStyle) -> String {
+        match &self.schema_name {
+            None => style.quote_parts(&[&self.table_name]),
+            Some(schema_name) => style.quote_parts(&[schema_name, &self.table_name]),
+        }
+    }
+}
+
 /// Uniquely identifies a column in a given table in a database.
 ///
 /// The schema will be assumed to be the
@@ -152,6 +340,18 @@ impl TryFrom<Vec<Ident>> for ColumnRef {
     }
 }
 
+impl ColumnRef {
+    /// Render the name using the given quoting style.
+    pub fn quoted(&self, style: QuoteStyle) -> String {
+        let parts = [&self.schema_name, &self.table_name]
+            .into_iter()
+            .flatten()
+            .chain([&self.col_name])
+            .collect::<Vec<_>>();
+        style.quote_parts(&parts)
+    }
+}
+
 /// Invalid identifier.
 #[derive(Debug, PartialEq)]
 pub struct IdentifierError {
@@ -168,3 +368,66 @@ impl Display for IdentifierError {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ColumnRef, QuoteStyle, TableRef};
+
+    #[test]
+    fn quoted_identifiers() {
+        let col = ColumnRef {
+            schema_name: None,
+            table_name: Some("order".into()),
+            col_name: "select".into(),
+        };
+        assert_eq!(col.quoted(QuoteStyle::None), "order.select");
+        assert_eq!(col.quoted(QuoteStyle::DoubleQuote), "\"order\".\"select\"");
+        assert_eq!(col.quoted(QuoteStyle::Backtick), "`order`.`select`");
+
+        let table = TableRef {
+            schema_name: Some("main".into()),
+            table_name: "we\"ird".into(),
+        };
+        assert_eq!(
+            table.quoted(QuoteStyle::DoubleQuote),
+            "\"main\".\"we\"\"ird\""
+        );
+        assert_eq!(table.quoted(QuoteStyle::Backtick), "`main`.`we\"ird`");
+    }
+
+    #[test]
+    fn display() {
+        let table = TableRef {
+            schema_name: None,
+            table_name: "table1".into(),
+        };
+        assert_eq!(table.to_string(), "table1");
+
+        let table = TableRef {
+            schema_name: Some("schema1".into()),
+            table_name: "table1".into(),
+        };
+        assert_eq!(table.to_string(), "schema1.table1");
+
+        let col = ColumnRef {
+            schema_name: None,
+            table_name: None,
+            col_name: "col1".into(),
+        };
+        assert_eq!(col.to_string(), "col1");
+
+        let col = ColumnRef {
+            schema_name: None,
+            table_name: Some("table1".into()),
+            col_name: "col1".into(),
+        };
+        assert_eq!(col.to_string(), "table1.col1");
+
+        let col = ColumnRef {
+            schema_name: Some("schema1".into()),
+            table_name: Some("table1".into()),
+            col_name: "col1".into(),
+        };
+        assert_eq!(col.to_string(), "schema1.table1.col1");
+    }
+}
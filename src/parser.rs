@@ -3,16 +3,164 @@
 use sqlparser::ast::Statement;
 use sqlparser::dialect::GenericDialect;
 use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Tokenizer};
 
 /// Parses a SQL statement.
+///
+/// `?` placeholders are numbered in order of appearance, so they become `$1`, `$2`, etc. They
+/// cannot be mixed with explicitly numbered ones, since it would not be clear which value each one
+/// refers to.
 pub fn parse(sql: &str) -> Result<Vec<Statement>, ParserError> {
     let dialect = GenericDialect {};
-    Parser::parse_sql(&dialect, sql)
+    let mut tokens = Tokenizer::new(&dialect, sql).tokenize()?;
+
+    let mut num_placeholders = 0;
+    let mut numbered = false;
+    for token in tokens.iter_mut() {
+        if let Token::Placeholder(p) = token {
+            if p == "?" {
+                num_placeholders += 1;
+                *p = format!("${}", num_placeholders);
+            } else {
+                numbered = true;
+            }
+        }
+    }
+    if numbered && num_placeholders > 0 {
+        return Err(ParserError::ParserError(
+            "Cannot mix ? placeholders with numbered ones like $1".to_owned(),
+        ));
+    }
+
+    // same as `Parser::parse_sql`, which only takes a string.
+    let mut parser = Parser::new(tokens, &dialect);
+    let mut stmts = Vec::new();
+    let mut expecting_statement_delimiter = false;
+    loop {
+        while parser.consume_token(&Token::SemiColon) {
+            expecting_statement_delimiter = false;
+        }
+
+        if parser.peek_token() == Token::EOF {
+            break;
+        }
+        if expecting_statement_delimiter {
+            return Err(ParserError::ParserError(format!(
+                "Expected end of statement, found: {}",
+                parser.peek_token()
+            )));
+        }
+
+        stmts.push(parser.parse_statement()?);
+        expecting_statement_delimiter = true;
+    }
+    Ok(stmts)
+}
+
+/// Parses SQL and classifies each of its statements.
+pub fn classify(sql: &str) -> Result<Vec<StatementKind>, ParserError> {
+    Ok(parse(sql)?.iter().map(StatementKind::of).collect())
+}
+
+/// The broad category a statement falls into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    /// Data definition: `CREATE`, `ALTER`, `DROP`, etc.
+    Ddl,
+    /// Data manipulation: `INSERT`, `UPDATE`, `DELETE`, etc.
+    Dml,
+    /// A query that only reads data: `SELECT`, `VALUES`, etc.
+    Query,
+    /// Transaction control: `START TRANSACTION`, `COMMIT`, `ROLLBACK`, etc.
+    TransactionControl,
+    /// Everything else, like `SET`, `SHOW` or `EXPLAIN`.
+    Other,
+}
+
+impl StatementKind {
+    /// The kind of the given statement.
+    pub fn of(stmt: &Statement) -> Self {
+        match stmt {
+            Statement::Query(_) => Self::Query,
+            Statement::Insert { .. }
+            | Statement::Update { .. }
+            | Statement::Delete { .. }
+            | Statement::Truncate { .. }
+            | Statement::Copy { .. }
+            | Statement::Merge { .. }
+            | Statement::Directory { .. } => Self::Dml,
+            Statement::CreateView { .. }
+            | Statement::CreateTable { .. }
+            | Statement::CreateVirtualTable { .. }
+            | Statement::CreateIndex { .. }
+            | Statement::AlterTable { .. }
+            | Statement::Drop { .. }
+            | Statement::Comment { .. }
+            | Statement::CreateSchema { .. }
+            | Statement::CreateDatabase { .. }
+            | Statement::CreateFunction { .. } => Self::Ddl,
+            Statement::StartTransaction { .. }
+            | Statement::SetTransaction { .. }
+            | Statement::Commit { .. }
+            | Statement::Rollback { .. }
+            | Statement::Savepoint { .. } => Self::TransactionControl,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A short, human readable name for the kind of the given statement, e.g. `CREATE TABLE`.
+pub fn statement_name(stmt: &Statement) -> &'static str {
+    match stmt {
+        Statement::Analyze { .. } => "ANALYZE",
+        Statement::Truncate { .. } => "TRUNCATE",
+        Statement::Msck { .. } => "MSCK",
+        Statement::Query(_) => "SELECT",
+        Statement::Insert { .. } => "INSERT",
+        Statement::Directory { .. } => "INSERT DIRECTORY",
+        Statement::Copy { .. } => "COPY",
+        Statement::Close { .. } => "CLOSE",
+        Statement::Update { .. } => "UPDATE",
+        Statement::Delete { .. } => "DELETE",
+        Statement::CreateView { .. } => "CREATE VIEW",
+        Statement::CreateTable { .. } => "CREATE TABLE",
+        Statement::CreateVirtualTable { .. } => "CREATE VIRTUAL TABLE",
+        Statement::CreateIndex { .. } => "CREATE INDEX",
+        Statement::AlterTable { .. } => "ALTER TABLE",
+        Statement::Drop { .. } => "DROP",
+        Statement::Declare { .. } => "DECLARE",
+        Statement::Fetch { .. } => "FETCH",
+        Statement::Discard { .. } => "DISCARD",
+        Statement::SetRole { .. } => "SET ROLE",
+        Statement::SetVariable { .. } => "SET",
+        Statement::ShowVariable { .. } => "SHOW",
+        Statement::ShowCreate { .. } => "SHOW CREATE",
+        Statement::ShowColumns { .. } => "SHOW COLUMNS",
+        Statement::StartTransaction { .. } => "START TRANSACTION",
+        Statement::SetTransaction { .. } => "SET TRANSACTION",
+        Statement::Comment { .. } => "COMMENT",
+        Statement::Commit { .. } => "COMMIT",
+        Statement::Rollback { .. } => "ROLLBACK",
+        Statement::CreateSchema { .. } => "CREATE SCHEMA",
+        Statement::CreateDatabase { .. } => "CREATE DATABASE",
+        Statement::CreateFunction { .. } => "CREATE FUNCTION",
+        Statement::Assert { .. } => "ASSERT",
+        Statement::Grant { .. } => "GRANT",
+        Statement::Revoke { .. } => "REVOKE",
+        Statement::Deallocate { .. } => "DEALLOCATE",
+        Statement::Execute { .. } => "EXECUTE",
+        Statement::Prepare { .. } => "PREPARE",
+        Statement::Kill { .. } => "KILL",
+        Statement::ExplainTable { .. } => "EXPLAIN TABLE",
+        Statement::Explain { .. } => "EXPLAIN",
+        Statement::Savepoint { .. } => "SAVEPOINT",
+        Statement::Merge { .. } => "MERGE",
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::parse;
+    use super::{classify, parse, StatementKind};
 
     #[test]
     fn sanity_check() {
@@ -24,4 +172,25 @@ mod test {
         let stmt = &ast[0];
         assert_eq!(stmt.to_string(), sql);
     }
+
+    #[test]
+    fn statement_kinds() {
+        let sql = "
+            CREATE TABLE foo (a INTEGER, b STRING);
+            INSERT INTO foo VALUES (1, 'one');
+            SELECT a, b FROM foo WHERE a = 1;
+            COMMIT;
+            SHOW search_path;
+        ";
+        assert_eq!(
+            classify(sql).unwrap(),
+            vec![
+                StatementKind::Ddl,
+                StatementKind::Dml,
+                StatementKind::Query,
+                StatementKind::TransactionControl,
+                StatementKind::Other
+            ]
+        );
+    }
 }
@@ -5,8 +5,13 @@ use crate::{vm::TableIndex, BoundedString};
 /// A namespace in a database.
 ///
 /// The schema only holds a reference to the actual tables, which are owned by the VM.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct Schema {
+    #[cfg_attr(
+        feature = "persistence",
+        serde(with = "crate::identifier::serde_bounded_string")
+    )]
     name: BoundedString,
     tables: Vec<TableIndex>,
 }
@@ -34,6 +39,13 @@ impl Schema {
         self.tables.push(table);
         self
     }
+
+    /// Remove a table from the schema. Returns `false` if the table was not in the schema.
+    pub fn remove_table(&mut self, table: TableIndex) -> bool {
+        let len = self.tables.len();
+        self.tables.retain(|t| *t != table);
+        self.tables.len() != len
+    }
 }
 
 #[cfg(test)]
@@ -52,5 +64,9 @@ mod tests {
         assert_eq!(schema.tables().len(), 1);
         assert_eq!(schema.tables()[0], TableIndex::default());
         assert_ne!(schema.tables()[0], TableIndex::default().next_index());
+
+        assert!(!schema.remove_table(TableIndex::default().next_index()));
+        assert!(schema.remove_table(TableIndex::default()));
+        assert_eq!(schema.tables().len(), 0);
     }
 }
@@ -1,24 +1,101 @@
 //! Tables and rows.
 
+use std::{collections::BTreeMap, fmt::Display};
+
+use hashbrown::HashMap;
 use sqlparser::ast::{ColumnOption, ColumnOptionDef, DataType};
 
-use crate::{column::Column, identifier::ColumnRef, value::Value, vm::RuntimeError, BoundedString};
+use crate::{
+    column::Column,
+    identifier::{identifiers_eq, ColumnRef},
+    value::Value,
+    vm::RuntimeError,
+    BoundedString,
+};
 
 pub(super) const TABLE_UNIQUE_KEY_NAME: &str = "__otter_unique_key";
 
 pub(super) const TABLE_TEMPORARY_NAME: &str = "__otter_temporary_table";
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 /// A table in a database.
 ///
 /// Contains both the metadata and the actual data.
 pub struct Table {
+    #[cfg_attr(
+        feature = "persistence",
+        serde(with = "crate::identifier::serde_bounded_string")
+    )]
     name: BoundedString,
     pub(super) raw_columns: Vec<Column>,
     /// The table's data.
+    ///
+    /// Empty if the table is stored by column, see [`StorageLayout`].
     // TODO: provide methods that verify the data while adding
     pub(super) raw_data: Vec<RawRow>,
+    /// The table's data column by column, including the internal columns, if the table is
+    /// stored by column.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    column_data: Option<Vec<Vec<Value>>>,
     row_id: u64,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    indexes: Vec<HashIndex>,
+}
+
+/// How the data of a table is laid out in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageLayout {
+    /// Row by row. Best for adding and reading whole rows.
+    Rows,
+    /// Column by column. Best for reading a few of the columns of many rows, as in projections
+    /// and aggregates of single columns.
+    Columnar,
+}
+
+impl Default for StorageLayout {
+    fn default() -> Self {
+        Self::Rows
+    }
+}
+
+impl Display for StorageLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rows => write!(f, "rows"),
+            Self::Columnar => write!(f, "columnar"),
+        }
+    }
+}
+
+/// A hash index on a column of a table, mapping each value in the column to the positions of the
+/// rows which have it.
+///
+/// Only the column is saved along with the table, the map is rebuilt on load.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+struct HashIndex {
+    /// Raw index of the column.
+    column: usize,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    rows: HashMap<Value, Vec<usize>>,
+}
+
+impl HashIndex {
+    fn build<'a>(column: usize, values: impl Iterator<Item = &'a Value>) -> Self {
+        let mut index = Self {
+            column,
+            rows: HashMap::new(),
+        };
+        for (position, value) in values.enumerate() {
+            index.insert(position, value);
+        }
+        index
+    }
+
+    fn insert(&mut self, position: usize, value: &Value) {
+        self.rows.entry(value.clone()).or_default().push(position);
+    }
 }
 
 impl Table {
@@ -41,7 +118,9 @@ impl Table {
             name,
             raw_columns: columns,
             raw_data: Vec::new(),
+            column_data: None,
             row_id: 0,
+            indexes: Vec::new(),
         }
     }
 
@@ -57,7 +136,9 @@ impl Table {
             name: table.name,
             raw_columns: table.raw_columns.clone(),
             raw_data: Vec::new(),
+            column_data: None,
             row_id: 0,
+            indexes: Vec::new(),
         }
     }
 
@@ -66,17 +147,289 @@ impl Table {
     /// **Note**: validation is not implemented yet and the return type is subject to change.
     pub fn new_row(&mut self, mut data: Vec<Value>) -> &mut Self {
         data.insert(0, Value::Int64(self.row_id as i64));
-        self.raw_data.push(RawRow { raw_data: data });
+        let position = self.num_rows();
+        for index in self.indexes.iter_mut() {
+            index.insert(position, &data[index.column]);
+        }
+        match &mut self.column_data {
+            Some(columns) => {
+                // columns whose data is added later are filled in by `add_column_data`.
+                let mut data = data.into_iter();
+                for column in columns.iter_mut() {
+                    column.push(data.next().unwrap_or(Value::Null));
+                }
+            }
+            None => self.raw_data.push(RawRow { raw_data: data }),
+        }
         self.row_id += 1;
         self
     }
 
+    /// Reserve capacity for at least `additional` more rows, so that adding a known number of
+    /// rows does not reallocate along the way.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        match &mut self.column_data {
+            Some(columns) => {
+                for column in columns.iter_mut() {
+                    column.reserve_exact(additional);
+                }
+            }
+            None => self.raw_data.reserve_exact(additional),
+        }
+    }
+
+    /// How the table's data is laid out in memory.
+    pub fn layout(&self) -> StorageLayout {
+        match self.column_data {
+            Some(_) => StorageLayout::Columnar,
+            None => StorageLayout::Rows,
+        }
+    }
+
+    /// Change how the table's data is laid out in memory, moving the existing data over.
+    pub fn set_layout(&mut self, layout: StorageLayout) -> &mut Self {
+        match (layout, self.column_data.take()) {
+            (StorageLayout::Columnar, None) => {
+                let mut columns: Vec<_> = (0..self.raw_columns.len())
+                    .map(|_| Vec::with_capacity(self.raw_data.len()))
+                    .collect();
+                for row in std::mem::take(&mut self.raw_data) {
+                    for (column, value) in columns.iter_mut().zip(row.raw_data) {
+                        column.push(value);
+                    }
+                }
+                self.column_data = Some(columns);
+            }
+            (StorageLayout::Rows, Some(columns)) => {
+                let num_rows = columns.first().map_or(0, Vec::len);
+                let mut rows: Vec<_> = (0..num_rows)
+                    .map(|_| RawRow {
+                        raw_data: Vec::with_capacity(columns.len()),
+                    })
+                    .collect();
+                for column in columns {
+                    for (row, value) in rows.iter_mut().zip(column) {
+                        row.raw_data.push(value);
+                    }
+                }
+                self.raw_data = rows;
+            }
+            (_, column_data) => self.column_data = column_data,
+        }
+        self
+    }
+
+    /// Number of rows in the table.
+    pub fn num_rows(&self) -> usize {
+        match &self.column_data {
+            // the unique key is always the first column.
+            Some(columns) => columns[0].len(),
+            None => self.raw_data.len(),
+        }
+    }
+
+    /// A reference to the row at the given position, without the internal columns.
+    pub(crate) fn row_shared(&self, position: usize) -> RowShared<'_> {
+        match &self.column_data {
+            Some(columns) => RowShared {
+                data: columns
+                    .iter()
+                    .zip(&self.raw_columns)
+                    .filter(|(_, col)| !col.is_internal())
+                    .map(|(column, _)| &column[position])
+                    .collect(),
+            },
+            None => RowShared::from_raw(&self.raw_data[position], self),
+        }
+    }
+
+    /// References to all the rows, without the internal columns.
+    pub(crate) fn rows_shared(&self) -> impl Iterator<Item = RowShared<'_>> {
+        (0..self.num_rows()).map(move |position| self.row_shared(position))
+    }
+
+    /// A copy of the row at the given position, including the internal columns.
+    pub(super) fn raw_row(&self, position: usize) -> RawRow {
+        match &self.column_data {
+            Some(columns) => RawRow {
+                raw_data: columns
+                    .iter()
+                    .map(|column| column[position].clone())
+                    .collect(),
+            },
+            None => self.raw_data[position].clone(),
+        }
+    }
+
+    /// The value in the row at the given position and the column at raw index `col_index`.
+    pub(crate) fn raw_value(&self, position: usize, col_index: usize) -> &Value {
+        match &self.column_data {
+            Some(columns) => &columns[col_index][position],
+            None => &self.raw_data[position].raw_data[col_index],
+        }
+    }
+
+    /// Change the value in the row at the given position and the column at raw index
+    /// `col_index`.
+    ///
+    /// **Note**: indexes are not updated, see [`rebuild_indexes`](`Self::rebuild_indexes`).
+    pub(crate) fn set_raw_value(&mut self, position: usize, col_index: usize, value: Value) {
+        match &mut self.column_data {
+            Some(columns) => columns[col_index][position] = value,
+            None => self.raw_data[position].raw_data[col_index] = value,
+        }
+    }
+
+    /// All values of the column at raw index `col_index`, in row order.
+    fn raw_values(&self, col_index: usize) -> Box<dyn Iterator<Item = &Value> + '_> {
+        match &self.column_data {
+            Some(columns) => Box::new(columns[col_index].iter()),
+            None => Box::new(
+                self.raw_data
+                    .iter()
+                    .map(move |row| &row.raw_data[col_index]),
+            ),
+        }
+    }
+
+    /// A copy of all values of the column at raw index `col_index`, in row order.
+    ///
+    /// This is a single copy when the table is stored by column.
+    pub(crate) fn raw_column_values(&self, col_index: usize) -> Vec<Value> {
+        match &self.column_data {
+            Some(columns) => columns[col_index].clone(),
+            None => self.raw_values(col_index).cloned().collect(),
+        }
+    }
+
+    /// Put the rows in the order given by the permutation.
+    pub(crate) fn reorder(&mut self, perm: &mut permutation::Permutation) {
+        match &mut self.column_data {
+            Some(columns) => {
+                for column in columns.iter_mut() {
+                    perm.apply_slice_in_place(column);
+                }
+            }
+            None => perm.apply_slice_in_place(&mut self.raw_data),
+        }
+        self.rebuild_indexes();
+    }
+
+    /// Keep only the rows for which `keep` is `true`, in the same order.
+    pub(crate) fn retain_rows(&mut self, keep: &[bool]) {
+        match &mut self.column_data {
+            Some(columns) => {
+                for column in columns.iter_mut() {
+                    let mut keep = keep.iter();
+                    column.retain(|_| *keep.next().unwrap());
+                }
+            }
+            None => {
+                let mut keep = keep.iter();
+                self.raw_data.retain(|_| *keep.next().unwrap());
+            }
+        }
+        self.rebuild_indexes();
+    }
+
+    /// Keep only the first `len` rows.
+    pub(crate) fn truncate_rows(&mut self, len: usize) {
+        match &mut self.column_data {
+            Some(columns) => {
+                for column in columns.iter_mut() {
+                    column.truncate(len);
+                }
+            }
+            None => self.raw_data.truncate(len),
+        }
+        self.rebuild_indexes();
+    }
+
+    /// Remove the first `count` rows, or all of them if there are fewer.
+    pub(crate) fn skip_rows(&mut self, count: usize) {
+        let count = count.min(self.num_rows());
+        match &mut self.column_data {
+            Some(columns) => {
+                for column in columns.iter_mut() {
+                    column.drain(..count);
+                }
+            }
+            None => {
+                self.raw_data.drain(..count);
+            }
+        }
+        self.rebuild_indexes();
+    }
+
+    /// Index the values of a column, to find the rows with a given value without going through
+    /// all of them. Does nothing if the column is indexed already.
+    ///
+    /// The index is kept up to date as rows are added.
+    pub fn create_index(&mut self, col_name: &BoundedString) -> Result<&mut Self, RuntimeError> {
+        let (col_index, col) = self.get_column(col_name)?;
+        if col.is_internal() {
+            return Err(RuntimeError::ColumnNotFound(ColumnRef {
+                schema_name: None,
+                table_name: Some(*self.name()),
+                col_name: *col_name,
+            }));
+        }
+
+        if !self.indexes.iter().any(|index| index.column == col_index) {
+            let index = HashIndex::build(col_index, self.raw_values(col_index));
+            self.indexes.push(index);
+        }
+        Ok(self)
+    }
+
+    /// The indexed columns of the table.
+    pub fn indexed_columns(&self) -> impl Iterator<Item = &Column> {
+        self.indexes
+            .iter()
+            .map(|index| &self.raw_columns[index.column])
+    }
+
+    /// Positions of the rows whose value in the column at raw index `col_index` is `value`, if
+    /// the column is indexed.
+    pub(crate) fn index_lookup(&self, col_index: usize, value: &Value) -> Option<&[usize]> {
+        let index = self
+            .indexes
+            .iter()
+            .find(|index| index.column == col_index)?;
+        Some(index.rows.get(value).map(Vec::as_slice).unwrap_or(&[]))
+    }
+
+    /// Build the indexes again, after the rows were changed in place.
+    pub(crate) fn rebuild_indexes(&mut self) {
+        self.indexes = self
+            .indexes
+            .iter()
+            .map(|index| HashIndex::build(index.column, self.raw_values(index.column)))
+            .collect();
+    }
+
+    /// Turn the table into an iterator over its rows (without the internal data), without copying
+    /// them.
+    pub fn into_rows(mut self) -> IntoRows {
+        self.set_layout(StorageLayout::Rows);
+        IntoRows {
+            internal: self.raw_columns.iter().map(|c| c.is_internal()).collect(),
+            rows: self.raw_data.into_iter(),
+        }
+    }
+
     /// Retrieve a copy of all of the table's non-internal data.
     pub fn all_data(&self) -> Vec<Row> {
-        self.raw_data
+        self.rows_shared().map(|row| Row::new(row.data())).collect()
+    }
+
+    /// Retrieve a copy of all of the table's non-internal data, column by column.
+    pub fn to_columns(&self) -> Vec<(Column, Vec<Value>)> {
+        self.raw_columns
             .iter()
-            .cloned()
-            .map(|row| Row::from_raw(row, self))
+            .enumerate()
+            .filter(|(_, col)| !col.is_internal())
+            .map(|(col_index, col)| (col.clone(), self.raw_column_values(col_index)))
             .collect()
     }
 
@@ -99,13 +452,77 @@ impl Table {
     /// Add a new column to the table.
     ///
     /// **Note**: this does not yet modify any of the rows. They must be kept consistent
-    /// externally using [`add_column_data`](`Self::add_column_data`).
+    /// externally using [`add_column_data`](`Self::add_column_data`). In the columnar layout, the
+    /// new column is filled with NULLs until then so that all columns keep the same length.
     // TODO: does not add the column data to the rows.
     pub fn add_column(&mut self, column: Column) -> &mut Self {
+        let num_rows = self.num_rows();
         self.raw_columns.push(column);
+        if let Some(columns) = &mut self.column_data {
+            columns.push(vec![Value::Null; num_rows]);
+        }
         self
     }
 
+    /// Remove a column along with its data in all rows.
+    pub fn remove_column(&mut self, col_name: &BoundedString) -> Result<Column, RuntimeError> {
+        let (col_index, col) = self.get_column(col_name)?;
+        if col.is_internal() {
+            return Err(RuntimeError::ColumnNotFound(ColumnRef {
+                schema_name: None,
+                table_name: Some(*self.name()),
+                col_name: *col_name,
+            }));
+        }
+
+        match &mut self.column_data {
+            Some(columns) => {
+                columns.remove(col_index);
+            }
+            None => {
+                for row in self.raw_data.iter_mut() {
+                    row.raw_data.remove(col_index);
+                }
+            }
+        }
+        self.indexes.retain(|index| index.column != col_index);
+        for index in self.indexes.iter_mut() {
+            if index.column > col_index {
+                index.column -= 1;
+            }
+        }
+        Ok(self.raw_columns.remove(col_index))
+    }
+
+    /// Rename a column. The data in the rows is not affected.
+    pub fn rename_column(
+        &mut self,
+        old_name: &BoundedString,
+        new_name: BoundedString,
+    ) -> Result<&mut Self, RuntimeError> {
+        let (col_index, col) = self.get_column(old_name)?;
+        if col.is_internal() {
+            return Err(RuntimeError::ColumnNotFound(ColumnRef {
+                schema_name: None,
+                table_name: Some(*self.name()),
+                col_name: *old_name,
+            }));
+        }
+
+        if let Ok((other_index, _)) = self.get_column(&new_name) {
+            if other_index != col_index {
+                return Err(RuntimeError::ColumnExists(ColumnRef {
+                    schema_name: None,
+                    table_name: Some(*self.name()),
+                    col_name: new_name,
+                }));
+            }
+        }
+
+        self.raw_columns[col_index].rename(new_name);
+        Ok(self)
+    }
+
     /// Add data for a new column to all rows.
     pub fn add_column_data(
         &mut self,
@@ -114,16 +531,24 @@ impl Table {
     ) -> Result<&mut Self, RuntimeError> {
         let (col_index, _) = self.get_column(col_name)?;
 
-        if !self.is_empty() && self.raw_data.len() != data.len() {
+        if !self.is_empty() && self.num_rows() != data.len() {
             return Err(RuntimeError::TableNewColumnSizeMismatch {
                 table_name: *self.name(),
-                table_len: self.raw_data.len(),
+                table_len: self.num_rows(),
                 col_name: *col_name,
                 col_len: data.len(),
             });
         }
 
-        if !self.is_empty() {
+        if self.column_data.is_some() {
+            if self.is_empty() {
+                self.reserve(data.len());
+                for _ in 0..data.len() {
+                    self.new_row(vec![]);
+                }
+            }
+            self.column_data.as_mut().unwrap()[col_index] = data;
+        } else if !self.is_empty() {
             let first_row_size = self.raw_data[0].raw_data.len();
             if first_row_size == col_index {
                 // column is the last one. just push it at the end.
@@ -143,6 +568,7 @@ impl Table {
                 }
             }
         } else {
+            self.reserve(data.len());
             for value in data {
                 self.new_row(vec![value]);
             }
@@ -151,12 +577,50 @@ impl Table {
         Ok(self)
     }
 
+    /// Add a new column at the end, along with its data for all rows.
+    ///
+    /// Unlike [`add_column_data`](`Self::add_column_data`), the data is placed by position and
+    /// not by name, so other columns with the same name are not affected.
+    pub fn add_column_with_data(
+        &mut self,
+        column: Column,
+        data: Vec<Value>,
+    ) -> Result<&mut Self, RuntimeError> {
+        if self.is_empty() && self.has_no_columns() {
+            self.reserve(data.len());
+            for _ in 0..data.len() {
+                self.new_row(vec![]);
+            }
+        } else if self.num_rows() != data.len() {
+            return Err(RuntimeError::TableNewColumnSizeMismatch {
+                table_name: *self.name(),
+                table_len: self.num_rows(),
+                col_name: *column.name(),
+                col_len: data.len(),
+            });
+        }
+
+        match &mut self.column_data {
+            Some(columns) => columns.push(data),
+            None => {
+                for (row, new_data) in self.raw_data.iter_mut().zip(data) {
+                    row.raw_data.push(new_data);
+                }
+            }
+        }
+        self.raw_columns.push(column);
+        Ok(self)
+    }
+
     /// Map column name to its index and definition.
     pub(super) fn get_column(
         &self,
         col_name: &BoundedString,
     ) -> Result<(usize, &Column), RuntimeError> {
-        let idx = self.raw_columns.iter().position(|c| c.name() == col_name);
+        let idx = self
+            .raw_columns
+            .iter()
+            .position(|c| identifiers_eq(c.name(), col_name));
         if let Some(idx) = idx {
             Ok((idx, &self.raw_columns[idx]))
         } else {
@@ -168,15 +632,51 @@ impl Table {
         }
     }
 
+    /// Position of the referenced column among the table's (non-internal) columns.
+    ///
+    /// Joined tables store columns whose names clash as `table.col`, so a qualified reference
+    /// looks for such a column first.
+    pub(crate) fn resolve_column(&self, col_ref: &ColumnRef) -> Option<usize> {
+        if let Some(table_name) = col_ref.table_name {
+            let qualified: BoundedString = format!("{}.{}", table_name, col_ref.col_name)
+                .as_str()
+                .into();
+            if let Some(index) = self
+                .columns()
+                .position(|c| identifiers_eq(c.name(), &qualified))
+            {
+                return Some(index);
+            }
+        }
+
+        self.columns()
+            .position(|c| identifiers_eq(c.name(), &col_ref.col_name))
+    }
+
+    /// Position of the referenced column among all of the table's columns, including internal ones.
+    pub(super) fn resolve_raw_column(&self, col_ref: &ColumnRef) -> Option<usize> {
+        let index = self.resolve_column(col_ref)?;
+        self.raw_columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.is_internal())
+            .nth(index)
+            .map(|(i, _)| i)
+    }
+
     /// Retrieve all data of a column.
     pub fn get_column_data(&self, col_name: &BoundedString) -> Result<Vec<Value>, RuntimeError> {
         let (col_index, _) = self.get_column(col_name)?;
+        Ok(self.raw_column_values(col_index))
+    }
 
-        Ok(self
-            .raw_data
-            .iter()
-            .map(|row| row.raw_data[col_index].clone())
-            .collect())
+    /// Iterate over all values of a column without copying them.
+    pub fn column_iter(
+        &self,
+        col_name: &BoundedString,
+    ) -> Result<impl Iterator<Item = &Value>, RuntimeError> {
+        let (col_index, _) = self.get_column(col_name)?;
+        Ok(self.raw_values(col_index))
     }
 
     /// Rename the table.
@@ -186,7 +686,7 @@ impl Table {
 
     /// Whether the table has no rows.
     pub fn is_empty(&self) -> bool {
-        self.raw_data.is_empty()
+        self.num_rows() == 0
     }
 
     /// Whether the table has no defined columns.
@@ -194,6 +694,48 @@ impl Table {
         self.columns().next().is_none()
     }
 
+    /// Take a copy of the table's rows, to later find out what changed with [`diff`](`Self::diff`).
+    pub fn snapshot(&self) -> TableSnapshot {
+        TableSnapshot {
+            rows: (0..self.num_rows())
+                .map(|position| {
+                    (
+                        self.raw_value(position, 0).clone(),
+                        Row::new(self.row_shared(position).data()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Rows added, removed and changed since the snapshot was taken.
+    ///
+    /// Rows are told apart by the table's internal unique key, so a row that was updated is
+    /// reported as changed rather than as removed and added again.
+    pub fn diff(&self, snapshot: &TableSnapshot) -> TableDiff {
+        let mut diff = TableDiff::default();
+        let mut current = BTreeMap::new();
+        for position in 0..self.num_rows() {
+            let key = self.raw_value(position, 0);
+            let row = Row::new(self.row_shared(position).data());
+            match snapshot.rows.get(key) {
+                None => diff.added.push(row.clone()),
+                Some(old_row) if *old_row != row => {
+                    diff.changed.push((old_row.clone(), row.clone()))
+                }
+                Some(_) => {}
+            }
+            current.insert(key, row);
+        }
+        diff.removed = snapshot
+            .rows
+            .iter()
+            .filter(|(key, _)| !current.contains_key(key))
+            .map(|(_, row)| row.clone())
+            .collect();
+        diff
+    }
+
     /// Create a new row filled with sentinel values for the data type.
     ///
     /// Note: does not add the row to the table.
@@ -201,6 +743,7 @@ impl Table {
         let data = self
             .raw_columns
             .iter()
+            .filter(|c| !c.is_internal())
             .map(|c| Value::sentinel_value(c.data_type()))
             .collect::<Result<Vec<_>, _>>()?;
         Ok(Row { data })
@@ -229,6 +772,109 @@ impl std::fmt::Display for Table {
     }
 }
 
+#[cfg(any(test, feature = "test-utils"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-utils")))]
+impl Table {
+    /// Whether both tables have the same columns and the same rows in the same order.
+    ///
+    /// Columns are compared by name and data type. Table names are not compared.
+    pub fn rows_equal_ordered(&self, other: &Table) -> bool {
+        self.columns_equal(other) && self.all_data() == other.all_data()
+    }
+
+    /// Whether both tables have the same columns and the same rows, in any order.
+    ///
+    /// Duplicate rows must appear the same number of times in both tables.
+    pub fn rows_equal_unordered(&self, other: &Table) -> bool {
+        let sorted_data = |table: &Table| {
+            let mut data: Vec<_> = table.all_data().into_iter().map(|r| r.data).collect();
+            data.sort();
+            data
+        };
+        self.columns_equal(other) && sorted_data(self) == sorted_data(other)
+    }
+
+    fn columns_equal(&self, other: &Table) -> bool {
+        self.columns()
+            .map(|c| (c.name(), c.data_type()))
+            .eq(other.columns().map(|c| (c.name(), c.data_type())))
+    }
+}
+
+/// A copy of a table's rows at some point in time. See [`Table::snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSnapshot {
+    /// Rows by the table's internal unique key.
+    rows: BTreeMap<Value, Row>,
+}
+
+/// Changes to a table's rows since a [`TableSnapshot`] was taken. See [`Table::diff`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TableDiff {
+    /// Rows that were not in the snapshot, in table order.
+    pub added: Vec<Row>,
+    /// Rows that are no longer in the table, in the order they were inserted.
+    pub removed: Vec<Row>,
+    /// Rows whose values changed, as pairs of the old and the new row.
+    pub changed: Vec<(Row, Row)>,
+}
+
+impl TableDiff {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// An iterator which moves the rows out of a table. Created by [`Table::into_rows`].
+#[derive(Debug)]
+pub struct IntoRows {
+    /// Whether each of the raw columns is internal, and hence left out of the rows.
+    internal: Vec<bool>,
+    rows: std::vec::IntoIter<RawRow>,
+}
+
+impl IntoRows {
+    /// An iterator without any rows.
+    pub(crate) fn empty() -> Self {
+        Self {
+            internal: Vec::new(),
+            rows: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl Iterator for IntoRows {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        let raw = self.rows.next()?;
+        Some(Row {
+            data: raw
+                .raw_data
+                .into_iter()
+                .zip(&self.internal)
+                .filter_map(|(value, internal)| (!internal).then(|| value))
+                .collect(),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IntoRows {}
+
+impl IntoIterator for Table {
+    type Item = Row;
+    type IntoIter = IntoRows;
+
+    fn into_iter(self) -> IntoRows {
+        self.into_rows()
+    }
+}
+
 /// Trait to retrieve data from something that looks like a row in a table.
 pub trait RowLike {
     /// Copy or move of the data contained in the row.
@@ -243,6 +889,7 @@ pub trait RowLike {
 
 /// A row stored in a table. Represents a relation in relational algebra terms.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct Row {
     /// Values for each column in the row.
     data: Vec<Value>,
@@ -263,23 +910,6 @@ impl Row {
         Self { data }
     }
 
-    pub(super) fn from_raw(raw: RawRow, table: &Table) -> Row {
-        Row {
-            data: raw
-                .raw_data
-                .into_iter()
-                .enumerate()
-                .filter_map(|(i, value)| {
-                    if table.raw_columns[i].is_internal() {
-                        None
-                    } else {
-                        Some(value)
-                    }
-                })
-                .collect(),
-        }
-    }
-
     pub fn to_shared(&self) -> RowShared {
         RowShared::from_row(self)
     }
@@ -334,6 +964,7 @@ impl<'a> From<&'a Row> for RowShared<'a> {
 
 /// A row in a table, including internal columns.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct RawRow {
     /// Values for each column in the row.
     pub(crate) raw_data: Vec<Value>,
@@ -353,8 +984,12 @@ impl RowLike for RawRow {
 mod tests {
     use sqlparser::ast::DataType;
 
-    use super::Table;
-    use crate::{column::Column, table::Row, value::Value};
+    use super::{StorageLayout, Table};
+    use crate::{
+        column::Column,
+        table::{Row, RowLike, TableDiff},
+        value::Value,
+    };
 
     #[test]
     fn create_table() {
@@ -387,4 +1022,259 @@ mod tests {
             }]
         );
     }
+
+    #[test]
+    fn into_rows() {
+        let mut table = Table::new("test".into(), vec![]);
+        table.add_column(Column::new(
+            "col1".into(),
+            DataType::Int(None),
+            vec![],
+            false,
+        ));
+        table.add_column(Column::new(
+            "col2".into(),
+            DataType::Int(None),
+            vec![],
+            true,
+        ));
+        table.new_row(vec![Value::Int64(1), Value::Int64(2)]);
+        table.new_row(vec![Value::Int64(3), Value::Int64(4)]);
+
+        let mut rows = table.into_rows();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows.next(), Some(Row::new(vec![Value::Int64(1)])));
+        assert_eq!(rows.next(), Some(Row::new(vec![Value::Int64(3)])));
+        assert_eq!(rows.next(), None);
+    }
+
+    #[test]
+    fn columnar_layout() {
+        let mut table = Table::new("test".into(), vec![]);
+        table.set_layout(StorageLayout::Columnar);
+        table.add_column(Column::new(
+            "col1".into(),
+            DataType::Int(None),
+            vec![],
+            false,
+        ));
+        table.add_column(Column::new(
+            "col2".into(),
+            DataType::Int(None),
+            vec![],
+            false,
+        ));
+        table.new_row(vec![Value::Int64(1), Value::Int64(2)]);
+        table.new_row(vec![Value::Int64(3), Value::Int64(4)]);
+        table.create_index(&"col2".into()).unwrap();
+
+        assert_eq!(table.layout(), StorageLayout::Columnar);
+        assert_eq!(table.num_rows(), 2);
+        assert_eq!(
+            table.get_column_data(&"col2".into()).unwrap(),
+            vec![Value::Int64(2), Value::Int64(4)]
+        );
+        assert_eq!(table.index_lookup(2, &Value::Int64(4)), Some(&[1][..]));
+
+        // the data is the same in either layout.
+        let data = table.all_data();
+        assert_eq!(
+            data,
+            vec![
+                Row::new(vec![Value::Int64(1), Value::Int64(2)]),
+                Row::new(vec![Value::Int64(3), Value::Int64(4)]),
+            ]
+        );
+        table.set_layout(StorageLayout::Rows);
+        assert_eq!(table.layout(), StorageLayout::Rows);
+        assert_eq!(table.all_data(), data);
+        table.set_layout(StorageLayout::Columnar);
+        assert_eq!(table.all_data(), data);
+
+        // a column added to a table with rows is NULL in all of them.
+        table.add_column(Column::new(
+            "col3".into(),
+            DataType::Int(None),
+            vec![],
+            false,
+        ));
+        assert_eq!(
+            table.get_column_data(&"col3".into()).unwrap(),
+            vec![Value::Null, Value::Null]
+        );
+        table.new_row(vec![Value::Int64(5), Value::Int64(6), Value::Int64(7)]);
+        assert_eq!(table.num_rows(), 3);
+        table
+            .add_column_data(
+                &"col3".into(),
+                vec![Value::Int64(1), Value::Int64(2), Value::Int64(3)],
+            )
+            .unwrap();
+        table.remove_column(&"col3".into()).unwrap();
+        table.retain_rows(&[true, true, false]);
+
+        table.remove_column(&"col1".into()).unwrap();
+        table.new_row(vec![Value::Int64(6)]);
+        assert_eq!(table.index_lookup(1, &Value::Int64(6)), Some(&[2][..]));
+        assert_eq!(
+            table.into_rows().collect::<Vec<_>>(),
+            vec![
+                Row::new(vec![Value::Int64(2)]),
+                Row::new(vec![Value::Int64(4)]),
+                Row::new(vec![Value::Int64(6)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_columns() {
+        let mut table = Table::new("test".into(), vec![]);
+        let col1 = Column::new("col1".into(), DataType::Int(None), vec![], false);
+        let col2 = Column::new("col2".into(), DataType::String, vec![], false);
+        table.add_column(col1.clone());
+        table.add_column(col2.clone());
+
+        table.new_row(vec![Value::Int64(1), Value::String("one".to_owned())]);
+        table.new_row(vec![Value::Int64(2), Value::String("two".to_owned())]);
+        table.new_row(vec![Value::Int64(3), Value::Null]);
+
+        let columns = table.to_columns();
+        assert_eq!(
+            columns,
+            vec![
+                (
+                    col1,
+                    vec![Value::Int64(1), Value::Int64(2), Value::Int64(3)]
+                ),
+                (
+                    col2,
+                    vec![
+                        Value::String("one".to_owned()),
+                        Value::String("two".to_owned()),
+                        Value::Null
+                    ]
+                )
+            ]
+        );
+
+        for (row_index, row) in table.all_data().into_iter().enumerate() {
+            for (col_index, value) in row.data().into_iter().enumerate() {
+                assert_eq!(columns[col_index].1[row_index], value);
+            }
+        }
+    }
+
+    #[test]
+    fn column_iter() {
+        let mut table = Table::new("test".into(), vec![]);
+        table.add_column(Column::new(
+            "col1".into(),
+            DataType::Int(None),
+            vec![],
+            false,
+        ));
+        table.add_column(Column::new("col2".into(), DataType::Double, vec![], false));
+
+        for i in 1..=100 {
+            table.new_row(vec![
+                Value::Int64(i),
+                Value::Float64((i as f64 / 2.0).into()),
+            ]);
+        }
+
+        let sum: i64 = table
+            .column_iter(&"col1".into())
+            .unwrap()
+            .map(|v| match v {
+                Value::Int64(i) => *i,
+                _ => panic!("unexpected value {v}"),
+            })
+            .sum();
+        assert_eq!(sum, 5050);
+
+        let sum: f64 = table
+            .column_iter(&"col2".into())
+            .unwrap()
+            .map(|v| match v {
+                Value::Float64(f) => f.into_inner(),
+                _ => panic!("unexpected value {v}"),
+            })
+            .sum();
+        assert_eq!(sum, 2525.0);
+
+        assert!(table.column_iter(&"col3".into()).is_err());
+    }
+
+    #[test]
+    fn snapshot_diff() {
+        let mut table = Table::new("test".into(), vec![]);
+        table.add_column(Column::new(
+            "col1".into(),
+            DataType::Int(None),
+            vec![],
+            false,
+        ));
+        for i in 1..=3 {
+            table.new_row(vec![Value::Int64(i)]);
+        }
+
+        let snapshot = table.snapshot();
+        assert!(table.diff(&snapshot).is_empty());
+
+        table
+            .raw_data
+            .retain(|row| row.raw_data[1] != Value::Int64(2));
+        table.raw_data[1].raw_data[1] = Value::Int64(30);
+        table.new_row(vec![Value::Int64(4)]);
+
+        assert_eq!(
+            table.diff(&snapshot),
+            TableDiff {
+                added: vec![Row::new(vec![Value::Int64(4)])],
+                removed: vec![Row::new(vec![Value::Int64(2)])],
+                changed: vec![(
+                    Row::new(vec![Value::Int64(3)]),
+                    Row::new(vec![Value::Int64(30)])
+                )],
+            }
+        );
+    }
+
+    #[test]
+    fn rows_equal() {
+        let new_table = |rows: &[(i64, &str)]| {
+            let mut table = Table::new("test".into(), vec![]);
+            table.add_column(Column::new(
+                "col1".into(),
+                DataType::Int(None),
+                vec![],
+                false,
+            ));
+            table.add_column(Column::new("col2".into(), DataType::String, vec![], false));
+            for (col1, col2) in rows {
+                table.new_row(vec![Value::Int64(*col1), Value::String(col2.to_string())]);
+            }
+            table
+        };
+
+        let table = new_table(&[(1, "a"), (2, "b"), (2, "b")]);
+        let same = new_table(&[(1, "a"), (2, "b"), (2, "b")]);
+        let shuffled = new_table(&[(2, "b"), (1, "a"), (2, "b")]);
+        let different_counts = new_table(&[(1, "a"), (1, "a"), (2, "b")]);
+
+        assert!(table.rows_equal_ordered(&same));
+        assert!(table.rows_equal_unordered(&same));
+
+        assert!(!table.rows_equal_ordered(&shuffled));
+        assert!(table.rows_equal_unordered(&shuffled));
+
+        assert!(!table.rows_equal_unordered(&different_counts));
+
+        let mut renamed = new_table(&[(1, "a"), (2, "b"), (2, "b")]);
+        renamed
+            .rename_column(&"col2".into(), "col3".into())
+            .unwrap();
+        assert!(!table.rows_equal_ordered(&renamed));
+        assert!(!table.rows_equal_unordered(&renamed));
+    }
 }
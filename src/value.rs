@@ -4,6 +4,7 @@ use std::{
     ops::{Add, Div, Mul, Neg, Not, Rem, Sub},
 };
 
+use chrono::{NaiveDate, NaiveDateTime};
 use ordered_float::OrderedFloat;
 use sqlparser::ast::{self, DataType};
 
@@ -15,7 +16,12 @@ use crate::{
 /// A value contained within a table's cell.
 ///
 /// One or more [`DataType`] variants may be mapped to a single variant of `Value`.
-#[derive(Debug, PartialEq, PartialOrd, Clone, Eq, Ord)]
+///
+/// Values are totally ordered, which is what grouping and `DISTINCT` rely on to tell values
+/// apart. All floating `NaN`s are equal to each other regardless of their bits, and larger than
+/// any other float.
+#[derive(Debug, PartialEq, PartialOrd, Clone, Eq, Ord, Hash)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     Null,
 
@@ -34,13 +40,22 @@ pub enum Value {
     // TODO: Float32
     Float64(OrderedFloat<f64>),
 
-    // TODO: date and timestamp
+    // date and time types
+    // reference: https://dev.mysql.com/doc/refman/8.0/en/date-and-time-types.html
+    // TODO: Time
+    Date(NaiveDate),
+
+    /// Date and time without a time zone, used for both `TIMESTAMP` and `DATETIME`.
+    Timestamp(NaiveDateTime),
 
     // string types
     // reference: https://dev.mysql.com/doc/refman/8.0/en/string-types.html
     String(String),
 
     Binary(Vec<u8>),
+
+    /// A list of values, which may contain `NULL`s. Produced by `ARRAY_AGG`.
+    Array(Vec<Value>),
 }
 
 impl Display for Value {
@@ -50,8 +65,18 @@ impl Display for Value {
             Self::Bool(v) => write!(f, "{}", v),
             Self::Int64(v) => write!(f, "{}", v),
             Self::Float64(v) => write!(f, "{}", v),
+            Self::Date(v) => write!(f, "{}", v),
+            Self::Timestamp(v) => write!(f, "{}", v),
             Self::String(v) => write!(f, "{}", v),
             Self::Binary(v) => write!(f, "{:?}", v),
+            Self::Array(v) => write!(
+                f,
+                "[{}]",
+                v.iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         }
     }
 }
@@ -60,6 +85,8 @@ impl Value {
     pub fn is_true(self) -> Result<Value, ValueUnaryOpError> {
         match self {
             Value::Bool(lhs) => Ok(Value::Bool(lhs)),
+            // unknown is neither true nor false
+            Value::Null => Ok(Value::Bool(false)),
             _ => Err(ValueUnaryOpError {
                 operator: UnOp::IsTrue,
                 value: self,
@@ -70,6 +97,7 @@ impl Value {
     pub fn is_false(self) -> Result<Value, ValueUnaryOpError> {
         match self {
             Value::Bool(lhs) => Ok(Value::Bool(!lhs)),
+            Value::Null => Ok(Value::Bool(false)),
             _ => Err(ValueUnaryOpError {
                 operator: UnOp::IsFalse,
                 value: self,
@@ -91,10 +119,11 @@ impl Value {
         }
     }
 
+    /// Whether the string matches the pattern, where `%` stands for any sequence of characters
+    /// and `_` for any single character. A backslash makes the character after it match itself.
     pub fn like(self, rhs: Value) -> Result<Value, ValueBinaryOpError> {
         match (&self, &rhs) {
-            // TODO: implement proper pattern matching
-            (Value::String(lhs), Value::String(rhs)) => Ok(Value::Bool(lhs.contains(rhs))),
+            (Value::String(lhs), Value::String(rhs)) => Ok(Value::Bool(like_match(lhs, rhs))),
             _ => Err(ValueBinaryOpError {
                 operator: BinOp::Like,
                 values: (self, rhs),
@@ -102,28 +131,210 @@ impl Value {
         }
     }
 
+    /// Like [`like`](`Self::like`), ignoring case.
     pub fn ilike(self, rhs: Value) -> Result<Value, ValueBinaryOpError> {
         match (&self, &rhs) {
-            // TODO: implement proper pattern matching
-            (Value::String(lhs), Value::String(rhs)) => Ok(Value::Bool(
-                lhs.to_lowercase().contains(&rhs.to_lowercase()),
-            )),
+            (Value::String(lhs), Value::String(rhs)) => Ok(Value::Bool(like_match(
+                &lhs.to_lowercase(),
+                &rhs.to_lowercase(),
+            ))),
             _ => Err(ValueBinaryOpError {
-                operator: BinOp::Like,
+                operator: BinOp::ILike,
                 values: (self, rhs),
             }),
         }
     }
 
+    /// Logical exclusive-or of two booleans. `NULL` if either of them is `NULL`.
+    pub fn xor(self, rhs: Value) -> Result<Value, ValueBinaryOpError> {
+        match (&self, &rhs) {
+            (Value::Bool(lhs), Value::Bool(rhs)) => Ok(Value::Bool(lhs ^ rhs)),
+            (Value::Null, Value::Bool(_) | Value::Null) | (Value::Bool(_), Value::Null) => {
+                Ok(Value::Null)
+            }
+            _ => Err(ValueBinaryOpError {
+                operator: BinOp::Xor,
+                values: (self, rhs),
+            }),
+        }
+    }
+
+    /// Whether the value counts as true when used as a condition, e.g. in a `WHERE` clause.
+    ///
+    /// Numbers are true when non-zero and `NULL` is never true. Returns `None` for values that
+    /// have no truth value, like strings.
+    pub fn is_truthy(&self) -> Option<bool> {
+        match self {
+            Self::Null => Some(false),
+            Self::Bool(b) => Some(*b),
+            Self::Int64(i) => Some(*i != 0),
+            Self::Float64(f) => Some(f.into_inner() != 0.0),
+            Self::Date(_)
+            | Self::Timestamp(_)
+            | Self::String(_)
+            | Self::Binary(_)
+            | Self::Array(_) => None,
+        }
+    }
+
     /// Type of data this value is.
     pub fn data_type(&self) -> DataType {
         match self {
-            Self::Null => DataType::Int(None),
+            Self::Null => unknown_type(),
             Self::Bool(_) => DataType::Boolean,
             Self::Int64(_) => DataType::Int(None),
             Self::Float64(_) => DataType::Float(None),
+            Self::Date(_) => DataType::Date,
+            Self::Timestamp(_) => DataType::Timestamp,
             Self::String(_) => DataType::String,
             Self::Binary(_) => DataType::Bytea,
+            // the element type is that of the first non-NULL element, if any.
+            Self::Array(v) => DataType::Array(Box::new(
+                v.iter()
+                    .find(|v| **v != Self::Null)
+                    .unwrap_or(&Self::Null)
+                    .data_type(),
+            )),
+        }
+    }
+
+    /// Whether the value can be stored in a column of the given type.
+    ///
+    /// `NULL` fits any type. Integers also fit float types, see [`promote_to`](`Self::promote_to`).
+    pub(crate) fn fits_type(&self, data_type: &DataType) -> bool {
+        match self {
+            Self::Null => true,
+            Self::Bool(_) => matches!(data_type, DataType::Boolean),
+            Self::Int64(_) => is_integer_type(data_type) || is_float_type(data_type),
+            Self::Float64(_) => is_float_type(data_type),
+            Self::Date(_) => matches!(data_type, DataType::Date),
+            Self::Timestamp(_) => is_timestamp_type(data_type),
+            Self::String(_) => is_string_type(data_type),
+            Self::Binary(_) => is_binary_type(data_type),
+            Self::Array(v) => match data_type {
+                DataType::Array(element_type) => v.iter().all(|v| v.fits_type(element_type)),
+                _ => false,
+            },
+        }
+    }
+
+    /// Convert the value to the given type, as in `CAST(value AS data_type)`.
+    ///
+    /// `NULL` can be cast to any type. Floats are rounded to the nearest integer. Strings are
+    /// parsed, ignoring surrounding whitespace.
+    pub fn cast(self, data_type: &DataType) -> Result<Value, ValueCastError> {
+        let cast = match (&self, data_type) {
+            (Self::Null, _) => Some(Self::Null),
+
+            (Self::Bool(b), d) if is_integer_type(d) => Some(Self::Int64(*b as i64)),
+            (Self::Int64(i), d) if is_integer_type(d) => Some(Self::Int64(*i)),
+            (Self::Float64(f), d) if is_integer_type(d) => {
+                let rounded = f.into_inner().round();
+                // the bounds of i64 are exactly representable as floats.
+                if rounded >= i64::MIN as f64 && rounded < i64::MAX as f64 {
+                    Some(Self::Int64(rounded as i64))
+                } else {
+                    None
+                }
+            }
+            (Self::String(s), d) if is_integer_type(d) => s.trim().parse().ok().map(Self::Int64),
+
+            (Self::Int64(i), d) if is_float_type(d) => Some(Self::Float64((*i as f64).into())),
+            (Self::Float64(f), d) if is_float_type(d) => Some(Self::Float64(*f)),
+            (Self::String(s), d) if is_float_type(d) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(|f| Self::Float64(f.into())),
+
+            (
+                Self::Bool(_)
+                | Self::Int64(_)
+                | Self::Float64(_)
+                | Self::Date(_)
+                | Self::Timestamp(_),
+                d,
+            ) if is_string_type(d) => Some(Self::String(self.to_string())),
+            (Self::String(s), d) if is_string_type(d) => Some(Self::String(s.clone())),
+
+            (Self::Bool(b), DataType::Boolean) => Some(Self::Bool(*b)),
+            (Self::Int64(i), DataType::Boolean) => Some(Self::Bool(*i != 0)),
+            (Self::String(s), DataType::Boolean) => match s.trim().to_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "on" | "1" => Some(Self::Bool(true)),
+                "false" | "f" | "no" | "n" | "off" | "0" => Some(Self::Bool(false)),
+                _ => None,
+            },
+
+            (Self::Date(d), DataType::Date) => Some(Self::Date(*d)),
+            (Self::Timestamp(t), DataType::Date) => Some(Self::Date(t.date())),
+            (Self::String(s), DataType::Date) => parse_date(s).map(Self::Date),
+
+            (Self::Date(d), t) if is_timestamp_type(t) => {
+                d.and_hms_opt(0, 0, 0).map(Self::Timestamp)
+            }
+            (Self::Timestamp(t), d) if is_timestamp_type(d) => Some(Self::Timestamp(*t)),
+            (Self::String(s), d) if is_timestamp_type(d) => parse_timestamp(s).map(Self::Timestamp),
+
+            (Self::String(s), d) if is_binary_type(d) => Some(Self::Binary(s.as_bytes().to_vec())),
+            (Self::Binary(b), d) if is_binary_type(d) => Some(Self::Binary(b.clone())),
+
+            (Self::Array(_), d) if is_string_type(d) => Some(Self::String(self.to_string())),
+            (Self::Array(v), DataType::Array(element_type)) => v
+                .iter()
+                .map(|v| v.clone().cast(element_type).ok())
+                .collect::<Option<Vec<_>>>()
+                .map(Self::Array),
+
+            _ => None,
+        };
+
+        cast.ok_or(ValueCastError {
+            value: self,
+            data_type: data_type.clone(),
+        })
+    }
+
+    /// Convert the value to be stored in a column of the given type, if it needs to be.
+    ///
+    /// Integers are converted to floats, dates to timestamps, and strings to the dates and
+    /// timestamps they spell out. Everything else, including strings that are not valid dates, is
+    /// returned as is.
+    pub(crate) fn promote_to(self, data_type: &DataType) -> Self {
+        match self {
+            Self::Int64(i) if is_float_type(data_type) => Self::Float64((i as f64).into()),
+            Self::Date(_) | Self::String(_)
+                if matches!(data_type, DataType::Date) || is_timestamp_type(data_type) =>
+            {
+                self.clone().cast(data_type).unwrap_or(self)
+            }
+            _ => self,
+        }
+    }
+
+    /// Bring two values to be compared to the same type, if one is a date or a timestamp.
+    ///
+    /// A string compared with a date or timestamp is read as one, and a date compared with a
+    /// timestamp is taken as its midnight. Other values are returned as is.
+    pub(crate) fn coerce_for_comparison(
+        self,
+        other: Value,
+        operator: BinOp,
+    ) -> Result<(Value, Value), ValueBinaryOpError> {
+        let data_type = match (&self, &other) {
+            (Self::Timestamp(_), Self::Date(_) | Self::String(_))
+            | (Self::Date(_) | Self::String(_), Self::Timestamp(_)) => DataType::Timestamp,
+            (Self::Date(_), Self::String(_)) | (Self::String(_), Self::Date(_)) => DataType::Date,
+            _ => return Ok((self, other)),
+        };
+        match (
+            self.clone().cast(&data_type),
+            other.clone().cast(&data_type),
+        ) {
+            (Ok(left), Ok(right)) => Ok((left, right)),
+            _ => Err(ValueBinaryOpError {
+                operator,
+                values: (self, other),
+            }),
         }
     }
 
@@ -131,16 +342,172 @@ impl Value {
     pub(crate) fn sentinel_value(data_type: &DataType) -> Result<Self, RuntimeError> {
         Ok(match data_type {
             DataType::Boolean => Self::Bool(false),
-            DataType::Int(_) => Self::Int64(0),
-            DataType::UnsignedInt(_) => Self::Int64(0),
-            DataType::Float(_) => Self::Float64(0.0.into()),
-            DataType::String => Self::String("".to_owned()),
-            DataType::Bytea => Self::Binary(vec![]),
+            d if is_integer_type(d) => Self::Int64(0),
+            d if is_float_type(d) => Self::Float64(0.0.into()),
+            d if is_string_type(d) => Self::String("".to_owned()),
+            d if is_binary_type(d) => Self::Binary(vec![]),
+            DataType::Date => Self::Date(NaiveDate::default()),
+            d if is_timestamp_type(d) => Self::Timestamp(NaiveDateTime::default()),
+            DataType::Array(_) => Self::Array(vec![]),
             _ => return Err(RuntimeError::UnsupportedType(data_type.clone())),
         })
     }
 }
 
+/// Whether `s` matches the `LIKE` pattern.
+///
+/// On a mismatch after a `%`, the `%` is retried one character further along `s`. Only the last
+/// `%` needs to be retried, so this takes at most quadratic time.
+fn like_match(s: &str, pattern: &str) -> bool {
+    let s: Vec<char> = s.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    let (mut i, mut p) = (0, 0);
+    // the position in the pattern after the last `%`, and the position in `s` it was tried at.
+    let mut retry = None;
+    while i < s.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                p += 1;
+                retry = Some((p, i));
+                continue;
+            }
+            Some('_') => {
+                i += 1;
+                p += 1;
+                continue;
+            }
+            Some('\\') if pattern.get(p + 1) == Some(&s[i]) => {
+                i += 1;
+                p += 2;
+                continue;
+            }
+            Some(c) if *c != '\\' && *c == s[i] => {
+                i += 1;
+                p += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match retry {
+            Some((retry_p, retry_i)) => {
+                p = retry_p;
+                i = retry_i + 1;
+                retry = Some((retry_p, i));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '%')
+}
+
+/// The type of values which are all `NULL`, like the result of an aggregate over no rows, about
+/// which nothing else is known.
+pub(crate) fn unknown_type() -> DataType {
+    DataType::Custom(ast::ObjectName(vec![ast::Ident::new("unknown")]))
+}
+
+/// Whether values of the type are stored as [`Value::Int64`].
+fn is_integer_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::TinyInt(_)
+            | DataType::SmallInt(_)
+            | DataType::Int(_)
+            | DataType::BigInt(_)
+            | DataType::UnsignedTinyInt(_)
+            | DataType::UnsignedSmallInt(_)
+            | DataType::UnsignedInt(_)
+            | DataType::UnsignedBigInt(_)
+    )
+}
+
+/// Whether values of the type are stored as [`Value::Float64`].
+fn is_float_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Float(_) | DataType::Real | DataType::Double
+    )
+}
+
+/// Whether values of the type are stored as [`Value::String`].
+fn is_string_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::String
+            | DataType::Text
+            | DataType::Char(_)
+            | DataType::Varchar(_)
+            | DataType::Nvarchar(_)
+            | DataType::Clob(_)
+    )
+}
+
+/// Whether values of the type are stored as [`Value::Binary`].
+fn is_binary_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Bytea | DataType::Binary(_) | DataType::Varbinary(_) | DataType::Blob(_)
+    )
+}
+
+/// Whether values of the type are stored as [`Value::Timestamp`].
+fn is_timestamp_type(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Timestamp | DataType::Datetime)
+}
+
+/// Parse a date like `2023-01-31`.
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok()
+}
+
+/// Parse a timestamp like `2023-01-31 13:45:00`, optionally with fractional seconds.
+///
+/// The date and time may also be separated by a `T`, and a date alone is taken as its midnight.
+fn parse_timestamp(s: &str) -> Option<NaiveDateTime> {
+    let s = s.trim();
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| parse_date(s).and_then(|d| d.and_hms_opt(0, 0, 0)))
+}
+
+/// A type that can hold values of both given types, e.g. for the columns of a `UNION`.
+///
+/// Integers are promoted to floats, and the unknown type of `NULL`s to the other type. Returns
+/// `None` if there is no such type.
+pub(crate) fn common_type(left: &DataType, right: &DataType) -> Option<DataType> {
+    let unknown = unknown_type();
+    if left == right || *right == unknown {
+        Some(left.clone())
+    } else if *left == unknown {
+        Some(right.clone())
+    } else if is_integer_type(left) && is_integer_type(right) {
+        Some(DataType::BigInt(None))
+    } else if is_integer_type(left) && is_float_type(right) {
+        Some(right.clone())
+    } else if is_float_type(left) && (is_integer_type(right) || is_float_type(right)) {
+        Some(left.clone())
+    } else {
+        None
+    }
+}
+
+impl Value {
+    /// Value of a typed string literal, like `DATE '2023-01-31'`.
+    pub(crate) fn from_typed_string(
+        data_type: &DataType,
+        value: String,
+    ) -> Result<Self, ValueError> {
+        Value::String(value.clone())
+            .cast(data_type)
+            .map_err(|_| ValueError {
+                reason: "Invalid literal for its type",
+                value: ast::Value::SingleQuotedString(value),
+            })
+    }
+}
+
 impl TryFrom<ast::Value> for Value {
     type Error = ValueError;
 
@@ -177,12 +544,16 @@ impl Add for Value {
 
     fn add(self, rhs: Self) -> Self::Output {
         match self {
-            Value::Null | Value::Bool(_) | Value::String(_) | Value::Binary(_) => {
-                Err(ValueBinaryOpError {
-                    operator: BinOp::Plus,
-                    values: (self, rhs),
-                })
-            }
+            Value::Null
+            | Value::Bool(_)
+            | Value::Date(_)
+            | Value::Timestamp(_)
+            | Value::String(_)
+            | Value::Binary(_)
+            | Value::Array(_) => Err(ValueBinaryOpError {
+                operator: BinOp::Plus,
+                values: (self, rhs),
+            }),
             Value::Int64(lhs) => match rhs {
                 Value::Int64(rhs) => Ok(Value::Int64(lhs + rhs)),
                 _ => Err(ValueBinaryOpError {
@@ -206,12 +577,16 @@ impl Sub for Value {
 
     fn sub(self, rhs: Self) -> Self::Output {
         match self {
-            Value::Null | Value::Bool(_) | Value::String(_) | Value::Binary(_) => {
-                Err(ValueBinaryOpError {
-                    operator: BinOp::Minus,
-                    values: (self, rhs),
-                })
-            }
+            Value::Null
+            | Value::Bool(_)
+            | Value::Date(_)
+            | Value::Timestamp(_)
+            | Value::String(_)
+            | Value::Binary(_)
+            | Value::Array(_) => Err(ValueBinaryOpError {
+                operator: BinOp::Minus,
+                values: (self, rhs),
+            }),
             Value::Int64(lhs) => match rhs {
                 Value::Int64(rhs) => Ok(Value::Int64(lhs - rhs)),
                 _ => Err(ValueBinaryOpError {
@@ -235,12 +610,16 @@ impl Mul for Value {
 
     fn mul(self, rhs: Self) -> Self::Output {
         match self {
-            Value::Null | Value::Bool(_) | Value::String(_) | Value::Binary(_) => {
-                Err(ValueBinaryOpError {
-                    operator: BinOp::Multiply,
-                    values: (self, rhs),
-                })
-            }
+            Value::Null
+            | Value::Bool(_)
+            | Value::Date(_)
+            | Value::Timestamp(_)
+            | Value::String(_)
+            | Value::Binary(_)
+            | Value::Array(_) => Err(ValueBinaryOpError {
+                operator: BinOp::Multiply,
+                values: (self, rhs),
+            }),
             Value::Int64(lhs) => match rhs {
                 Value::Int64(rhs) => Ok(Value::Int64(lhs * rhs)),
                 _ => Err(ValueBinaryOpError {
@@ -264,12 +643,16 @@ impl Div for Value {
 
     fn div(self, rhs: Self) -> Self::Output {
         match self {
-            Value::Null | Value::Bool(_) | Value::String(_) | Value::Binary(_) => {
-                Err(ValueBinaryOpError {
-                    operator: BinOp::Divide,
-                    values: (self, rhs),
-                })
-            }
+            Value::Null
+            | Value::Bool(_)
+            | Value::Date(_)
+            | Value::Timestamp(_)
+            | Value::String(_)
+            | Value::Binary(_)
+            | Value::Array(_) => Err(ValueBinaryOpError {
+                operator: BinOp::Divide,
+                values: (self, rhs),
+            }),
             Value::Int64(lhs) => match rhs {
                 Value::Int64(rhs) => Ok(Value::Int64(lhs / rhs)),
                 _ => Err(ValueBinaryOpError {
@@ -293,12 +676,16 @@ impl Rem for Value {
 
     fn rem(self, rhs: Self) -> Self::Output {
         match self {
-            Value::Null | Value::Bool(_) | Value::String(_) | Value::Binary(_) => {
-                Err(ValueBinaryOpError {
-                    operator: BinOp::Modulo,
-                    values: (self, rhs),
-                })
-            }
+            Value::Null
+            | Value::Bool(_)
+            | Value::Date(_)
+            | Value::Timestamp(_)
+            | Value::String(_)
+            | Value::Binary(_)
+            | Value::Array(_) => Err(ValueBinaryOpError {
+                operator: BinOp::Modulo,
+                values: (self, rhs),
+            }),
             Value::Int64(lhs) => match rhs {
                 Value::Int64(rhs) => Ok(Value::Int64(lhs % rhs)),
                 _ => Err(ValueBinaryOpError {
@@ -322,12 +709,16 @@ impl Neg for Value {
 
     fn neg(self) -> Self::Output {
         match self {
-            Value::Null | Value::Bool(_) | Value::String(_) | Value::Binary(_) => {
-                Err(ValueUnaryOpError {
-                    operator: UnOp::Minus,
-                    value: self,
-                })
-            }
+            Value::Null
+            | Value::Bool(_)
+            | Value::Date(_)
+            | Value::Timestamp(_)
+            | Value::String(_)
+            | Value::Binary(_)
+            | Value::Array(_) => Err(ValueUnaryOpError {
+                operator: UnOp::Minus,
+                value: self,
+            }),
             Value::Int64(lhs) => Ok(Value::Int64(-lhs)),
             Value::Float64(lhs) => Ok(Value::Float64(-lhs)),
         }
@@ -340,6 +731,8 @@ impl Not for Value {
     fn not(self) -> Self::Output {
         match self {
             Value::Bool(lhs) => Ok(Value::Bool(!lhs)),
+            // not unknown is still unknown
+            Value::Null => Ok(Value::Null),
             _ => Err(ValueUnaryOpError {
                 operator: UnOp::Not,
                 value: self,
@@ -382,6 +775,23 @@ impl Display for ValueUnaryOpError {
     }
 }
 
+/// Error converting a [`Value`] to another type.
+#[derive(Debug, PartialEq)]
+pub struct ValueCastError {
+    pub value: Value,
+    pub data_type: DataType,
+}
+
+impl Display for ValueCastError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "ValueCastError: cannot cast '{:?}' to {}",
+            self.value, self.data_type
+        )
+    }
+}
+
 /// Error in the value of a [`Value`].
 #[derive(Debug, PartialEq)]
 pub struct ValueError {
@@ -397,11 +807,12 @@ impl Display for ValueError {
 
 #[cfg(test)]
 mod tests {
-    use sqlparser::ast;
+    use chrono::NaiveDate;
+    use sqlparser::ast::{self, DataType};
 
-    use crate::value::ValueError;
+    use crate::{expr::BinOp, value::ValueError};
 
-    use super::Value;
+    use super::{common_type, Value, ValueCastError};
 
     #[test]
     fn create_value() {
@@ -410,6 +821,145 @@ mod tests {
         assert!(value != Value::String("test".to_owned()));
     }
 
+    #[test]
+    fn truthiness() {
+        assert_eq!(Value::Bool(true).is_truthy(), Some(true));
+        assert_eq!(Value::Bool(false).is_truthy(), Some(false));
+        assert_eq!(Value::Int64(1).is_truthy(), Some(true));
+        assert_eq!(Value::Int64(-3).is_truthy(), Some(true));
+        assert_eq!(Value::Int64(0).is_truthy(), Some(false));
+        assert_eq!(Value::Float64(0.5.into()).is_truthy(), Some(true));
+        assert_eq!(Value::Float64(0.0.into()).is_truthy(), Some(false));
+        assert_eq!(Value::Null.is_truthy(), Some(false));
+        assert_eq!(Value::String("true".to_owned()).is_truthy(), None);
+    }
+
+    #[test]
+    fn type_promotion() {
+        assert_eq!(
+            common_type(&DataType::Int(None), &DataType::Int(None)),
+            Some(DataType::Int(None))
+        );
+        assert_eq!(
+            common_type(&DataType::Int(None), &DataType::Float(None)),
+            Some(DataType::Float(None))
+        );
+        assert_eq!(
+            common_type(&DataType::Double, &DataType::BigInt(None)),
+            Some(DataType::Double)
+        );
+        assert_eq!(common_type(&DataType::Int(None), &DataType::String), None);
+
+        assert_eq!(
+            Value::Int64(2).promote_to(&DataType::Float(None)),
+            Value::Float64(2.0.into())
+        );
+        assert_eq!(
+            Value::Int64(2).promote_to(&DataType::Int(None)),
+            Value::Int64(2)
+        );
+        assert_eq!(Value::Null.promote_to(&DataType::Float(None)), Value::Null);
+
+        assert!(Value::Int64(2).fits_type(&DataType::BigInt(None)));
+        assert!(Value::Int64(2).fits_type(&DataType::Double));
+        assert!(!Value::Float64(2.0.into()).fits_type(&DataType::Int(None)));
+        assert!(Value::String("a".to_owned()).fits_type(&DataType::Varchar(None)));
+        assert!(!Value::String("a".to_owned()).fits_type(&DataType::Boolean));
+        assert!(Value::Null.fits_type(&DataType::Boolean));
+    }
+
+    #[test]
+    fn arrays() {
+        let array = Value::Array(vec![Value::Null, Value::Int64(1), Value::Int64(2)]);
+        assert_eq!(array.to_string(), "[NULL, 1, 2]");
+        assert_eq!(
+            array.data_type(),
+            DataType::Array(Box::new(DataType::Int(None)))
+        );
+        assert!(array.fits_type(&DataType::Array(Box::new(DataType::BigInt(None)))));
+        assert!(!array.fits_type(&DataType::Int(None)));
+        assert_eq!(
+            array.clone().cast(&DataType::String),
+            Ok(Value::String("[NULL, 1, 2]".to_owned()))
+        );
+        assert_eq!(
+            array.cast(&DataType::Array(Box::new(DataType::Float(None)))),
+            Ok(Value::Array(vec![
+                Value::Null,
+                Value::Float64(1.0.into()),
+                Value::Float64(2.0.into())
+            ]))
+        );
+    }
+
+    #[test]
+    fn dates() {
+        let date = NaiveDate::from_ymd_opt(2023, 1, 31).unwrap();
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+
+        assert_eq!(
+            Value::String(" 2023-01-31 ".to_owned()).cast(&DataType::Date),
+            Ok(Value::Date(date))
+        );
+        assert_eq!(
+            Value::String("2023-01-31 13:45:10.5".to_owned()).cast(&DataType::Timestamp),
+            Ok(Value::Timestamp(
+                date.and_hms_milli_opt(13, 45, 10, 500).unwrap()
+            ))
+        );
+        assert_eq!(
+            Value::String("2023-01-31T13:45:10".to_owned()).cast(&DataType::Datetime),
+            Ok(Value::Timestamp(date.and_hms_opt(13, 45, 10).unwrap()))
+        );
+        assert_eq!(
+            Value::String("2023-01-31".to_owned()).cast(&DataType::Timestamp),
+            Ok(Value::Timestamp(midnight))
+        );
+        assert!(Value::String("2023-02-30".to_owned())
+            .cast(&DataType::Date)
+            .is_err());
+        assert_eq!(
+            Value::Timestamp(midnight).cast(&DataType::Date),
+            Ok(Value::Date(date))
+        );
+        assert_eq!(
+            Value::Date(date).cast(&DataType::Text),
+            Ok(Value::String("2023-01-31".to_owned()))
+        );
+
+        assert_eq!(
+            Value::from_typed_string(&DataType::Date, "2023-01-31".to_owned()),
+            Ok(Value::Date(date))
+        );
+        assert_eq!(
+            Value::from_typed_string(&DataType::Date, "yesterday".to_owned()),
+            Err(ValueError {
+                reason: "Invalid literal for its type",
+                value: ast::Value::SingleQuotedString("yesterday".to_owned())
+            })
+        );
+
+        assert_eq!(
+            Value::String("2023-01-31".to_owned()).promote_to(&DataType::Date),
+            Value::Date(date)
+        );
+        assert_eq!(
+            Value::String("soon".to_owned()).promote_to(&DataType::Date),
+            Value::String("soon".to_owned())
+        );
+        assert!(Value::Date(date).fits_type(&DataType::Date));
+        assert!(!Value::Date(date).fits_type(&DataType::Timestamp));
+
+        assert!(Value::Date(date) < Value::Date(date.succ_opt().unwrap()));
+        assert_eq!(
+            Value::Date(date).coerce_for_comparison(Value::Timestamp(midnight), BinOp::Equal),
+            Ok((Value::Timestamp(midnight), Value::Timestamp(midnight)))
+        );
+        assert!(Value::Date(date)
+            .coerce_for_comparison(Value::String("never".to_owned()), BinOp::LessThan)
+            .is_err());
+    }
+
     #[test]
     fn conversion_from_ast() {
         assert_eq!(Value::try_from(ast::Value::Null), Ok(Value::Null));
@@ -452,4 +1002,68 @@ mod tests {
             })
         )
     }
+
+    #[test]
+    fn nan() {
+        let nan = Value::Float64(f64::NAN.into());
+        let negative_nan = Value::Float64((-f64::NAN).into());
+        let other_nan = Value::Float64(f64::from_bits(f64::NAN.to_bits() | 1).into());
+
+        assert_eq!(nan, negative_nan);
+        assert_eq!(nan, other_nan);
+        assert_eq!(nan.cmp(&other_nan), std::cmp::Ordering::Equal);
+        assert!(nan > Value::Float64(f64::INFINITY.into()));
+    }
+
+    #[test]
+    fn cast() {
+        let string = |s: &str| Value::String(s.to_owned());
+        let float = |f: f64| Value::Float64(f.into());
+
+        assert_eq!(Value::Null.cast(&DataType::Boolean), Ok(Value::Null));
+
+        assert_eq!(float(2.5).cast(&DataType::Int(None)), Ok(Value::Int64(3)));
+        assert_eq!(
+            float(-2.4).cast(&DataType::BigInt(None)),
+            Ok(Value::Int64(-2))
+        );
+        assert_eq!(Value::Int64(2).cast(&DataType::Double), Ok(float(2.0)));
+        assert_eq!(
+            Value::Bool(true).cast(&DataType::Int(None)),
+            Ok(Value::Int64(1))
+        );
+
+        assert_eq!(
+            string(" 42 ").cast(&DataType::Int(None)),
+            Ok(Value::Int64(42))
+        );
+        assert_eq!(string("1.5").cast(&DataType::Float(None)), Ok(float(1.5)));
+        assert_eq!(Value::Int64(42).cast(&DataType::String), Ok(string("42")));
+        assert_eq!(float(1.5).cast(&DataType::Varchar(None)), Ok(string("1.5")));
+
+        assert_eq!(
+            string("TRUE").cast(&DataType::Boolean),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            string("off").cast(&DataType::Boolean),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            Value::Int64(0).cast(&DataType::Boolean),
+            Ok(Value::Bool(false))
+        );
+
+        assert_eq!(
+            string("abc").cast(&DataType::Int(None)),
+            Err(ValueCastError {
+                value: string("abc"),
+                data_type: DataType::Int(None)
+            })
+        );
+        assert!(string("maybe").cast(&DataType::Boolean).is_err());
+        assert!(float(f64::INFINITY).cast(&DataType::Int(None)).is_err());
+        assert!(float(1e300).cast(&DataType::Int(None)).is_err());
+        assert!(Value::Binary(vec![1]).cast(&DataType::Int(None)).is_err());
+    }
 }
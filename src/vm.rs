@@ -3,22 +3,26 @@
 //! See [`VirtualMachine`] and [`Register`].
 use hashbrown::HashMap;
 use permutation::permutation;
-use sqlparser::ast::DataType;
+use sqlparser::ast::{ColumnOption, DataType, Statement};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt::Display;
+use std::sync::Arc;
 
 use sqlparser::parser::ParserError;
 
-use crate::codegen::{codegen_ast, CodegenError};
+use crate::codegen::{codegen_ast, expand_lateral_aliases, push_down_filters, CodegenError};
 use crate::column::Column;
-use crate::expr::eval::ExprExecError;
-use crate::expr::Expr;
-use crate::ic::{Instruction, IntermediateCode};
-use crate::identifier::{ColumnRef, TableRef};
-use crate::parser::parse;
+use crate::expr::agg::{CustomAggregate, CustomAggregates};
+use crate::expr::eval::{EvalOptions, ExprExecError};
+use crate::expr::{BinOp, Expr, ExprError};
+use crate::ic::{Assignment, Instruction, IntermediateCode, JoinType, OrderKey};
+use crate::identifier::{identifiers_eq, ColumnRef, QuoteStyle, TableRef};
+use crate::parser::{parse, StatementKind};
 use crate::schema::Schema;
-use crate::table::{Row, RowShared, Table};
-use crate::value::Value;
+use crate::table::{IntoRows, Row, RowLike, RowShared, Table};
+use crate::value::{common_type, Value};
 use crate::{BoundedString, Database};
 
 const DEFAULT_DATABASE_NAME: &str = "default";
@@ -42,6 +46,7 @@ impl Display for RegisterIndex {
 
 /// An index that can be used as a reference to a table.
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct TableIndex(usize);
 
 impl TableIndex {
@@ -51,12 +56,145 @@ impl TableIndex {
     }
 }
 
+impl Display for TableIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// How non-boolean values are treated in a `WHERE` (or `HAVING`) clause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Only booleans are allowed. Anything else is an error.
+    Strict,
+    /// Values are coerced using [`Value::is_truthy`], e.g. non-zero numbers are true.
+    Coercing,
+}
+
+impl FilterMode {
+    /// Whether a row for which the condition evaluated to `val` is kept.
+    ///
+    /// `None` if the value is not allowed as a condition in this mode.
+    fn keeps(self, val: &Value) -> Option<bool> {
+        match (self, val) {
+            (_, Value::Bool(b)) => Some(*b),
+            // unknown, so the row is not kept
+            (_, Value::Null) => Some(false),
+            (Self::Coercing, _) => val.is_truthy(),
+            (Self::Strict, _) => None,
+        }
+    }
+}
+
+impl Default for FilterMode {
+    fn default() -> Self {
+        Self::Strict
+    }
+}
+
+/// How strings longer than the length of their column (e.g. `VARCHAR(10)`) are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringLengthMode {
+    /// The length is not checked and strings are stored as they are.
+    Unchecked,
+    /// Strings are truncated to the length, with a [`Warning::StringTruncated`].
+    Truncate,
+    /// Storing a string which is too long is an error.
+    Strict,
+}
+
+impl Default for StringLengthMode {
+    fn default() -> Self {
+        Self::Unchecked
+    }
+}
+
+/// A problem which does not stop a statement from executing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// A string was cut short to fit its column.
+    StringTruncated {
+        col_name: BoundedString,
+        max_len: u64,
+        value: Value,
+    },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StringTruncated {
+                col_name,
+                max_len,
+                value,
+            } => write!(
+                f,
+                "Value '{}' was truncated to {} characters to fit in column '{}'",
+                value, max_len, col_name
+            ),
+        }
+    }
+}
+
+/// Default maximum number of columns in a table, the same as MySQL's.
+///
+/// See [`VirtualMachine::set_max_columns`].
+pub const DEFAULT_MAX_COLUMNS: usize = 4096;
+
+/// State of the database at the start of a transaction, which is restored if it is rolled back.
+#[derive(Debug, Clone)]
+struct Transaction {
+    database: Database,
+    /// Copies of the stored tables as they were before the transaction first changed them. Tables
+    /// which the transaction has not changed are not copied.
+    ///
+    /// `None` stands for a table which is not in the VM. This is only the case while the
+    /// transaction's state is swapped in, for a table which it dropped.
+    tables: HashMap<TableIndex, Option<Table>>,
+}
+
+/// The contents of a database as saved to disk: its schemas and their tables.
+#[cfg(feature = "persistence")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedDatabase {
+    database: Database,
+    tables: Vec<(TableIndex, Table)>,
+}
+
 /// Executor of an SQL query.
+#[derive(Clone)]
 pub struct VirtualMachine {
     database: Database,
     registers: HashMap<RegisterIndex, Register>,
     tables: HashMap<TableIndex, Table>,
     last_table_index: TableIndex,
+    filter_mode: FilterMode,
+    strict_create: bool,
+    lateral_aliases: bool,
+    string_length_mode: StringLengthMode,
+    max_columns: usize,
+    bool_int_coercion: bool,
+    autocommit: bool,
+    /// The transaction in progress, if any.
+    transaction: Option<Transaction>,
+    /// Warnings from the statements executed since the last call to
+    /// [`execute`](`VirtualMachine::execute`) (or one of its variants).
+    warnings: Vec<Warning>,
+    search_path: Vec<BoundedString>,
+    quote_style: QuoteStyle,
+    /// Aggregate functions registered with
+    /// [`register_aggregate`](`VirtualMachine::register_aggregate`).
+    aggregates: CustomAggregates,
+    /// Tables which hold a subset of the rows of another table, i.e. filtered tables, mapped to the
+    /// table they were created from.
+    ///
+    /// Used to write updates through to the original table.
+    derived_from: HashMap<TableIndex, TableIndex>,
+    /// Tables made by projections, mapped to what each of their columns was projected from (see
+    /// [`projection_source`]).
+    ///
+    /// Used to tell whether output columns with the same name hold the same values.
+    projected_from: HashMap<TableIndex, Vec<Expr>>,
 }
 
 impl VirtualMachine {
@@ -66,7 +204,202 @@ impl VirtualMachine {
             registers: Default::default(),
             tables: Default::default(),
             last_table_index: Default::default(),
+            filter_mode: Default::default(),
+            strict_create: false,
+            lateral_aliases: false,
+            string_length_mode: Default::default(),
+            max_columns: DEFAULT_MAX_COLUMNS,
+            bool_int_coercion: false,
+            autocommit: true,
+            transaction: None,
+            warnings: Default::default(),
+            search_path: Default::default(),
+            quote_style: QuoteStyle::default(),
+            aggregates: Default::default(),
+            derived_from: Default::default(),
+            projected_from: Default::default(),
+        }
+    }
+
+    /// Set how non-boolean values are treated when filtering. Defaults to
+    /// [`FilterMode::Strict`].
+    pub fn set_filter_mode(&mut self, mode: FilterMode) -> &mut Self {
+        self.filter_mode = mode;
+        self
+    }
+
+    /// Set whether `CREATE TABLE` rejects definitions which can never be satisfied, like a
+    /// `NOT NULL` column with a `DEFAULT` of `NULL`. Off by default, in which case such
+    /// definitions only fail when a row is inserted.
+    pub fn set_strict_create(&mut self, strict: bool) -> &mut Self {
+        self.strict_create = strict;
+        self
+    }
+
+    /// Set whether an expression in a `SELECT` list can refer to the aliases of the expressions
+    /// before it, as in `SELECT a * 2 AS d, d + 1 AS e FROM t`. This is an extension to SQL and is
+    /// off by default.
+    ///
+    /// When on, such an alias takes precedence over a source column of the same name.
+    pub fn set_lateral_aliases(&mut self, lateral: bool) -> &mut Self {
+        self.lateral_aliases = lateral;
+        self
+    }
+
+    /// Set how strings which are too long for their column are stored. Defaults to
+    /// [`StringLengthMode::Unchecked`].
+    pub fn set_string_length_mode(&mut self, mode: StringLengthMode) -> &mut Self {
+        self.string_length_mode = mode;
+        self
+    }
+
+    /// Set the maximum number of columns a table can be created with. Defaults to
+    /// [`DEFAULT_MAX_COLUMNS`].
+    pub fn set_max_columns(&mut self, max_columns: usize) -> &mut Self {
+        self.max_columns = max_columns;
+        self
+    }
+
+    /// Set whether booleans can be compared with integers, as in `WHERE bool_col = 1`, with `true`
+    /// taken as `1` and `false` as `0`. Off by default, in which case such comparisons are an
+    /// error.
+    pub fn set_bool_int_coercion(&mut self, coercion: bool) -> &mut Self {
+        self.bool_int_coercion = coercion;
+        self
+    }
+
+    /// Set whether each statement is committed as soon as it is executed. On by default.
+    ///
+    /// When off, the first statement starts a transaction, and its changes and those of the
+    /// statements after it are only committed by a `COMMIT`, or undone by a `ROLLBACK`. Until
+    /// then, they are only visible to this VM and not to
+    /// [`execute_committed`](`Self::execute_committed`). Turning autocommit back on commits the
+    /// transaction in progress.
+    ///
+    /// When on, a transaction can still be started explicitly with `BEGIN`.
+    pub fn set_autocommit(&mut self, autocommit: bool) -> &mut Self {
+        self.autocommit = autocommit;
+        if autocommit {
+            self.transaction = None;
+        }
+        self
+    }
+
+    /// Set the schemas that unqualified table names are looked up in, in order.
+    ///
+    /// The first schema that has a table with the name is used. Schemas which do not exist are
+    /// skipped. New tables without a schema name are created in the first existing schema.
+    ///
+    /// An empty search path (the default), or one without any existing schemas, means only the
+    /// default schema is used.
+    pub fn set_search_path(&mut self, schemas: Vec<BoundedString>) -> &mut Self {
+        self.search_path = schemas;
+        self
+    }
+
+    /// Set how identifiers are quoted in the SQL rendered by
+    /// [`dump_schema`](`Self::dump_schema`), including the names inside `CHECK` and `DEFAULT`
+    /// expressions. Defaults to [`QuoteStyle::DoubleQuote`].
+    ///
+    /// Names which are keywords, like a column named `select`, can only be parsed back when they
+    /// are quoted. `EXPLAIN` output and error messages are not SQL and are not affected.
+    pub fn set_quote_style(&mut self, style: QuoteStyle) -> &mut Self {
+        self.quote_style = style;
+        self
+    }
+
+    /// Register an aggregate function which can then be called by name (case-insensitively) in
+    /// queries, like the built-in ones. Registering a name again replaces the earlier function.
+    /// Built-in aggregates take precedence over registered ones of the same name.
+    ///
+    /// The function takes a single argument. Its state starts out as the value returned by `init`,
+    /// `accumulate` folds each of the group's values which is not `NULL` into the state, and
+    /// `finalize` turns the final state into the result.
+    pub fn register_aggregate(
+        &mut self,
+        name: &str,
+        init: impl Fn() -> Value + Send + Sync + 'static,
+        accumulate: impl Fn(Value, Value) -> Result<Value, ExprExecError> + Send + Sync + 'static,
+        finalize: impl Fn(Value) -> Value + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.aggregates.insert(
+            name.to_lowercase(),
+            CustomAggregate {
+                init: Arc::new(init),
+                accumulate: Arc::new(accumulate),
+                finalize: Arc::new(finalize),
+            },
+        );
+        self
+    }
+
+    /// All the tables in the database, sorted by schema name and then by table name.
+    ///
+    /// The tables themselves are kept in a hash map, so this is the order to rely on when
+    /// enumerating them.
+    pub fn list_tables(&self) -> Vec<TableRef> {
+        let mut tables: Vec<TableRef> = self
+            .database
+            .schemas()
+            .iter()
+            .flat_map(|schema| {
+                schema.tables().iter().map(|table_index| TableRef {
+                    schema_name: Some(*schema.name()),
+                    table_name: *self.tables[table_index].name(),
+                })
+            })
+            .collect();
+        tables.sort_by_key(|t| (t.schema_name, t.table_name));
+        tables
+    }
+
+    /// Render the structure of the database as SQL statements which recreate it.
+    ///
+    /// This includes schemas and tables along with the options of their columns, like defaults and
+    /// check constraints. Data is not included. Tables in the default schema are not qualified with
+    /// the schema's name. Names are quoted as set by [`set_quote_style`](`Self::set_quote_style`).
+    pub fn dump_schema(&self) -> String {
+        let style = self.quote_style;
+        let default_schema_name = self.database.default_schema().name();
+
+        let mut sql = String::new();
+        for schema in self.database.schemas() {
+            let is_default = identifiers_eq(schema.name(), default_schema_name);
+            if !is_default {
+                sql.push_str(&format!("CREATE SCHEMA {};\n", style.quote(schema.name())));
+            }
+
+            for table_index in schema.tables() {
+                let table = &self.tables[table_index];
+                let name = TableRef {
+                    schema_name: (!is_default).then(|| *schema.name()),
+                    table_name: *table.name(),
+                };
+                let columns = table
+                    .columns()
+                    .map(|col| {
+                        let mut def = format!("{} {}", style.quote(col.name()), col.data_type());
+                        for option in col.options() {
+                            let mut option = option.clone();
+                            if let ColumnOption::Check(expr) | ColumnOption::Default(expr) =
+                                &mut option.option
+                            {
+                                style.requote_expr(expr);
+                            }
+                            def.push_str(&format!(" {}", option));
+                        }
+                        def
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                sql.push_str(&format!(
+                    "CREATE TABLE {} ({});\n",
+                    name.quoted(style),
+                    columns
+                ));
+            }
         }
+        sql
     }
 
     /// Inserts a value for the register at the given index.
@@ -80,7 +413,11 @@ impl VirtualMachine {
     }
 
     /// Creates a new table with a temp name and returns its index.
-    fn new_temp_table(&mut self) -> TableIndex {
+    ///
+    /// The table is not added to any schema. It can be populated using
+    /// [`table_mut`](`Self::table_mut`) and queried without SQL using methods like
+    /// [`filter_table`](`Self::filter_table`).
+    pub fn new_temp_table(&mut self) -> TableIndex {
         let index = self.last_table_index.next_index();
         self.tables.insert(index, Table::new_temp(index.0));
         self.last_table_index = index;
@@ -97,41 +434,488 @@ impl VirtualMachine {
     }
 
     /// Get a reference to an existing table at the given index.
-    fn table(&self, index: &TableIndex) -> Option<&Table> {
+    pub fn table(&self, index: &TableIndex) -> Option<&Table> {
         self.tables.get(index)
     }
 
+    /// Get a mutable reference to an existing table at the given index.
+    pub fn table_mut(&mut self, index: &TableIndex) -> Option<&mut Table> {
+        self.save_for_rollback(index);
+        self.tables.get_mut(index)
+    }
+
+    /// Filter the table at the given index using `expr`, without going through SQL.
+    ///
+    /// The table itself is not modified. The matching rows are returned as a new table.
+    pub fn filter_table(&mut self, index: &TableIndex, expr: Expr) -> Result<Table, RuntimeError> {
+        let table_reg_index = self.load_table(index)?;
+        self.execute_table_ic(IntermediateCode {
+            instrs: vec![
+                Instruction::Filter {
+                    index: table_reg_index,
+                    expr,
+                },
+                Instruction::Return {
+                    index: table_reg_index,
+                },
+            ],
+        })
+    }
+
+    /// Project the given expressions (with optional aliases) out of the table at the given index,
+    /// without going through SQL.
+    ///
+    /// The table itself is not modified. The projection is returned as a new table.
+    pub fn project_table(
+        &mut self,
+        index: &TableIndex,
+        exprs: Vec<(Expr, Option<BoundedString>)>,
+    ) -> Result<Table, RuntimeError> {
+        let table_reg_index = self.load_table(index)?;
+        let output_reg_index = table_reg_index.next_index();
+
+        let mut instrs = vec![Instruction::Empty {
+            index: output_reg_index,
+        }];
+        for (expr, alias) in exprs {
+            instrs.push(Instruction::Project {
+                input: table_reg_index,
+                output: output_reg_index,
+                expr,
+                alias,
+            });
+        }
+        instrs.push(Instruction::Return {
+            index: output_reg_index,
+        });
+
+        self.execute_table_ic(IntermediateCode { instrs })
+    }
+
+    /// Generate the intermediate code for a statement, taking the VM's settings into account.
+    fn codegen(&self, stmt: &Statement) -> Result<IntermediateCode, CodegenError> {
+        let mut ic = codegen_ast(stmt)?;
+        if self.lateral_aliases {
+            expand_lateral_aliases(&mut ic);
+        }
+        push_down_filters(&mut ic);
+        Ok(ic)
+    }
+
+    /// Store a reference to the table at the given index in the first register.
+    fn load_table(&mut self, index: &TableIndex) -> Result<RegisterIndex, RuntimeError> {
+        if !self.tables.contains_key(index) {
+            return Err(RuntimeError::TableIndexNotFound(*index));
+        }
+
+        let table_reg_index = RegisterIndex::default();
+        self.insert_register(table_reg_index, Register::TableRef(*index));
+        Ok(table_reg_index)
+    }
+
+    /// Executes intermediate code which is expected to return a table.
+    fn execute_table_ic(&mut self, ic: IntermediateCode) -> Result<Table, RuntimeError> {
+        match self.execute_ic(&ic)? {
+            Some(table) => Ok(table),
+            None => Err(RuntimeError::EmptyRegister(RegisterIndex::default())),
+        }
+    }
+
+    /// Add a column computed by a projection to the output table.
+    fn add_projected_column(
+        out_table: &mut Table,
+        inp_table_name: &BoundedString,
+        values: Vec<Value>,
+        data_type: DataType,
+        expr: &Expr,
+        alias: Option<BoundedString>,
+    ) -> Result<(), RuntimeError> {
+        if !out_table.has_no_columns() && values.len() != out_table.num_rows() {
+            return Err(RuntimeError::ProjectTableSizeMismatch {
+                inp_table_name: *inp_table_name,
+                inp_table_len: values.len(),
+                out_table_name: out_table.name().to_owned(),
+                out_table_len: out_table.num_rows(),
+            });
+        }
+
+        let new_col = Column::new(
+            projected_column_name(out_table, expr, alias),
+            data_type,
+            vec![],
+            false,
+        );
+        out_table.add_column_with_data(new_col, values)?;
+        Ok(())
+    }
+
     /// Drop an existing table from the VM.
     ///
     /// Note: does NOT remove the table from the schema (if it was added to a schema).
     // TODO: ensure that IC gen calls this when a temp table is created.
     fn drop_table(&mut self, index: &TableIndex) {
         self.tables.remove(index);
+        self.derived_from.remove(index);
+        self.projected_from.remove(index);
+    }
+
+    /// Whether the table belongs to a schema, as opposed to being made by the code itself.
+    fn is_stored(&self, index: &TableIndex) -> bool {
+        self.database
+            .schemas()
+            .iter()
+            .any(|schema| schema.tables().contains(index))
     }
 
     /// Executes the given SQL.
     pub fn execute(&mut self, code: &str) -> Result<Option<Table>, ExecutionError> {
+        Ok(self.execute_traced(code)?.0)
+    }
+
+    /// Executes the given SQL and iterates over the rows of the result of the last statement,
+    /// without copying them.
+    ///
+    /// The statements are run before the iterator is returned, so errors are reported then. There
+    /// are no rows if the last statement does not return a table.
+    pub fn execute_iter(&mut self, code: &str) -> Result<IntoRows, ExecutionError> {
+        Ok(match self.execute(code)? {
+            Some(table) => table.into_rows(),
+            None => IntoRows::empty(),
+        })
+    }
+
+    /// Executes the given SQL, also returning the intermediate code that was generated and run
+    /// for each statement.
+    ///
+    /// If a statement fails, the code for the statements after it is not generated.
+    pub fn execute_traced(
+        &mut self,
+        code: &str,
+    ) -> Result<(Option<Table>, Vec<IntermediateCode>), ExecutionError> {
+        self.warnings.clear();
         let ast = parse(code)?;
         let mut ret = None;
+        let mut ics = Vec::with_capacity(ast.len());
         for stmt in ast {
-            let ic = codegen_ast(&stmt)?;
+            let ic = self.codegen(&stmt)?;
             ret = self.execute_ic(&ic)?;
+            ics.push(ic);
+        }
+        Ok((ret, ics))
+    }
+
+    /// Executes the given SQL, also returning the warnings produced by all of its statements.
+    ///
+    /// Warnings do not stop execution. If a statement fails, only the error is returned.
+    pub fn execute_with_warnings(
+        &mut self,
+        code: &str,
+    ) -> Result<(Option<Table>, Vec<Warning>), ExecutionError> {
+        let (ret, _) = self.execute_traced(code)?;
+        Ok((ret, std::mem::take(&mut self.warnings)))
+    }
+
+    /// Executes the given SQL once for each row of parameters.
+    ///
+    /// The SQL is parsed and compiled only once. Its placeholders (`?`, or `$1`, `$2`, etc.) are
+    /// replaced by the values of each row in turn. Execution stops at the first error, keeping the
+    /// changes made for the earlier rows.
+    pub fn execute_batch(
+        &mut self,
+        code: &str,
+        params: &[Vec<Value>],
+    ) -> Result<(), ExecutionError> {
+        self.warnings.clear();
+        let ast = parse(code)?;
+        let ics = ast
+            .iter()
+            .map(|stmt| self.codegen(stmt))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let num_params = ics.iter().map(|ic| ic.num_params()).max().unwrap_or(0);
+        if let Some(row) = params.iter().find(|row| row.len() != num_params) {
+            return Err(RuntimeError::ParameterCountMismatch {
+                expected: num_params,
+                got: row.len(),
+            }
+            .into());
+        }
+
+        for row in params {
+            for ic in &ics {
+                self.execute_ic(&ic.bind(row))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes the given SQL with its placeholders (`?`, or `$1`, `$2`, etc.) replaced by the
+    /// given values.
+    ///
+    /// The values are bound after parsing, so they are never read as SQL. The number of values
+    /// must match the number of placeholders.
+    pub fn execute_prepared(
+        &mut self,
+        code: &str,
+        params: &[Value],
+    ) -> Result<Option<Table>, ExecutionError> {
+        self.warnings.clear();
+        let ast = parse(code)?;
+        let ics = ast
+            .iter()
+            .map(|stmt| self.codegen(stmt))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let num_params = ics.iter().map(|ic| ic.num_params()).max().unwrap_or(0);
+        if params.len() != num_params {
+            return Err(RuntimeError::ParameterCountMismatch {
+                expected: num_params,
+                got: params.len(),
+            }
+            .into());
+        }
+
+        let mut ret = None;
+        for ic in &ics {
+            ret = self.execute_ic(&ic.bind(params))?;
         }
         Ok(ret)
     }
 
+    /// Executes the given SQL against the last committed state of the database, as a new
+    /// connection would see it.
+    ///
+    /// Changes made by the transaction in progress are not seen, and changes made by the given SQL
+    /// are discarded. Since they are discarded, the SQL can not control transactions itself.
+    pub fn execute_committed(&mut self, code: &str) -> Result<Option<Table>, ExecutionError> {
+        let ast = parse(code)?;
+        if let Some(stmt) = ast
+            .iter()
+            .find(|stmt| StatementKind::of(stmt) == StatementKind::TransactionControl)
+        {
+            return Err(CodegenError::UnsupportedStatement(
+                "Transaction control in execute_committed",
+                stmt.to_string(),
+            )
+            .into());
+        }
+
+        // the transaction in progress is set aside, and the SQL runs in a transaction of its own
+        // which is rolled back at the end.
+        let mut in_progress = self.transaction.take();
+        if let Some(transaction) = &mut in_progress {
+            self.swap_transaction_state(transaction);
+        }
+        let warnings = std::mem::take(&mut self.warnings);
+        let autocommit = std::mem::replace(&mut self.autocommit, true);
+        self.begin();
+
+        let mut ret = Ok(None);
+        for stmt in &ast {
+            ret = self
+                .codegen(stmt)
+                .map_err(ExecutionError::from)
+                .and_then(|ic| Ok(self.execute_ic(&ic)?));
+            if ret.is_err() {
+                break;
+            }
+        }
+
+        self.rollback();
+        self.autocommit = autocommit;
+        self.warnings = warnings;
+        if let Some(mut transaction) = in_progress {
+            self.swap_transaction_state(&mut transaction);
+            self.transaction = Some(transaction);
+        }
+        ret
+    }
+
+    /// Swap the schemas and the changed tables of the VM with the ones saved by the transaction,
+    /// i.e. with the state from before the transaction. Swapping again undoes it.
+    fn swap_transaction_state(&mut self, transaction: &mut Transaction) {
+        std::mem::swap(&mut self.database, &mut transaction.database);
+        for (index, saved) in transaction.tables.iter_mut() {
+            let current = self.tables.remove(index);
+            if let Some(table) = saved.take() {
+                self.tables.insert(*index, table);
+            }
+            *saved = current;
+        }
+    }
+
+    /// Save the database, with all of its schemas and tables, to a file.
+    ///
+    /// Only committed changes are saved. The file is overwritten if it exists. Settings of the VM
+    /// are not saved.
+    #[cfg(feature = "persistence")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "persistence")))]
+    pub fn save_to_path(&self, path: impl AsRef<std::path::Path>) -> Result<(), PersistenceError> {
+        let database = match &self.transaction {
+            Some(transaction) => &transaction.database,
+            None => &self.database,
+        };
+        let committed_table = |index: &TableIndex| match &self.transaction {
+            Some(transaction) => match transaction.tables.get(index) {
+                Some(table) => table.as_ref(),
+                None => self.tables.get(index),
+            },
+            None => self.tables.get(index),
+        };
+        let saved = SavedDatabase {
+            database: database.clone(),
+            tables: database
+                .schemas()
+                .iter()
+                .flat_map(|schema| schema.tables())
+                .map(|index| (*index, committed_table(index).unwrap().clone()))
+                .collect(),
+        };
+
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(&mut writer, &saved)
+            .map_err(|e| PersistenceError::Corrupt(e.to_string()))?;
+        std::io::Write::flush(&mut writer)?;
+        Ok(())
+    }
+
+    /// Load a database saved with [`save_to_path`](`Self::save_to_path`) into a new VM with the
+    /// default settings.
+    #[cfg(feature = "persistence")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "persistence")))]
+    pub fn load_from_path(path: impl AsRef<std::path::Path>) -> Result<Self, PersistenceError> {
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let saved: SavedDatabase = serde_json::from_reader(reader)
+            .map_err(|e| PersistenceError::Corrupt(e.to_string()))?;
+
+        let mut vm = Self::new(*saved.database.name());
+        vm.last_table_index = saved
+            .tables
+            .iter()
+            .map(|(index, _)| *index)
+            .max_by_key(|index| index.0)
+            .unwrap_or_default();
+        vm.tables = saved.tables.into_iter().collect();
+        for table in vm.tables.values_mut() {
+            table.rebuild_indexes();
+        }
+        if let Some(index) = saved
+            .database
+            .schemas()
+            .iter()
+            .flat_map(|schema| schema.tables())
+            .find(|index| !vm.tables.contains_key(*index))
+        {
+            return Err(PersistenceError::Corrupt(format!(
+                "table {} is in a schema but has no data",
+                index
+            )));
+        }
+        vm.database = saved.database;
+        Ok(vm)
+    }
+
     /// Executes the given intermediate code.
     pub fn execute_ic(&mut self, ic: &IntermediateCode) -> Result<Option<Table>, RuntimeError> {
+        if !self.autocommit {
+            self.begin();
+        }
+
         let mut ret = None;
-        for instr in &ic.instrs {
+        let res = ic.instrs.iter().try_for_each(|instr| {
             ret = self.execute_instr(instr)?;
+            Ok(())
+        });
+
+        // filtered tables are only needed by the statement that made them.
+        let filtered: Vec<_> = self.derived_from.keys().copied().collect();
+        for table_index in filtered {
+            if self.is_stored(&table_index) {
+                self.derived_from.remove(&table_index);
+            } else {
+                self.drop_table(&table_index);
+            }
+        }
+        self.projected_from.clear();
+
+        res.map(|()| ret)
+    }
+
+    /// Options for evaluating expressions, from the VM's settings.
+    fn eval_options(&self) -> EvalOptions {
+        EvalOptions {
+            bool_int_coercion: self.bool_int_coercion,
+        }
+    }
+
+    /// Start a transaction, unless there is one in progress.
+    ///
+    /// Only the schemas are copied here. Tables are copied by
+    /// [`save_for_rollback`](`Self::save_for_rollback`) as they are changed.
+    fn begin(&mut self) {
+        if self.transaction.is_none() {
+            self.transaction = Some(Transaction {
+                database: self.database.clone(),
+                tables: Default::default(),
+            });
+        }
+    }
+
+    /// Keep a copy of a table as it was at the start of the transaction in progress, if the table
+    /// existed then and this is the first time the transaction changes it.
+    ///
+    /// Must be called before each change to a stored table.
+    fn save_for_rollback(&mut self, index: &TableIndex) {
+        if let Some(transaction) = &mut self.transaction {
+            let existed = transaction
+                .database
+                .schemas()
+                .iter()
+                .any(|schema| schema.tables().contains(index));
+            if existed && !transaction.tables.contains_key(index) {
+                transaction
+                    .tables
+                    .insert(*index, self.tables.get(index).cloned());
+            }
+        }
+    }
+
+    /// Undo the changes of the transaction in progress, if there is one.
+    fn rollback(&mut self) {
+        if let Some(transaction) = self.transaction.take() {
+            // tables created by the transaction go away with it.
+            let created: Vec<_> = self
+                .database
+                .schemas()
+                .iter()
+                .flat_map(|schema| schema.tables())
+                .filter(|index| {
+                    !transaction
+                        .database
+                        .schemas()
+                        .iter()
+                        .any(|schema| schema.tables().contains(index))
+                })
+                .copied()
+                .collect();
+            for index in created {
+                self.drop_table(&index);
+            }
+
+            self.database = transaction.database;
+            for (index, table) in transaction.tables {
+                match table {
+                    Some(table) => self.tables.insert(index, table),
+                    None => self.tables.remove(&index),
+                };
+            }
         }
-        Ok(ret)
     }
 
     /// Executes the given instruction.
     fn execute_instr(&mut self, instr: &Instruction) -> Result<Option<Table>, RuntimeError> {
         let _ = &self.database;
+        let options = self.eval_options();
         match instr {
             Instruction::Value { index, value } => {
                 self.registers
@@ -140,30 +924,11 @@ impl VirtualMachine {
             Instruction::Expr { index, expr } => {
                 self.registers.insert(*index, Register::Expr(expr.clone()));
             }
-            Instruction::Source { index, name } => match name {
-                TableRef {
-                    schema_name: None,
-                    table_name: _,
-                } => {
-                    let table_index = self.find_table(self.database.default_schema(), name)?;
-                    self.registers
-                        .insert(*index, Register::TableRef(table_index));
-                }
-                TableRef {
-                    schema_name: Some(schema_name),
-                    table_name: _,
-                } => {
-                    let schema = if let Some(schema) = self.database.schema_by_name(schema_name) {
-                        schema
-                    } else {
-                        return Err(RuntimeError::SchemaNotFound(*schema_name));
-                    };
-
-                    let table_index = self.find_table(schema, name)?;
-                    self.registers
-                        .insert(*index, Register::TableRef(table_index));
-                }
-            },
+            Instruction::Source { index, name } => {
+                let table_index = self.resolve_table(name)?;
+                self.registers
+                    .insert(*index, Register::TableRef(table_index));
+            }
             Instruction::Empty { index } => {
                 let table_index = self.new_temp_table();
                 self.registers
@@ -172,9 +937,34 @@ impl VirtualMachine {
             Instruction::NonExistent { index } => {
                 self.registers.insert(*index, Register::NonExistentTable);
             }
+            Instruction::Explain { index, code } => {
+                let table_index = self.new_temp_table();
+                let table = self.tables.get_mut(&table_index).unwrap();
+                table.add_column(Column::new(
+                    "instruction".into(),
+                    DataType::String,
+                    vec![],
+                    false,
+                ));
+                for instr in &code.instrs {
+                    table.new_row(vec![Value::String(instr.to_string())]);
+                }
+                self.registers
+                    .insert(*index, Register::TableRef(table_index));
+            }
             Instruction::Return { index } => match self.registers.remove(index) {
                 None => return Err(RuntimeError::EmptyRegister(*index)),
-                Some(Register::TableRef(t)) => return Ok(Some(self.tables[&t].clone())),
+                Some(Register::TableRef(t)) => {
+                    // tables made by the code itself are not needed anymore, so they are moved
+                    // out instead of copied.
+                    let table = if self.is_stored(&t) {
+                        self.tables[&t].clone()
+                    } else {
+                        self.derived_from.remove(&t);
+                        self.tables.remove(&t).unwrap()
+                    };
+                    return Ok(Some(table));
+                }
                 Some(Register::Value(v)) => {
                     let mut table = Table::new_temp(self.last_table_index.next_index().0);
                     self.last_table_index = self.last_table_index.next_index();
@@ -190,35 +980,97 @@ impl VirtualMachine {
                     let table_index = *table_index;
                     // TODO: should be safe to unwrap, but make it an error anyway?
                     let table = self.tables.get(&table_index).unwrap();
-                    let filtered_data = table
-                        .raw_data
-                        .iter()
-                        .filter_map(|row| {
-                            match Expr::execute(expr, table, RowShared::from_raw(row, &table)) {
-                                Ok(val) => match val {
-                                    Value::Bool(b) => {
-                                        if b {
-                                            Some(Ok(row.clone()))
-                                        } else {
-                                            None
-                                        }
-                                    }
-                                    _ => Some(Err(RuntimeError::FilterWithNonBoolean(
-                                        expr.clone(),
-                                        val.clone(),
-                                    ))),
-                                },
-                                Err(e) => Some(Err(e.into())),
-                            }
-                        })
-                        .collect::<Result<_, _>>()?;
-                    let new_table_index = self.new_table_from(&table_index);
-                    self.tables.get_mut(&new_table_index).unwrap().raw_data = filtered_data;
-                    self.insert_register(*index, Register::TableRef(new_table_index));
-                }
-                Some(reg) => return Err(RuntimeError::RegisterNotATable("filter", reg.clone())),
-            },
-            Instruction::Project {
+
+                    // validate once instead of failing on every row
+                    if let Some(col_ref) = expr
+                        .column_refs()
+                        .into_iter()
+                        .find(|col_ref| table.resolve_column(col_ref).is_none())
+                    {
+                        return Err(RuntimeError::ColumnNotFound(ColumnRef {
+                            schema_name: None,
+                            table_name: Some(*table.name()),
+                            col_name: col_ref.col_name,
+                        }));
+                    }
+
+                    let filter_mode = self.filter_mode;
+
+                    let filtered_data = if let Some(positions) = index_probe(table, expr) {
+                        positions
+                            .iter()
+                            .map(|position| table.raw_row(*position))
+                            .collect()
+                    } else {
+                        (0..table.num_rows())
+                            .filter_map(|position| {
+                                match Expr::execute_with(
+                                    expr,
+                                    table,
+                                    table.row_shared(position),
+                                    options,
+                                ) {
+                                    Ok(val) => match filter_mode.keeps(&val) {
+                                        Some(keep) => keep.then(|| Ok(table.raw_row(position))),
+                                        None => Some(Err(RuntimeError::FilterWithNonBoolean(
+                                            expr.clone(),
+                                            val,
+                                        ))),
+                                    },
+                                    Err(e) => Some(Err(e.into())),
+                                }
+                            })
+                            .collect::<Result<_, _>>()?
+                    };
+                    let new_table_index = self.new_table_from(&table_index);
+                    self.tables.get_mut(&new_table_index).unwrap().raw_data = filtered_data;
+                    self.derived_from.insert(new_table_index, table_index);
+                    self.insert_register(*index, Register::TableRef(new_table_index));
+                }
+                Some(Register::GroupedTable { .. }) => {
+                    let group_table_index = self.last_table_index.next_index();
+                    self.last_table_index = group_table_index;
+                    let filter_mode = self.filter_mode;
+
+                    if let Some(Register::GroupedTable {
+                        grouped_col,
+                        other_cols,
+                        data,
+                    }) = self.registers.get_mut(index)
+                    {
+                        let group_table =
+                            new_group_table(group_table_index, grouped_col, other_cols, expr)?;
+
+                        // aggregates are computed over the same groups as in the projection.
+                        let mut kept = Vec::with_capacity(data.len());
+                        for (key, rows) in data.drain(..) {
+                            let full_rows = group_rows(&key, &rows);
+                            let full_rows: Vec<_> =
+                                full_rows.iter().map(RowShared::from_row).collect();
+                            let val = Expr::execute_aggregate_custom(
+                                expr,
+                                &group_table,
+                                &full_rows,
+                                options,
+                                &self.aggregates,
+                            )?;
+                            match filter_mode.keeps(&val) {
+                                Some(true) => kept.push((key, rows)),
+                                Some(false) => {}
+                                None => {
+                                    return Err(RuntimeError::FilterWithNonBoolean(
+                                        expr.clone(),
+                                        val,
+                                    ))
+                                }
+                            }
+                        }
+                        *data = kept;
+                    }
+                }
+                Some(reg) => return Err(RuntimeError::RegisterNotATable("filter", reg.clone())),
+            },
+            Instruction::Project {
                 input,
                 output,
                 expr,
@@ -230,20 +1082,27 @@ impl VirtualMachine {
                     let out_table = self.tables.get_mut(out_table_index).unwrap();
                     // we assume out table is empty at this point, so use it like an input table
                     // because why not.
-                    let val =
-                        Expr::execute(expr, &out_table, out_table.sentinel_row()?.to_shared())?;
+                    let val = Expr::execute_with(
+                        expr,
+                        &out_table,
+                        out_table.sentinel_row()?.to_shared(),
+                        options,
+                    )?;
                     let data_type = val.data_type();
                     out_table.new_row(vec![val]);
 
-                    // TODO: provide a unique name here
                     let new_col = Column::new(
-                        alias.unwrap_or("PLACEHOLDER".into()),
+                        projected_column_name(out_table, expr, *alias),
                         data_type,
                         vec![],
                         false,
                     );
 
                     out_table.add_column(new_col);
+                    self.projected_from
+                        .entry(*out_table_index)
+                        .or_default()
+                        .push(expr.clone());
                 }
                 (
                     Some(Register::TableRef(inp_table_index)),
@@ -254,69 +1113,149 @@ impl VirtualMachine {
                         .get_many_mut([inp_table_index, out_table_index])
                         .unwrap();
 
-                    if !out_table.is_empty()
-                        && (inp_table.raw_data.len() != out_table.raw_data.len())
-                    {
-                        return Err(RuntimeError::ProjectTableSizeMismatch {
-                            inp_table_name: inp_table.name().to_owned(),
-                            inp_table_len: inp_table.raw_data.len(),
-                            out_table_name: out_table.name().to_owned(),
-                            out_table_len: out_table.raw_data.len(),
-                        });
-                    }
-
                     if let Expr::Wildcard = expr {
-                        // TODO: this could be optimized.
-                        for col in inp_table.columns() {
-                            out_table.add_column(col.clone());
-                            out_table.add_column_data(
-                                col.name(),
-                                inp_table.get_column_data(col.name())?,
-                            )?;
-                        }
-                    } else {
-                        if inp_table.raw_data.len() == out_table.raw_data.len() {
-                            for (inp_row, out_row) in
-                                inp_table.raw_data.iter().zip(out_table.raw_data.iter_mut())
-                            {
-                                let val = Expr::execute(
-                                    expr,
-                                    inp_table,
-                                    RowShared::from_raw(&inp_row, &inp_table),
-                                )?;
-                                out_row.raw_data.push(val);
-                            }
-                        } else {
-                            for inp_row in inp_table.raw_data.iter() {
-                                let val = Expr::execute(
-                                    expr,
-                                    inp_table,
-                                    RowShared::from_raw(&inp_row, &inp_table),
-                                )?;
-                                out_table.new_row(vec![val]);
-                            }
+                        if !out_table.is_empty() && (inp_table.num_rows() != out_table.num_rows()) {
+                            return Err(RuntimeError::ProjectTableSizeMismatch {
+                                inp_table_name: inp_table.name().to_owned(),
+                                inp_table_len: inp_table.num_rows(),
+                                out_table_name: out_table.name().to_owned(),
+                                out_table_len: out_table.num_rows(),
+                            });
                         }
 
-                        let data_type = if !out_table.raw_data.is_empty() {
-                            let newly_added =
-                                out_table.raw_data.first().unwrap().raw_data.last().unwrap();
-                            newly_added.data_type()
+                        // columns are appended after any already projected ones, by position so
+                        // that duplicate names (e.g. `SELECT *, *`) do not get mixed up.
+                        // constraints of the source columns do not apply to the result.
+                        let sources = self.projected_from.entry(*out_table_index).or_default();
+                        for (col, data) in inp_table.to_columns() {
+                            sources.push(Expr::ColumnRef(ColumnRef {
+                                schema_name: None,
+                                table_name: None,
+                                col_name: *col.name(),
+                            }));
+                            let col =
+                                Column::new(*col.name(), col.data_type().clone(), vec![], false);
+                            out_table.add_column_with_data(col, data)?;
+                        }
+                    } else {
+                        // without a GROUP BY, an aggregate treats the whole table as one group.
+                        let (values, data_type) = if expr.contains_aggregate_with(&self.aggregates)
+                        {
+                            let val = Expr::execute_aggregate_on_table(
+                                expr,
+                                inp_table,
+                                options,
+                                &self.aggregates,
+                            )?;
+                            let data_type = val.data_type();
+                            (vec![val], data_type)
                         } else {
-                            let sentinel = inp_table.sentinel_row()?;
-                            let output_val = Expr::execute(expr, inp_table, sentinel.to_shared())?;
-                            output_val.data_type()
+                            // a column is copied as a whole instead of going through the rows.
+                            let values = match expr {
+                                Expr::ColumnRef(col_ref) => inp_table
+                                    .resolve_raw_column(col_ref)
+                                    .map(|col_index| inp_table.raw_column_values(col_index)),
+                                _ => None,
+                            };
+                            let values = match values {
+                                Some(values) => values,
+                                None => inp_table
+                                    .rows_shared()
+                                    .map(|row| Expr::execute_with(expr, inp_table, row, options))
+                                    .collect::<Result<Vec<_>, _>>()?,
+                            };
+                            // the type is that of the first non-NULL value, if any.
+                            let data_type = match values
+                                .iter()
+                                .find(|val| **val != Value::Null)
+                                .or_else(|| values.first())
+                            {
+                                Some(val) => val.data_type(),
+                                None => {
+                                    let sentinel = inp_table.sentinel_row()?;
+                                    Expr::execute_with(
+                                        expr,
+                                        inp_table,
+                                        sentinel.to_shared(),
+                                        options,
+                                    )?
+                                    .data_type()
+                                }
+                            };
+                            (values, data_type)
                         };
 
-                        // TODO: provide a unique name here
-                        let new_col = Column::new(
-                            alias.unwrap_or("PLACEHOLDER".into()),
+                        Self::add_projected_column(
+                            out_table,
+                            inp_table.name(),
+                            values,
                             data_type,
-                            vec![],
-                            false,
-                        );
+                            expr,
+                            *alias,
+                        )?;
+                        self.projected_from
+                            .entry(*out_table_index)
+                            .or_default()
+                            .push(projection_source(expr, inp_table));
+                    }
+                }
+                (
+                    Some(Register::GroupedTable {
+                        grouped_col,
+                        other_cols,
+                        data,
+                    }),
+                    Some(Register::TableRef(out_table_index)),
+                ) => {
+                    if let Expr::Wildcard = expr {
+                        return Err(RuntimeError::Unsupported(
+                            "Wildcard projection of grouped tables is not supported",
+                        ));
+                    }
 
-                        out_table.add_column(new_col);
+                    let group_table_index = self.last_table_index.next_index();
+                    self.last_table_index = group_table_index;
+                    let group_table =
+                        new_group_table(group_table_index, grouped_col, other_cols, expr)?;
+
+                    let mut values = Vec::with_capacity(data.len());
+                    for (key, rows) in data {
+                        let rows = group_rows(key, rows);
+                        let rows: Vec<_> = rows.iter().map(RowShared::from_row).collect();
+                        values.push(Expr::execute_aggregate_custom(
+                            expr,
+                            &group_table,
+                            &rows,
+                            options,
+                            &self.aggregates,
+                        )?);
                     }
+
+                    let data_type = match values.first() {
+                        Some(val) => val.data_type(),
+                        None => Expr::execute_aggregate_custom(
+                            expr,
+                            &group_table,
+                            &[],
+                            options,
+                            &self.aggregates,
+                        )?
+                        .data_type(),
+                    };
+
+                    let out_table = self.tables.get_mut(out_table_index).unwrap();
+                    Self::add_projected_column(
+                        out_table,
+                        group_table.name(),
+                        values,
+                        data_type,
+                        expr,
+                        *alias,
+                    )?;
+                    self.projected_from
+                        .entry(*out_table_index)
+                        .or_default()
+                        .push(projection_source(expr, &group_table));
                 }
                 (Some(reg), Some(Register::TableRef(_))) => {
                     return Err(RuntimeError::RegisterNotATable("project", reg.clone()))
@@ -328,12 +1267,100 @@ impl VirtualMachine {
                     return Err(RuntimeError::RegisterNotATable("project", reg.clone()))
                 }
             },
-            Instruction::GroupBy { index: _, expr: _ } => todo!("group by is not implemented yet"),
-            Instruction::Order {
-                index,
-                expr,
-                ascending,
-            } => {
+            Instruction::GroupBy { index, expr } => match self.registers.get(index) {
+                None => return Err(RuntimeError::EmptyRegister(*index)),
+                Some(Register::TableRef(table_index)) => {
+                    let table = self.tables.get(table_index).unwrap();
+
+                    if let Some(col_ref) = expr
+                        .column_refs()
+                        .into_iter()
+                        .find(|col_ref| table.resolve_column(col_ref).is_none())
+                    {
+                        return Err(RuntimeError::ColumnNotFound(ColumnRef {
+                            schema_name: None,
+                            table_name: Some(*table.name()),
+                            col_name: col_ref.col_name,
+                        }));
+                    }
+
+                    // grouping by a plain column takes that column out of the rows. Any other
+                    // expression gets a column of its own and the rows are kept as they are.
+                    let grouped_col_index = match expr {
+                        Expr::ColumnRef(col_ref) => table.resolve_column(col_ref),
+                        _ => None,
+                    };
+
+                    let mut other_cols: Vec<Column> = table.columns().cloned().collect();
+                    let grouped_col = grouped_col_index.map(|i| other_cols.remove(i));
+
+                    // groups are kept in order of first appearance.
+                    let mut group_indices: BTreeMap<Value, usize> = BTreeMap::new();
+                    let mut data: Vec<(Value, Vec<Row>)> = Vec::new();
+                    for row in table.rows_shared() {
+                        let key = Expr::execute_with(expr, table, row.clone(), options)?;
+                        let mut row = row.data();
+                        if let Some(i) = grouped_col_index {
+                            row.remove(i);
+                        }
+
+                        match group_indices.get(&key) {
+                            Some(&i) => data[i].1.push(Row::new(row)),
+                            None => {
+                                group_indices.insert(key.clone(), data.len());
+                                data.push((key, vec![Row::new(row)]));
+                            }
+                        }
+                    }
+
+                    let grouped_col = match grouped_col {
+                        Some(col) => col,
+                        None => {
+                            let data_type = match data.first() {
+                                Some((key, _)) => key.data_type(),
+                                None => {
+                                    let sentinel = table.sentinel_row()?;
+                                    Expr::execute_with(expr, table, sentinel.to_shared(), options)?
+                                        .data_type()
+                                }
+                            };
+                            Column::new(expr.to_string().as_str().into(), data_type, vec![], false)
+                        }
+                    };
+
+                    self.insert_register(
+                        *index,
+                        Register::GroupedTable {
+                            grouped_col,
+                            other_cols,
+                            data,
+                        },
+                    );
+                }
+                Some(reg) => return Err(RuntimeError::RegisterNotATable("group by", reg.clone())),
+            },
+            Instruction::GroupAll { index } => match self.registers.get(index) {
+                None => return Err(RuntimeError::EmptyRegister(*index)),
+                Some(Register::TableRef(table_index)) => {
+                    let table = self.tables.get(table_index).unwrap();
+                    let rows = table.all_data();
+
+                    // every row has the same key, which is not visible to any expression.
+                    let grouped_col =
+                        Column::new("?group?".into(), DataType::Boolean, vec![], false);
+                    let other_cols = table.columns().cloned().collect();
+                    self.insert_register(
+                        *index,
+                        Register::GroupedTable {
+                            grouped_col,
+                            other_cols,
+                            data: vec![(Value::Null, rows)],
+                        },
+                    );
+                }
+                Some(reg) => return Err(RuntimeError::RegisterNotATable("group all", reg.clone())),
+            },
+            Instruction::Order { index, keys } => {
                 let table_index = match self.registers.get(index) {
                     None => return Err(RuntimeError::EmptyRegister(*index)),
                     Some(Register::TableRef(table_index)) => table_index,
@@ -344,19 +1371,105 @@ impl VirtualMachine {
                         ))
                     }
                 };
-                let table = self.tables.get_mut(table_index).unwrap();
+                let table = &self.tables[table_index];
 
-                let expr_values = table
-                    .raw_data
-                    .iter()
-                    .map(|row| Expr::execute(expr, table, RowShared::from_raw(&row, &table)))
-                    .collect::<Result<Vec<_>, _>>()?;
-                let mut perm = permutation::sort(expr_values);
-                perm.apply_slice_in_place(&mut table.raw_data);
+                // the output columns (including aliases) are all that is visible at this point.
+                // a name is ambiguous only if the columns which have it were projected from
+                // different things. a qualifier picks the ones projected from that table's column.
+                let sources = self.projected_from.get(table_index);
+                let mut key_table = None;
+                for col_ref in keys.iter().flat_map(|key| key.expr.column_refs()) {
+                    let same_named: Vec<_> = table
+                        .columns()
+                        .enumerate()
+                        .filter(|(_, c)| identifiers_eq(c.name(), &col_ref.col_name))
+                        .map(|(position, _)| position)
+                        .collect();
+                    if same_named.len() < 2 {
+                        continue;
+                    }
+                    let sources = match sources {
+                        Some(sources) => sources,
+                        None => return Err(RuntimeError::AmbiguousColumn(*col_ref)),
+                    };
+
+                    let qualified: Option<BoundedString> = col_ref.table_name.map(|table_name| {
+                        format!("{}.{}", table_name, col_ref.col_name)
+                            .as_str()
+                            .into()
+                    });
+                    let mut candidates = same_named.clone();
+                    if let Some(qualified) = qualified {
+                        // same as `Table::resolve_column`: the qualified name first.
+                        let projected_from = |name: &BoundedString| -> Vec<_> {
+                            same_named
+                                .iter()
+                                .copied()
+                                .filter(|position| {
+                                    matches!(
+                                        sources.get(*position),
+                                        Some(Expr::ColumnRef(source))
+                                            if identifiers_eq(&source.col_name, name)
+                                    )
+                                })
+                                .collect()
+                        };
+                        if let Some(positions) = [qualified, col_ref.col_name]
+                            .iter()
+                            .map(projected_from)
+                            .find(|positions| !positions.is_empty())
+                        {
+                            candidates = positions;
+                        }
+                    }
+
+                    let first = sources.get(candidates[0]);
+                    if first.is_none()
+                        || candidates
+                            .iter()
+                            .any(|position| sources.get(*position) != first)
+                    {
+                        return Err(RuntimeError::AmbiguousColumn(*col_ref));
+                    }
 
-                if !ascending {
-                    table.raw_data.reverse();
+                    // evaluating the key picks the first column with the name, so the one meant
+                    // gets the qualified name instead.
+                    if let (Some(qualified), true) = (qualified, candidates[0] != same_named[0]) {
+                        let key_table: &mut Table =
+                            key_table.get_or_insert_with(|| Table::new_from(table));
+                        key_table
+                            .raw_columns
+                            .iter_mut()
+                            .filter(|c| !c.is_internal())
+                            .nth(candidates[0])
+                            .unwrap()
+                            .rename(qualified);
+                    }
                 }
+                let key_table = key_table.as_ref().unwrap_or(table);
+
+                let key_values = table
+                    .rows_shared()
+                    .map(|row| {
+                        keys.iter()
+                            .map(|key| {
+                                Expr::execute_with(&key.expr, key_table, row.clone(), options)
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // each key has its own direction, so descending keys are compared in reverse
+                // instead of reversing the result. the sort is stable.
+                let mut perm = permutation::sort_by(&key_values, |row1, row2| {
+                    row1.iter()
+                        .zip(row2)
+                        .zip(keys)
+                        .map(|((val1, val2), key)| compare_by_key(val1, val2, key))
+                        .find(|ordering| ordering.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                });
+                self.tables.get_mut(table_index).unwrap().reorder(&mut perm);
             }
             Instruction::Limit { index, limit } => {
                 let table_index = match self.registers.get(index) {
@@ -368,7 +1481,32 @@ impl VirtualMachine {
                 };
                 let table = self.tables.get_mut(table_index).unwrap();
 
-                table.raw_data.truncate(*limit as usize);
+                table.truncate_rows(*limit as usize);
+            }
+            Instruction::Distinct { index } => {
+                let table_index = self.table_index_at(index, "distinct")?;
+                let table = self.tables.get_mut(&table_index).unwrap();
+
+                // internal columns are unique, so they are left out of the comparison.
+                let mut seen = BTreeSet::new();
+                let keep: Vec<_> = table
+                    .rows_shared()
+                    .map(|row| seen.insert(row.data()))
+                    .collect();
+                table.retain_rows(&keep);
+            }
+            Instruction::Offset { index, offset } => {
+                let table_index = match self.registers.get(index) {
+                    None => return Err(RuntimeError::EmptyRegister(*index)),
+                    Some(Register::TableRef(table_index)) => table_index,
+                    Some(register) => {
+                        return Err(RuntimeError::RegisterNotATable("offset", register.clone()))
+                    }
+                };
+                let table = self.tables.get_mut(table_index).unwrap();
+
+                let offset = (*offset).min(table.num_rows() as u64) as usize;
+                table.skip_rows(offset);
             }
             Instruction::NewSchema {
                 schema_name,
@@ -381,6 +1519,11 @@ impl VirtualMachine {
                     return Err(RuntimeError::SchemaExists(name));
                 }
             }
+            Instruction::Begin => self.begin(),
+            Instruction::Commit => {
+                self.transaction = None;
+            }
+            Instruction::Rollback => self.rollback(),
             Instruction::ColumnDef {
                 index,
                 name,
@@ -408,17 +1551,9 @@ impl VirtualMachine {
                 table_reg_index,
                 col_index,
             } => {
-                let table_index = match self.registers.get(table_reg_index) {
-                    None => return Err(RuntimeError::EmptyRegister(*table_reg_index)),
-                    Some(Register::TableRef(table_index)) => table_index,
-                    Some(register) => {
-                        return Err(RuntimeError::RegisterNotATable(
-                            "add column",
-                            register.clone(),
-                        ))
-                    }
-                };
-                let table = self.tables.get_mut(table_index).unwrap();
+                let table_index = self.table_index_at(table_reg_index, "add column")?;
+                self.save_for_rollback(&table_index);
+                let table = self.tables.get_mut(&table_index).unwrap();
 
                 let column = match self.registers.get(col_index) {
                     Some(Register::Column(column)) => column,
@@ -431,12 +1566,20 @@ impl VirtualMachine {
                     None => return Err(RuntimeError::EmptyRegister(*col_index)),
                 };
 
+                if table.num_columns() >= self.max_columns {
+                    return Err(RuntimeError::TooManyColumns {
+                        col_name: *column.name(),
+                        max_columns: self.max_columns,
+                    });
+                }
+
                 table.add_column(column.clone());
             }
             Instruction::NewTable {
                 index,
                 name,
                 exists_ok,
+                layout,
             } => {
                 let table_index = *match self.registers.get(index) {
                     None => return Err(RuntimeError::EmptyRegister(*index)),
@@ -451,6 +1594,7 @@ impl VirtualMachine {
 
                 let table = self.tables.get_mut(&table_index).unwrap();
                 table.rename(name.table_name);
+                table.set_layout(*layout);
 
                 let schema = self.find_schema(name.schema_name)?;
 
@@ -461,22 +1605,108 @@ impl VirtualMachine {
                         }
                     }
                     Err(RuntimeError::TableNotFound(_)) => {
+                        // defaults which need a row to be evaluated are left for the inserts.
+                        let table = &self.tables[&table_index];
+                        for col in table.columns() {
+                            if let Ok(default) = column_default(col) {
+                                let default = default.promote_to(col.data_type());
+                                if !default.fits_type(col.data_type()) {
+                                    return Err(RuntimeError::ColumnTypeMismatch {
+                                        col_name: *col.name(),
+                                        data_type: col.data_type().clone(),
+                                        value: default,
+                                    });
+                                }
+                            }
+                        }
+
+                        if self.strict_create {
+                            let table = &self.tables[&table_index];
+                            if let Some(col) = table.columns().find(|col| has_null_default(col)) {
+                                return Err(RuntimeError::NotNullColumnWithNullDefault(
+                                    ColumnRef {
+                                        schema_name: name.schema_name,
+                                        table_name: Some(name.table_name),
+                                        col_name: *col.name(),
+                                    },
+                                ));
+                            }
+                        }
+
+                        // a single column primary key is indexed, as it is often looked up.
+                        let table = self.tables.get_mut(&table_index).unwrap();
+                        if let [key_col] = table
+                            .columns()
+                            .filter(|col| col.is_primary_key())
+                            .map(|col| *col.name())
+                            .collect::<Vec<_>>()
+                            .as_slice()
+                        {
+                            table.create_index(key_col)?;
+                        }
+
                         self.find_schema_mut(name.schema_name)?
                             .add_table(table_index);
                     }
                     Err(e) => return Err(e),
                 }
             }
-            Instruction::DropTable { index: _ } => todo!("drop table is not implemented yet"),
-            Instruction::RemoveColumn {
-                index: _,
-                col_name: _,
-            } => todo!("remove column is not implemented yet"),
+            Instruction::DropTable { name, if_exists } => {
+                let table_index = match self.resolve_table(name) {
+                    Ok(table_index) => table_index,
+                    // a table in a schema which does not exist does not exist either.
+                    Err(RuntimeError::TableNotFound(_) | RuntimeError::SchemaNotFound(_))
+                        if *if_exists =>
+                    {
+                        return Ok(None)
+                    }
+                    Err(e) => return Err(e),
+                };
+
+                // the table is in exactly one schema.
+                let schema_name = *self
+                    .database
+                    .schemas()
+                    .iter()
+                    .find(|schema| schema.tables().contains(&table_index))
+                    .unwrap()
+                    .name();
+                self.database
+                    .schema_by_name_mut(&schema_name)
+                    .unwrap()
+                    .remove_table(table_index);
+
+                self.save_for_rollback(&table_index);
+                self.drop_table(&table_index);
+            }
+            Instruction::NewIndex { table, col_name } => {
+                let table_index = self.resolve_table(table)?;
+                self.save_for_rollback(&table_index);
+                self.tables
+                    .get_mut(&table_index)
+                    .unwrap()
+                    .create_index(col_name)?;
+            }
+            Instruction::RemoveColumn { index, col_name } => {
+                let table_index = self.table_index_at(index, "remove column")?;
+                self.save_for_rollback(&table_index);
+                self.tables
+                    .get_mut(&table_index)
+                    .unwrap()
+                    .remove_column(col_name)?;
+            }
             Instruction::RenameColumn {
-                index: _,
-                old_name: _,
-                new_name: _,
-            } => todo!("rename column is not implemented yet"),
+                index,
+                old_name,
+                new_name,
+            } => {
+                let table_index = self.table_index_at(index, "rename column")?;
+                self.save_for_rollback(&table_index);
+                self.tables
+                    .get_mut(&table_index)
+                    .unwrap()
+                    .rename_column(old_name, *new_name)?;
+            }
             Instruction::InsertDef {
                 table_reg_index,
                 index,
@@ -514,6 +1744,14 @@ impl VirtualMachine {
 
                 let col_info = table.get_column(col_name)?;
 
+                if insert.columns.iter().any(|(index, _)| *index == col_info.0) {
+                    return Err(RuntimeError::DuplicateInsertColumn(ColumnRef {
+                        schema_name: None,
+                        table_name: Some(*table.name()),
+                        col_name: *col_info.1.name(),
+                    }));
+                }
+
                 insert.columns.push((col_info.0, col_info.1.to_owned()));
             }
             Instruction::RowDef {
@@ -573,7 +1811,25 @@ impl VirtualMachine {
 
                 let table = self.tables.get(&insert.table).unwrap();
 
-                let value = Expr::execute(expr, table, table.sentinel_row()?.to_shared())?;
+                let value = match expr {
+                    // `DEFAULT` is the default of the column the value goes into.
+                    Expr::Default => {
+                        let position = insert.rows[row_index].len();
+                        let column = if insert.columns.is_empty() {
+                            table.columns().nth(position)
+                        } else {
+                            insert.columns.get(position).map(|(_, col)| col)
+                        };
+                        match column {
+                            Some(column) => column_default(column)?,
+                            // too many values, which is reported below.
+                            None => Value::Null,
+                        }
+                    }
+                    expr => {
+                        Expr::execute_with(expr, table, table.sentinel_row()?.to_shared(), options)?
+                    }
+                };
 
                 if insert.rows[row_index].len() + 1 > table.num_columns() {
                     return Err(RuntimeError::TooManyValuesToInsert(
@@ -588,7 +1844,7 @@ impl VirtualMachine {
             Instruction::Insert {
                 index: insert_index,
             } => {
-                let insert = match self.registers.remove(insert_index) {
+                let mut insert = match self.registers.remove(insert_index) {
                     Some(Register::InsertDef(insert)) => insert,
                     Some(register) => {
                         return Err(RuntimeError::RegisterNotAInsert("insert", register.clone()))
@@ -596,101 +1852,863 @@ impl VirtualMachine {
                     None => return Err(RuntimeError::EmptyRegister(*insert_index)),
                 };
 
+                self.save_for_rollback(&insert.table);
                 let table = self.tables.get_mut(&insert.table).unwrap();
+                let expected_len = if insert.columns.is_empty() {
+                    table.num_columns()
+                } else {
+                    insert.columns.len()
+                };
 
-                if !insert.columns.is_empty() && (insert.columns.len() != table.num_columns()) {
-                    return Err(RuntimeError::Unsupported(concat!(
-                        "Default values are not supported yet. ",
-                        "Some columns were missing in INSERT."
-                    )));
-                }
+                // validate all the rows before inserting any of them
+                if let Some(first_row) = insert.rows.first() {
+                    if let Some((row_index, row)) = insert
+                        .rows
+                        .iter()
+                        .enumerate()
+                        .find(|(_, row)| row.len() != first_row.len())
+                    {
+                        return Err(RuntimeError::InsertRowArityMismatch {
+                            table_name: *table.name(),
+                            row_index,
+                            row_len: row.len(),
+                            expected_len: first_row.len(),
+                        });
+                    }
 
-                for row in insert.rows {
-                    if table.num_columns() != row.len() {
+                    if first_row.len() < expected_len {
                         return Err(RuntimeError::NotEnoughValuesToInsert(
                             *table.name(),
-                            row.len(),
-                            table.num_columns(),
+                            first_row.len(),
+                            expected_len,
+                        ));
+                    } else if first_row.len() > expected_len {
+                        return Err(RuntimeError::TooManyValuesToInsert(
+                            *table.name(),
+                            first_row.len(),
+                            expected_len,
                         ));
                     }
+                }
+
+                // values for an explicit column list come in its order, not the table's. The
+                // columns left out of it get their default values.
+                if !insert.columns.is_empty() {
+                    let defaults = table
+                        .columns()
+                        .map(column_default)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let positions: Vec<usize> = insert
+                        .columns
+                        .iter()
+                        .map(|(_, col)| {
+                            table
+                                .columns()
+                                .position(|c| c.name() == col.name())
+                                .unwrap()
+                        })
+                        .collect();
+                    for row in insert.rows.iter_mut() {
+                        let mut ordered = defaults.clone();
+                        for (value, position) in row.drain(..).zip(&positions) {
+                            ordered[*position] = value;
+                        }
+                        *row = ordered;
+                    }
+                }
+
+                let mut rows = Vec::with_capacity(insert.rows.len());
+                for row in insert.rows {
+                    let row = row
+                        .into_iter()
+                        .zip(table.columns())
+                        .map(|(value, col)| {
+                            let value = value.promote_to(col.data_type());
+                            if !value.fits_type(col.data_type()) {
+                                return Err(RuntimeError::ColumnTypeMismatch {
+                                    col_name: *col.name(),
+                                    data_type: col.data_type().clone(),
+                                    value,
+                                });
+                            }
+                            fit_string_length(
+                                self.string_length_mode,
+                                col,
+                                value,
+                                &mut self.warnings,
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    rows.push(row);
+                }
+
+                check_not_null(table, &rows)?;
+                check_primary_key(table, &rows, &BTreeSet::new())?;
+                for row in rows {
                     table.new_row(row);
                 }
             }
-            Instruction::Update {
-                index: _,
-                col: _,
-                expr: _,
-            } => todo!("update is not implemented yet"),
-            Instruction::Union {
-                input1: _,
-                input2: _,
-                output: _,
-            } => todo!("union is not implemented yet"),
-            Instruction::CrossJoin {
-                input1: _,
-                input2: _,
-                output: _,
-            } => todo!("joins are not implemented yet"),
-            Instruction::NaturalJoin {
-                input1: _,
-                input2: _,
-                output: _,
-            } => todo!("joins are not implemented yet"),
-        }
-        Ok(None)
-    }
+            Instruction::Update { index, assignments } => {
+                let table_index = self.table_index_at(index, "update")?;
+                let table = &self.tables[&table_index];
 
-    /// Find [`TableIndex`] given the schema and its name.
-    fn find_table(&self, schema: &Schema, table: &TableRef) -> Result<TableIndex, RuntimeError> {
-        if let Some(table_index) = schema
-            .tables()
-            .iter()
-            .find(|table_index| self.tables[table_index].name() == &table.table_name)
-        {
-            Ok(*table_index)
-        } else {
-            Err(RuntimeError::TableNotFound(table.clone()))
-        }
-    }
+                let not_found = |col_ref: &ColumnRef| {
+                    RuntimeError::ColumnNotFound(ColumnRef {
+                        schema_name: None,
+                        table_name: Some(*table.name()),
+                        col_name: col_ref.col_name,
+                    })
+                };
+                let mut targets = Vec::with_capacity(assignments.len());
+                for Assignment { col, expr } in assignments {
+                    let col_ref = match col {
+                        Expr::ColumnRef(col_ref) => col_ref,
+                        _ => return Err(RuntimeError::Unsupported("Only columns can be updated")),
+                    };
+                    let col_index = table
+                        .resolve_raw_column(col_ref)
+                        .ok_or_else(|| not_found(col_ref))?;
+                    if targets.iter().any(|(index, _)| *index == col_index) {
+                        return Err(RuntimeError::DuplicateUpdateColumn(ColumnRef {
+                            schema_name: None,
+                            table_name: Some(*table.name()),
+                            col_name: col_ref.col_name,
+                        }));
+                    }
+                    if let Some(col_ref) = expr
+                        .column_refs()
+                        .into_iter()
+                        .find(|col_ref| table.resolve_column(col_ref).is_none())
+                    {
+                        return Err(not_found(col_ref));
+                    }
+                    // `SET col = DEFAULT` gives the same value for every row.
+                    let expr = match expr {
+                        Expr::Default => {
+                            Expr::Value(column_default(&table.raw_columns[col_index])?)
+                        }
+                        expr => expr.clone(),
+                    };
+                    targets.push((col_index, expr));
+                }
 
-    /// A reference to the given schema, or default schema if it's `None`.
-    fn find_schema(&self, name: Option<BoundedString>) -> Result<&Schema, RuntimeError> {
-        if let Some(schema_name) = name {
-            match self.database.schema_by_name(&schema_name) {
-                Some(schema) => Ok(schema),
-                None => return Err(RuntimeError::SchemaNotFound(schema_name)),
-            }
-        } else {
-            Ok(self.database.default_schema())
-        }
-    }
+                // every assignment is evaluated against the rows as they were, and all the new
+                // values are checked before any of them is written.
+                // the first column is the row's unique key, which is kept by filtered tables.
+                let mut new_values = BTreeMap::new();
+                for position in 0..table.num_rows() {
+                    let mut row_values = Vec::with_capacity(targets.len());
+                    for (col_index, expr) in &targets {
+                        let column = &table.raw_columns[*col_index];
+                        let value =
+                            Expr::execute_with(expr, table, table.row_shared(position), options)?
+                                .promote_to(column.data_type());
+                        if !value.fits_type(column.data_type()) {
+                            return Err(RuntimeError::ColumnTypeMismatch {
+                                col_name: *column.name(),
+                                data_type: column.data_type().clone(),
+                                value,
+                            });
+                        }
+                        if value == Value::Null && column.is_not_null() {
+                            return Err(RuntimeError::NullInNotNullColumn(ColumnRef {
+                                schema_name: None,
+                                table_name: Some(*table.name()),
+                                col_name: *column.name(),
+                            }));
+                        }
+                        row_values.push(fit_string_length(
+                            self.string_length_mode,
+                            column,
+                            value,
+                            &mut self.warnings,
+                        )?);
+                    }
+                    new_values.insert(table.raw_value(position, 0).clone(), row_values);
+                }
 
-    /// A mutable reference to the given schema, or default schema if it's `None`.
-    fn find_schema_mut(
-        &mut self,
-        name: Option<BoundedString>,
-    ) -> Result<&mut Schema, RuntimeError> {
-        if let Some(schema_name) = name {
-            match self.database.schema_by_name_mut(&schema_name) {
-                Some(schema) => Ok(schema),
-                None => return Err(RuntimeError::SchemaNotFound(schema_name)),
+                // the keys are checked against the whole table the rows were filtered from.
+                if targets
+                    .iter()
+                    .any(|(col_index, _)| table.raw_columns[*col_index].is_primary_key())
+                {
+                    let mut root_index = table_index;
+                    while let Some(source_index) = self.derived_from.get(&root_index) {
+                        root_index = *source_index;
+                    }
+                    let root = &self.tables[&root_index];
+                    let mut replaced = BTreeSet::new();
+                    let mut rows = Vec::with_capacity(new_values.len());
+                    for position in 0..root.num_rows() {
+                        if let Some(row_values) = new_values.get(root.raw_value(position, 0)) {
+                            replaced.insert(position);
+                            rows.push(
+                                root.raw_columns
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(_, col)| !col.is_internal())
+                                    .map(|(col_index, _)| {
+                                        match targets.iter().position(|(i, _)| *i == col_index) {
+                                            Some(i) => row_values[i].clone(),
+                                            None => root.raw_value(position, col_index).clone(),
+                                        }
+                                    })
+                                    .collect::<Vec<_>>(),
+                            );
+                        }
+                    }
+                    check_primary_key(root, &rows, &replaced)?;
+                }
+
+                // update the table in the register as well as the ones it was filtered from.
+                let mut current = Some(table_index);
+                while let Some(table_index) = current {
+                    self.save_for_rollback(&table_index);
+                    let table = self.tables.get_mut(&table_index).unwrap();
+                    for position in 0..table.num_rows() {
+                        if let Some(row_values) = new_values.get(table.raw_value(position, 0)) {
+                            for ((col_index, _), value) in targets.iter().zip(row_values) {
+                                table.set_raw_value(position, *col_index, value.clone());
+                            }
+                        }
+                    }
+                    table.rebuild_indexes();
+                    current = self.derived_from.get(&table_index).copied();
+                }
             }
-        } else {
-            Ok(self.database.default_schema_mut())
-        }
-    }
-}
+            Instruction::Union {
+                input1,
+                input2,
+                output,
+                all,
+            } => {
+                let table1_index = self.table_index_at(input1, "union")?;
+                let table2_index = self.table_index_at(input2, "union")?;
 
-impl Default for VirtualMachine {
-    fn default() -> Self {
-        Self::new(DEFAULT_DATABASE_NAME.into())
-    }
-}
+                let union_index = self.last_table_index.next_index();
+                self.last_table_index = union_index;
 
-#[derive(Debug, Clone, PartialEq)]
-/// A register in the executor VM.
-pub enum Register {
-    /// A reference to a table.
+                let table1 = &self.tables[&table1_index];
+                let table2 = &self.tables[&table2_index];
+
+                if table1.num_columns() != table2.num_columns() {
+                    return Err(RuntimeError::UnionColumnCountMismatch(
+                        table1.num_columns(),
+                        table2.num_columns(),
+                    ));
+                }
+
+                // names come from the first input, types are promoted to fit both inputs.
+                let mut union = Table::new_temp(union_index.0);
+                let mut data_types = Vec::with_capacity(table1.num_columns());
+                for (position, (col1, col2)) in table1.columns().zip(table2.columns()).enumerate() {
+                    let data_type = match common_type(col1.data_type(), col2.data_type()) {
+                        Some(data_type) => data_type,
+                        None => {
+                            return Err(RuntimeError::UnionColumnTypeMismatch {
+                                position,
+                                left: col1.data_type().clone(),
+                                right: col2.data_type().clone(),
+                            })
+                        }
+                    };
+                    // constraints of the source columns do not apply to the result.
+                    union.add_column(Column::new(*col1.name(), data_type.clone(), vec![], false));
+                    data_types.push(data_type);
+                }
+
+                // without ALL, duplicates make this an upper bound only.
+                if *all {
+                    union.reserve(table1.num_rows() + table2.num_rows());
+                }
+
+                let mut seen = BTreeSet::new();
+                for row in table1.all_data().into_iter().chain(table2.all_data()) {
+                    let row: Vec<Value> = row
+                        .data()
+                        .into_iter()
+                        .zip(data_types.iter())
+                        .map(|(value, data_type)| value.promote_to(data_type))
+                        .collect();
+                    if *all || seen.insert(row.clone()) {
+                        union.new_row(row);
+                    }
+                }
+
+                self.tables.insert(union_index, union);
+                self.insert_register(*output, Register::TableRef(union_index));
+            }
+            Instruction::CrossJoin {
+                input1,
+                input2,
+                output,
+            } => {
+                let table1_index = self.table_index_at(input1, "cross join")?;
+                let table2_index = self.table_index_at(input2, "cross join")?;
+
+                let joined_index = self.last_table_index.next_index();
+                self.last_table_index = joined_index;
+
+                // both inputs can be the same table, so only borrow them immutably.
+                let table1 = &self.tables[&table1_index];
+                let table2 = &self.tables[&table2_index];
+                let mut joined = Table::new_temp(joined_index.0);
+
+                add_joined_columns(&mut joined, table1, table2);
+                joined.reserve(table1.num_rows().saturating_mul(table2.num_rows()));
+
+                // an empty input results in an empty product.
+                let rows2 = table2.all_data();
+                for row1 in table1.all_data() {
+                    let row1 = row1.data();
+                    for row2 in rows2.iter() {
+                        joined.new_row(row1.iter().chain(row2.data_shared()).cloned().collect());
+                    }
+                }
+
+                self.tables.insert(joined_index, joined);
+                self.insert_register(*output, Register::TableRef(joined_index));
+            }
+            Instruction::Join {
+                input1,
+                input2,
+                output,
+                on,
+                join_type,
+            } => {
+                let table1_index = self.table_index_at(input1, "join")?;
+                let table2_index = self.table_index_at(input2, "join")?;
+
+                let joined_index = self.last_table_index.next_index();
+                self.last_table_index = joined_index;
+
+                let table1 = &self.tables[&table1_index];
+                let table2 = &self.tables[&table2_index];
+                let mut joined = Table::new_temp(joined_index.0);
+                add_joined_columns(&mut joined, table1, table2);
+
+                // validate once instead of failing on every pair of rows
+                if let Some(col_ref) = on
+                    .column_refs()
+                    .into_iter()
+                    .find(|col_ref| joined.resolve_column(col_ref).is_none())
+                {
+                    return Err(RuntimeError::ColumnNotFound(*col_ref));
+                }
+
+                // outer joins keep at least every row of one side.
+                match join_type {
+                    JoinType::Left => joined.reserve(table1.num_rows()),
+                    JoinType::Right => joined.reserve(table2.num_rows()),
+                    JoinType::Inner => {}
+                }
+
+                let nulls1 = vec![Value::Null; table1.columns().count()];
+                let nulls2 = vec![Value::Null; table2.columns().count()];
+
+                // the condition is evaluated against each combined row, so it is built first.
+                let rows2 = table2.all_data();
+                let mut matched2 = vec![false; rows2.len()];
+                for row1 in table1.all_data() {
+                    let row1 = row1.data();
+                    let mut matched1 = false;
+                    for (row2, matched2) in rows2.iter().zip(matched2.iter_mut()) {
+                        let row =
+                            Row::new(row1.iter().chain(row2.data_shared()).cloned().collect());
+                        let val =
+                            Expr::execute_with(on, &joined, RowShared::from_row(&row), options)?;
+                        match self.filter_mode.keeps(&val) {
+                            Some(true) => {
+                                joined.new_row(row.data());
+                                matched1 = true;
+                                *matched2 = true;
+                            }
+                            Some(false) => {}
+                            None => {
+                                return Err(RuntimeError::FilterWithNonBoolean(on.clone(), val))
+                            }
+                        }
+                    }
+
+                    if !matched1 && *join_type == JoinType::Left {
+                        joined.new_row(row1.into_iter().chain(nulls2.clone()).collect());
+                    }
+                }
+
+                // unmatched rows of the second table come after all the others.
+                if *join_type == JoinType::Right {
+                    for (row2, _) in rows2.iter().zip(matched2).filter(|(_, m)| !m) {
+                        joined.new_row(nulls1.iter().chain(row2.data_shared()).cloned().collect());
+                    }
+                }
+
+                self.tables.insert(joined_index, joined);
+                self.insert_register(*output, Register::TableRef(joined_index));
+            }
+            Instruction::NaturalJoin {
+                input1,
+                input2,
+                output,
+            } => {
+                let table1_index = self.table_index_at(input1, "natural join")?;
+                let table2_index = self.table_index_at(input2, "natural join")?;
+
+                let joined_index = self.last_table_index.next_index();
+                self.last_table_index = joined_index;
+
+                let table1 = &self.tables[&table1_index];
+                let table2 = &self.tables[&table2_index];
+                let mut joined = Table::new_temp(joined_index.0);
+
+                // raw indices of the columns both tables have, in each table.
+                let mut shared = Vec::new();
+                for col in table1.columns() {
+                    if let Ok((index2, _)) = table2.get_column(col.name()) {
+                        let (index1, _) = table1.get_column(col.name())?;
+                        shared.push((index1, index2));
+                    }
+                }
+
+                // shared columns only appear once, as part of the first table. constraints of the
+                // source columns do not apply to the result.
+                let unconstrained =
+                    |col: &Column| Column::new(*col.name(), col.data_type().clone(), vec![], false);
+                for col in table1.columns() {
+                    joined.add_column(unconstrained(col));
+                }
+                let mut rest2 = Vec::new();
+                for (index2, col) in table2.raw_columns.iter().enumerate() {
+                    if !col.is_internal() && shared.iter().all(|(_, i)| *i != index2) {
+                        joined.add_column(unconstrained(col));
+                        rest2.push(index2);
+                    }
+                }
+
+                // with no shared columns, every pair of rows matches like in a cross join.
+                for position1 in 0..table1.num_rows() {
+                    for position2 in 0..table2.num_rows() {
+                        let matches = shared.iter().all(|(index1, index2)| {
+                            let val1 = table1.raw_value(position1, *index1);
+                            let val2 = table2.raw_value(position2, *index2);
+                            *val1 != Value::Null && val1 == val2
+                        });
+                        if matches {
+                            joined.new_row(
+                                table1
+                                    .row_shared(position1)
+                                    .data()
+                                    .into_iter()
+                                    .chain(
+                                        rest2
+                                            .iter()
+                                            .map(|i| table2.raw_value(position2, *i).clone()),
+                                    )
+                                    .collect(),
+                            );
+                        }
+                    }
+                }
+
+                self.tables.insert(joined_index, joined);
+                self.insert_register(*output, Register::TableRef(joined_index));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The [`TableIndex`] in the [`Register::TableRef`] at the given register.
+    fn table_index_at(
+        &self,
+        index: &RegisterIndex,
+        operation: &'static str,
+    ) -> Result<TableIndex, RuntimeError> {
+        match self.registers.get(index) {
+            None => Err(RuntimeError::EmptyRegister(*index)),
+            Some(Register::TableRef(table_index)) => Ok(*table_index),
+            Some(reg) => Err(RuntimeError::RegisterNotATable(operation, reg.clone())),
+        }
+    }
+
+    /// Find [`TableIndex`] given the schema and its name.
+    fn find_table(&self, schema: &Schema, table: &TableRef) -> Result<TableIndex, RuntimeError> {
+        if let Some(table_index) = schema
+            .tables()
+            .iter()
+            .find(|table_index| identifiers_eq(self.tables[table_index].name(), &table.table_name))
+        {
+            Ok(*table_index)
+        } else {
+            Err(RuntimeError::TableNotFound(table.clone()))
+        }
+    }
+
+    /// Find [`TableIndex`] of a table, looking through the search path if it has no schema name.
+    ///
+    /// Like for new tables, the default schema is used when none of the schemas in the search
+    /// path exist.
+    fn resolve_table(&self, table: &TableRef) -> Result<TableIndex, RuntimeError> {
+        if table.schema_name.is_some() || self.current_schema_name().is_none() {
+            return self.find_table(self.find_schema(table.schema_name)?, table);
+        }
+
+        self.search_path
+            .iter()
+            .filter_map(|schema_name| self.database.schema_by_name(schema_name))
+            .find_map(|schema| self.find_table(schema, table).ok())
+            .ok_or(RuntimeError::TableNotFound(*table))
+    }
+
+    /// Name of the schema used when none is given: the first existing schema in the search path.
+    ///
+    /// `None` means the default schema, also when none of the schemas in the search path exist.
+    fn current_schema_name(&self) -> Option<BoundedString> {
+        self.search_path
+            .iter()
+            .find(|schema_name| self.database.schema_by_name(schema_name).is_some())
+            .copied()
+    }
+
+    /// A reference to the given schema, or the current schema if it's `None`.
+    fn find_schema(&self, name: Option<BoundedString>) -> Result<&Schema, RuntimeError> {
+        if let Some(schema_name) = name.or_else(|| self.current_schema_name()) {
+            match self.database.schema_by_name(&schema_name) {
+                Some(schema) => Ok(schema),
+                None => return Err(RuntimeError::SchemaNotFound(schema_name)),
+            }
+        } else {
+            Ok(self.database.default_schema())
+        }
+    }
+
+    /// A mutable reference to the given schema, or the current schema if it's `None`.
+    fn find_schema_mut(
+        &mut self,
+        name: Option<BoundedString>,
+    ) -> Result<&mut Schema, RuntimeError> {
+        if let Some(schema_name) = name.or_else(|| self.current_schema_name()) {
+            match self.database.schema_by_name_mut(&schema_name) {
+                Some(schema) => Ok(schema),
+                None => return Err(RuntimeError::SchemaNotFound(schema_name)),
+            }
+        } else {
+            Ok(self.database.default_schema_mut())
+        }
+    }
+}
+
+impl Default for VirtualMachine {
+    fn default() -> Self {
+        Self::new(DEFAULT_DATABASE_NAME.into())
+    }
+}
+
+/// What a column projected from `expr` holds: `expr` with its column references replaced by the
+/// names of the columns of `inp_table` they resolve to, so that e.g. `a` and `t.a` compare equal.
+fn projection_source(expr: &Expr, inp_table: &Table) -> Expr {
+    expr.clone().replace_with(&|e| match e {
+        Expr::ColumnRef(col_ref) => inp_table.resolve_column(col_ref).map(|position| {
+            Expr::ColumnRef(ColumnRef {
+                schema_name: None,
+                table_name: None,
+                col_name: *inp_table.columns().nth(position).unwrap().name(),
+            })
+        }),
+        _ => None,
+    })
+}
+
+/// The name of the column projected from `expr` into `out_table`.
+///
+/// Without an alias, a column reference keeps the name of the column. Any other expression gets
+/// a name like PostgreSQL's `?column?`, numbered so that it is unique in the table.
+fn projected_column_name(
+    out_table: &Table,
+    expr: &Expr,
+    alias: Option<BoundedString>,
+) -> BoundedString {
+    if let Some(alias) = alias {
+        return alias;
+    }
+    if let Expr::ColumnRef(col_ref) = expr {
+        return col_ref.col_name;
+    }
+
+    let is_taken =
+        |name: &BoundedString| out_table.columns().any(|c| identifiers_eq(c.name(), name));
+    let mut name: BoundedString = "?column?".into();
+    let mut n = 1;
+    while is_taken(&name) {
+        n += 1;
+        name = format!("?column?{}", n).as_str().into();
+    }
+    name
+}
+
+/// Compare two values of an [`OrderKey`], in the order they should be sorted in.
+///
+/// `NULL`s are placed according to the key alone, the direction only applies to other values.
+fn compare_by_key(val1: &Value, val2: &Value, key: &OrderKey) -> Ordering {
+    let null_ordering = if key.nulls_first {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    };
+    match (val1, val2) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => null_ordering,
+        (_, Value::Null) => null_ordering.reverse(),
+        _ if key.ascending => val1.cmp(val2),
+        _ => val2.cmp(val1),
+    }
+}
+
+/// A temporary table with the columns of a grouped table, to evaluate expressions over its groups.
+///
+/// The grouped column comes first, followed by the rest of the columns. All the columns referred to
+/// by `expr` must be in the table.
+fn new_group_table(
+    index: TableIndex,
+    grouped_col: &Column,
+    other_cols: &[Column],
+    expr: &Expr,
+) -> Result<Table, RuntimeError> {
+    let mut group_table = Table::new_temp(index.0);
+    group_table.add_column(grouped_col.clone());
+    for col in other_cols {
+        group_table.add_column(col.clone());
+    }
+
+    if let Some(col_ref) = expr
+        .column_refs()
+        .into_iter()
+        .find(|col_ref| group_table.resolve_column(col_ref).is_none())
+    {
+        return Err(RuntimeError::ColumnNotFound(*col_ref));
+    }
+
+    Ok(group_table)
+}
+
+/// The rows of a group as rows of the table made by [`new_group_table`], i.e. with the group's key
+/// first.
+fn group_rows(key: &Value, rows: &[Row]) -> Vec<Row> {
+    rows.iter()
+        .map(|row| {
+            let mut full_row = vec![key.clone()];
+            full_row.extend(row.clone().data());
+            Row::new(full_row)
+        })
+        .collect()
+}
+
+/// Add the columns of a join of `table1` and `table2` to `joined`.
+///
+/// The columns of `table1` are followed by those of `table2`. Columns sharing a name are
+/// qualified with their table's name, e.g. `table1.col1` and `table2.col1`.
+fn add_joined_columns(joined: &mut Table, table1: &Table, table2: &Table) {
+    for (table, other) in [(table1, table2), (table2, table1)] {
+        for col in table.columns() {
+            let name = if other
+                .columns()
+                .any(|other_col| identifiers_eq(other_col.name(), col.name()))
+            {
+                format!("{}.{}", table.name(), col.name()).as_str().into()
+            } else {
+                *col.name()
+            };
+            // constraints of the source columns do not apply to the result.
+            joined.add_column(Column::new(name, col.data_type().clone(), vec![], false));
+        }
+    }
+}
+
+/// Make a value fit the length of its column, if it is a string that is too long.
+///
+/// What happens to such strings depends on the mode. Warnings are added to `warnings`.
+fn fit_string_length(
+    mode: StringLengthMode,
+    col: &Column,
+    value: Value,
+    warnings: &mut Vec<Warning>,
+) -> Result<Value, RuntimeError> {
+    let max_len = match col.data_type() {
+        DataType::Char(Some(len))
+        | DataType::Varchar(Some(len))
+        | DataType::Nvarchar(Some(len)) => *len,
+        _ => return Ok(value),
+    };
+
+    match value {
+        Value::String(s) if s.chars().count() as u64 > max_len => match mode {
+            StringLengthMode::Unchecked => Ok(Value::String(s)),
+            StringLengthMode::Truncate => {
+                warnings.push(Warning::StringTruncated {
+                    col_name: *col.name(),
+                    max_len,
+                    value: Value::String(s.clone()),
+                });
+                Ok(Value::String(s.chars().take(max_len as usize).collect()))
+            }
+            StringLengthMode::Strict => Err(RuntimeError::StringTooLong {
+                col_name: *col.name(),
+                max_len,
+                value: Value::String(s),
+            }),
+        },
+        value => Ok(value),
+    }
+}
+
+/// The positions of the rows of the table which match a `column = value` condition, found with an
+/// index on the column instead of evaluating the condition for each row.
+///
+/// `None` if the condition has another form or the column is not indexed.
+fn index_probe<'a>(table: &'a Table, expr: &Expr) -> Option<&'a [usize]> {
+    let (col_ref, value) = match expr {
+        Expr::Binary {
+            left,
+            op: BinOp::Equal,
+            right,
+        } => match (left.as_ref(), right.as_ref()) {
+            (Expr::ColumnRef(col_ref), Expr::Value(value))
+            | (Expr::Value(value), Expr::ColumnRef(col_ref)) => (col_ref, value),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let col_index = table.resolve_raw_column(col_ref)?;
+    let data_type = table.raw_columns[col_index].data_type();
+    // the value must be stored the way the column's values are, so that being equal is the same
+    // as being the same value. Anything else, like `NULL`, is left to the full comparison.
+    let value = value.clone().promote_to(data_type);
+    if value == Value::Null || !value.fits_type(data_type) {
+        return None;
+    }
+    table.index_lookup(col_index, &value)
+}
+
+/// Check that none of the rows to be inserted into the table have a `NULL` in a `NOT NULL`
+/// column.
+fn check_not_null(table: &Table, rows: &[Vec<Value>]) -> Result<(), RuntimeError> {
+    for row in rows {
+        if let Some(col) = row
+            .iter()
+            .zip(table.columns())
+            .find(|(value, col)| **value == Value::Null && col.is_not_null())
+            .map(|(_, col)| col)
+        {
+            return Err(RuntimeError::NullInNotNullColumn(ColumnRef {
+                schema_name: None,
+                table_name: Some(*table.name()),
+                col_name: *col.name(),
+            }));
+        }
+    }
+    Ok(())
+}
+
+/// Check that none of the new rows of the table have a `NULL` primary key, or one which is
+/// already in the table or in an earlier row.
+///
+/// `replaced` are the positions of the rows which the new rows take the place of, as in an
+/// `UPDATE`. Their current keys are not counted.
+fn check_primary_key(
+    table: &Table,
+    rows: &[Vec<Value>],
+    replaced: &BTreeSet<usize>,
+) -> Result<(), RuntimeError> {
+    // (position in the rows, raw index in the table, column)
+    let (col_index, raw_index, col) = match table
+        .raw_columns
+        .iter()
+        .enumerate()
+        .filter(|(_, col)| !col.is_internal())
+        .enumerate()
+        .find(|(_, (_, col))| col.is_primary_key())
+    {
+        Some((col_index, (raw_index, col))) => (col_index, raw_index, col),
+        None => return Ok(()),
+    };
+
+    // without an index on the key, all the keys of the table are collected, which takes time
+    // linear in its size.
+    let mut table_keys = None;
+    let mut keys = BTreeSet::new();
+
+    for row in rows {
+        let key = &row[col_index];
+        if *key == Value::Null {
+            return Err(RuntimeError::NullPrimaryKey(ColumnRef {
+                schema_name: None,
+                table_name: Some(*table.name()),
+                col_name: *col.name(),
+            }));
+        }
+        let in_table = match table.index_lookup(raw_index, key) {
+            Some(positions) => positions
+                .iter()
+                .any(|position| !replaced.contains(position)),
+            None => table_keys
+                .get_or_insert_with(|| {
+                    (0..table.num_rows())
+                        .filter(|position| !replaced.contains(position))
+                        .map(|position| table.raw_value(position, raw_index).clone())
+                        .collect::<BTreeSet<_>>()
+                })
+                .contains(key),
+        };
+        if in_table || !keys.insert(key.clone()) {
+            return Err(RuntimeError::DuplicateKey {
+                table_name: *table.name(),
+                key: key.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Whether the column is `NOT NULL` but has a `DEFAULT` which evaluates to `NULL`.
+///
+/// Defaults which cannot be evaluated without a row are not considered.
+fn has_null_default(col: &Column) -> bool {
+    let is_not_null = col
+        .options()
+        .iter()
+        .any(|o| matches!(o.option, ColumnOption::NotNull));
+
+    is_not_null
+        && matches!(default_expr(col), Some(Ok(_)))
+        && column_default(col) == Ok(Value::Null)
+}
+
+/// The `DEFAULT` expression of the column, if it has one.
+fn default_expr(col: &Column) -> Option<Result<Expr, ExprError>> {
+    col.options().iter().find_map(|o| match &o.option {
+        ColumnOption::Default(expr) => Some(Expr::try_from(expr.clone())),
+        _ => None,
+    })
+}
+
+/// The value of the column's `DEFAULT` expression, or `NULL` if it has none.
+fn column_default(col: &Column) -> Result<Value, RuntimeError> {
+    match default_expr(col) {
+        Some(Ok(default)) => {
+            let table = Table::new_temp(0);
+            let row = Row::new(vec![]);
+            Ok(Expr::execute(&default, &table, row.to_shared())?)
+        }
+        Some(Err(_)) => Err(RuntimeError::Unsupported(
+            "The default value of the column is not supported",
+        )),
+        None => Ok(Value::Null),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A register in the executor VM.
+pub enum Register {
+    /// A reference to a table.
     TableRef(TableIndex),
     /// A reference to a non-existent table.
     NonExistentTable,
@@ -792,6 +2810,37 @@ impl Display for ExecutionError {
 
 impl Error for ExecutionError {}
 
+/// Error saving a database to a file or loading it from one.
+#[cfg(feature = "persistence")]
+#[cfg_attr(docsrs, doc(cfg(feature = "persistence")))]
+#[derive(Debug)]
+pub enum PersistenceError {
+    /// The file could not be read or written.
+    Io(std::io::Error),
+    /// The file does not contain a saved database.
+    Corrupt(String),
+}
+
+#[cfg(feature = "persistence")]
+impl From<std::io::Error> for PersistenceError {
+    fn from(err: std::io::Error) -> Self {
+        PersistenceError::Io(err)
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl Display for PersistenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not access the database file: {}", e),
+            Self::Corrupt(reason) => write!(f, "The database file is corrupt: {}", reason),
+        }
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl Error for PersistenceError {}
+
 /// All possible errors handled during execution.
 ///
 /// This includes constraint violations, errors in expression evaluation, unsupported features as
@@ -799,7 +2848,10 @@ impl Error for ExecutionError {}
 #[derive(Debug, PartialEq)]
 pub enum RuntimeError {
     ColumnNotFound(ColumnRef),
+    ColumnExists(ColumnRef),
+    AmbiguousColumn(ColumnRef),
     TableNotFound(TableRef),
+    TableIndexNotFound(TableIndex),
     TableExists(TableRef),
     SchemaNotFound(BoundedString),
     SchemaExists(BoundedString),
@@ -825,8 +2877,47 @@ pub enum RuntimeError {
     },
     UnsupportedType(DataType),
     ExprExecError(ExprExecError),
+    DuplicateInsertColumn(ColumnRef),
+    DuplicateUpdateColumn(ColumnRef),
+    ColumnTypeMismatch {
+        col_name: BoundedString,
+        data_type: DataType,
+        value: Value,
+    },
     TooManyValuesToInsert(BoundedString, usize, usize),
     NotEnoughValuesToInsert(BoundedString, usize, usize),
+    InsertRowArityMismatch {
+        table_name: BoundedString,
+        row_index: usize,
+        row_len: usize,
+        expected_len: usize,
+    },
+    UnionColumnCountMismatch(usize, usize),
+    UnionColumnTypeMismatch {
+        position: usize,
+        left: DataType,
+        right: DataType,
+    },
+    NotNullColumnWithNullDefault(ColumnRef),
+    StringTooLong {
+        col_name: BoundedString,
+        max_len: u64,
+        value: Value,
+    },
+    ParameterCountMismatch {
+        expected: usize,
+        got: usize,
+    },
+    TooManyColumns {
+        col_name: BoundedString,
+        max_columns: usize,
+    },
+    DuplicateKey {
+        table_name: BoundedString,
+        key: Value,
+    },
+    NullPrimaryKey(ColumnRef),
+    NullInNotNullColumn(ColumnRef),
     Unsupported(&'static str),
 }
 
@@ -840,7 +2931,10 @@ impl Display for RuntimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::ColumnNotFound(c) => write!(f, "Column not found: '{}'", c),
+            Self::ColumnExists(c) => write!(f, "Column already exists: '{}'", c),
+            Self::AmbiguousColumn(c) => write!(f, "Column reference is ambiguous: '{}'", c),
             Self::TableNotFound(t) => write!(f, "Table not found: '{}'", t),
+            Self::TableIndexNotFound(t) => write!(f, "Table not found at index: '{}'", t),
             Self::TableExists(s) => write!(f, "Table already exists: '{}'", s),
             Self::SchemaNotFound(s) => write!(f, "Schema not found: '{}'", s),
             Self::SchemaExists(s) => write!(f, "Schema already exists: '{}'", s),
@@ -911,6 +3005,21 @@ impl Display for RuntimeError {
             ),
             Self::UnsupportedType(d) => write!(f, "Unsupported type: {}", d),
             Self::ExprExecError(e) => write!(f, "{}", e),
+            Self::ColumnTypeMismatch {
+                col_name,
+                data_type,
+                value,
+            } => write!(
+                f,
+                "Value '{}' can not be stored in column '{}' of type {}",
+                value, col_name, data_type
+            ),
+            Self::DuplicateInsertColumn(c) => {
+                write!(f, "Column specified more than once in insert: '{}'", c)
+            }
+            Self::DuplicateUpdateColumn(c) => {
+                write!(f, "Column assigned more than once in update: '{}'", c)
+            }
             Self::TooManyValuesToInsert(table_name, got_num, expected_num) => write!(
                 f,
                 concat!(
@@ -927,6 +3036,69 @@ impl Display for RuntimeError {
                 ),
                 table_name, got_num, expected_num
             ),
+            Self::InsertRowArityMismatch {
+                table_name,
+                row_index,
+                row_len,
+                expected_len,
+            } => write!(
+                f,
+                concat!(
+                    "All rows to insert into table '{}' must have the same number of values. ",
+                    "Row {} has {} values while the first row has {}."
+                ),
+                table_name, row_index, row_len, expected_len
+            ),
+            Self::UnionColumnCountMismatch(left, right) => write!(
+                f,
+                "Queries in a UNION must have the same number of columns. \
+                 Got {} and {} columns",
+                left, right
+            ),
+            Self::UnionColumnTypeMismatch {
+                position,
+                left,
+                right,
+            } => write!(
+                f,
+                "Queries in a UNION must have the same column types. \
+                 Column {} has type {} and {}",
+                position + 1,
+                left,
+                right
+            ),
+            Self::NotNullColumnWithNullDefault(c) => {
+                write!(f, "Column is NOT NULL but its default is NULL: '{}'", c)
+            }
+            Self::StringTooLong {
+                col_name,
+                max_len,
+                value,
+            } => write!(
+                f,
+                "Value '{}' is longer than the {} characters allowed in column '{}'",
+                value, max_len, col_name
+            ),
+            Self::ParameterCountMismatch { expected, got } => {
+                write!(f, "Expected {} parameter value(s), got {}", expected, got)
+            }
+            Self::TooManyColumns {
+                col_name,
+                max_columns,
+            } => write!(
+                f,
+                "Cannot add column '{}': a table can have at most {} columns",
+                col_name, max_columns
+            ),
+            Self::DuplicateKey { table_name, key } => write!(
+                f,
+                "Duplicate primary key ({}) in table '{}'",
+                key, table_name
+            ),
+            Self::NullPrimaryKey(c) => write!(f, "Primary key column cannot be NULL: '{}'", c),
+            Self::NullInNotNullColumn(c) => {
+                write!(f, "NULL value in NOT NULL column: '{}'", c)
+            }
             Self::Unsupported(err) => write!(f, "{}", err,),
         }
     }
@@ -937,16 +3109,20 @@ mod tests {
     use sqlparser::ast::{ColumnOption, ColumnOptionDef, DataType};
 
     use crate::{
-        codegen::codegen_ast,
+        codegen::{codegen_ast, codegen_str, CodegenError},
         column::Column,
         expr::{eval::ExprExecError, BinOp, Expr},
-        identifier::{ColumnRef, TableRef},
+        ic::{Instruction, IntermediateCode},
+        identifier::{ColumnRef, QuoteStyle, TableRef},
         parser::parse,
-        table::{Row, Table},
+        table::{Row, RowLike, StorageLayout, Table, TABLE_UNIQUE_KEY_NAME},
         value::Value,
     };
 
-    use super::{RuntimeError, VirtualMachine};
+    use super::{
+        ExecutionError, FilterMode, Register, RegisterIndex, RuntimeError, StringLengthMode,
+        TableIndex, VirtualMachine, Warning,
+    };
 
     #[test]
     fn create_vm() {
@@ -1164,7 +3340,11 @@ mod tests {
             ",
             &mut vm,
         );
-        matches!(res.unwrap_err(), RuntimeError::Unsupported(_));
+        assert!(matches!(
+            res.unwrap_err(),
+            RuntimeError::NullInNotNullColumn(ColumnRef { col_name, .. })
+                if col_name.as_str() == "col1"
+        ));
     }
 
     #[test]
@@ -1178,7 +3358,7 @@ mod tests {
         assert_eq!(
             res.columns().collect::<Vec<_>>(),
             vec![&Column::new(
-                "PLACEHOLDER".into(),
+                "?column?".into(),
                 DataType::Int(None),
                 vec![],
                 false
@@ -1227,33 +3407,12 @@ mod tests {
             .unwrap()
             .unwrap();
 
+        // the result does not carry the constraints of the source columns
         assert_eq!(
             res.columns().cloned().collect::<Vec<_>>(),
             vec![
-                Column::new(
-                    "col1".into(),
-                    DataType::Int(None),
-                    vec![
-                        ColumnOptionDef {
-                            name: None,
-                            option: ColumnOption::Unique { is_primary: true },
-                        },
-                        ColumnOptionDef {
-                            name: None,
-                            option: ColumnOption::NotNull
-                        }
-                    ],
-                    false
-                ),
-                Column::new(
-                    "col2".into(),
-                    DataType::String,
-                    vec![ColumnOptionDef {
-                        name: None,
-                        option: ColumnOption::NotNull
-                    }],
-                    false
-                ),
+                Column::new("col1".into(), DataType::Int(None), vec![], false),
+                Column::new("col2".into(), DataType::String, vec![], false),
             ]
         );
 
@@ -1405,7 +3564,11 @@ mod tests {
         let res = check_single_statement("SELECT col1 FROM table1 WHERE col3 = 1", &mut vm);
         assert_eq!(
             res.unwrap_err(),
-            RuntimeError::ExprExecError(ExprExecError::NoSuchColumn("col3".into()))
+            RuntimeError::ColumnNotFound(ColumnRef {
+                schema_name: None,
+                table_name: Some("table1".into()),
+                col_name: "col3".into()
+            })
         );
 
         let res = check_single_statement("SELECT col1 FROM table1 WHERE col1 + 1", &mut vm);
@@ -1425,4 +3588,3853 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn new_temp_tables_are_distinct() {
+        let mut vm = VirtualMachine::default();
+
+        let index1 = vm.new_temp_table();
+        let index2 = vm.new_temp_table();
+        assert_ne!(index1, index2);
+
+        // the second table does not replace the first one
+        vm.table_mut(&index1).unwrap().add_column(Column::new(
+            "col1".into(),
+            DataType::Int(None),
+            vec![],
+            false,
+        ));
+        assert_eq!(vm.table_mut(&index1).unwrap().columns().count(), 1);
+        assert_eq!(vm.table_mut(&index2).unwrap().columns().count(), 0);
+        let name1 = *vm.table_mut(&index1).unwrap().name();
+        assert_ne!(&name1, vm.table_mut(&index2).unwrap().name());
+    }
+
+    #[test]
+    fn query_by_table_index() {
+        let mut vm = VirtualMachine::default();
+
+        let table_index = vm.new_temp_table();
+        let table = vm.table_mut(&table_index).unwrap();
+        table.add_column(Column::new(
+            "col1".into(),
+            DataType::Int(None),
+            vec![],
+            false,
+        ));
+        table.add_column(Column::new("col2".into(), DataType::String, vec![], false));
+        table.new_row(vec![Value::Int64(1), Value::String("foo".to_owned())]);
+        table.new_row(vec![Value::Int64(2), Value::String("bar".to_owned())]);
+        table.new_row(vec![Value::Int64(3), Value::String("baz".to_owned())]);
+
+        let col1 = Expr::ColumnRef(ColumnRef {
+            schema_name: None,
+            table_name: None,
+            col_name: "col1".into(),
+        });
+
+        let res = vm
+            .filter_table(
+                &table_index,
+                Expr::Binary {
+                    left: Box::new(col1.clone()),
+                    op: BinOp::GreaterThanOrEqual,
+                    right: Box::new(Expr::Value(Value::Int64(2))),
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(2), Value::String("bar".to_owned())]),
+                Row::new(vec![Value::Int64(3), Value::String("baz".to_owned())])
+            ]
+        );
+
+        // the original table is untouched
+        assert_eq!(vm.table(&table_index).unwrap().all_data().len(), 3);
+
+        let res = vm
+            .project_table(
+                &table_index,
+                vec![(
+                    Expr::Binary {
+                        left: Box::new(col1),
+                        op: BinOp::Multiply,
+                        right: Box::new(Expr::Value(Value::Int64(10))),
+                    },
+                    Some("col1_times_10".into()),
+                )],
+            )
+            .unwrap();
+        assert_eq!(res.columns().next().unwrap().name(), "col1_times_10");
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(10)]),
+                Row::new(vec![Value::Int64(20)]),
+                Row::new(vec![Value::Int64(30)])
+            ]
+        );
+
+        let missing = TableIndex(100);
+        assert_eq!(
+            vm.filter_table(&missing, Expr::Value(Value::Bool(true)))
+                .unwrap_err(),
+            RuntimeError::TableIndexNotFound(missing)
+        );
+    }
+
+    #[test]
+    fn filter_on_nonexistent_column() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER)", &mut vm).unwrap();
+
+        let expected = RuntimeError::ColumnNotFound(ColumnRef {
+            schema_name: None,
+            table_name: Some("table1".into()),
+            col_name: "col2".into(),
+        });
+
+        // reported even if there are no rows to evaluate the predicate on
+        let res = check_single_statement("SELECT * FROM table1 WHERE col2 > 1", &mut vm);
+        assert_eq!(res.unwrap_err(), expected);
+
+        check_single_statement("INSERT INTO table1 VALUES (1), (2), (3)", &mut vm).unwrap();
+
+        let res = check_single_statement(
+            "SELECT * FROM table1 WHERE col1 = 1 AND col2 IS NULL",
+            &mut vm,
+        );
+        assert_eq!(res.unwrap_err(), expected);
+    }
+
+    #[test]
+    fn identifier_lookups_agree() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE SCHEMA Schema1", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE Schema1.Table1 (Col1 INTEGER)", &mut vm).unwrap();
+
+        let table_ref = |schema_name: &str, table_name: &str| TableRef {
+            schema_name: Some(schema_name.into()),
+            table_name: table_name.into(),
+        };
+
+        // exact names are found by every lookup
+        let schema = vm.find_schema(Some("Schema1".into())).unwrap();
+        let table_index = vm
+            .find_table(schema, &table_ref("Schema1", "Table1"))
+            .unwrap();
+        assert!(vm
+            .table(&table_index)
+            .unwrap()
+            .get_column(&"Col1".into())
+            .is_ok());
+
+        // and differently cased names by none
+        assert_eq!(
+            vm.find_schema(Some("schema1".into())).unwrap_err(),
+            RuntimeError::SchemaNotFound("schema1".into())
+        );
+        assert_eq!(
+            vm.find_table(schema, &table_ref("Schema1", "table1"))
+                .unwrap_err(),
+            RuntimeError::TableNotFound(table_ref("Schema1", "table1"))
+        );
+        assert!(matches!(
+            vm.table(&table_index).unwrap().get_column(&"col1".into()),
+            Err(RuntimeError::ColumnNotFound(_))
+        ));
+
+        // the same holds when going through SQL
+        assert!(check_single_statement("SELECT Col1 FROM Schema1.Table1", &mut vm).is_ok());
+        assert!(check_single_statement("SELECT col1 FROM Schema1.Table1", &mut vm).is_err());
+        assert!(check_single_statement("SELECT Col1 FROM Schema1.table1", &mut vm).is_err());
+        assert!(check_single_statement("SELECT Col1 FROM schema1.Table1", &mut vm).is_err());
+    }
+
+    #[test]
+    fn insert_rows_with_differing_arity() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 INTEGER)", &mut vm)
+            .unwrap();
+
+        let res = check_single_statement("INSERT INTO table1 VALUES (1, 2), (3, 4), (5)", &mut vm);
+        assert_eq!(
+            res.unwrap_err(),
+            RuntimeError::InsertRowArityMismatch {
+                table_name: "table1".into(),
+                row_index: 2,
+                row_len: 1,
+                expected_len: 2,
+            }
+        );
+
+        // none of the rows were inserted
+        let res = check_single_statement("SELECT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![]);
+    }
+
+    #[test]
+    fn insert_duplicate_columns() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 INTEGER)", &mut vm)
+            .unwrap();
+
+        let res = check_single_statement("INSERT INTO table1 (col1, col1) VALUES (1, 2)", &mut vm);
+        assert_eq!(
+            res.unwrap_err(),
+            RuntimeError::DuplicateInsertColumn(ColumnRef {
+                schema_name: None,
+                table_name: Some("table1".into()),
+                col_name: "col1".into(),
+            })
+        );
+
+        let res = check_single_statement("SELECT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![]);
+    }
+
+    #[test]
+    fn order_by_alias_resolution() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table1 VALUES (1, 'b'), (2, 'a')", &mut vm).unwrap();
+
+        // the alias shadows the source column of the same name
+        let res = check_single_statement(
+            "SELECT col2 AS col1, col1 AS col2 FROM table1 ORDER BY col1",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("a".to_owned()), Value::Int64(2)]),
+                Row::new(vec![Value::String("b".to_owned()), Value::Int64(1)])
+            ]
+        );
+
+        // two output columns with the same name
+        let res =
+            check_single_statement("SELECT * , col2 AS col1 FROM table1 ORDER BY col1", &mut vm);
+        assert_eq!(
+            res.unwrap_err(),
+            RuntimeError::AmbiguousColumn(ColumnRef {
+                schema_name: None,
+                table_name: None,
+                col_name: "col1".into()
+            })
+        );
+
+        // ... unless they hold the same column
+        let res = check_single_statement(
+            "SELECT col1, table1.col1 FROM table1 ORDER BY col1 DESC",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(2), Value::Int64(2)]),
+                Row::new(vec![Value::Int64(1), Value::Int64(1)])
+            ]
+        );
+
+        // a qualifier picks the column projected from that table
+        check_single_statement("CREATE TABLE table2 (col1 INTEGER)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table2 VALUES (4), (3)", &mut vm).unwrap();
+        let res = check_single_statement(
+            "SELECT table2.col1, table1.col1 FROM table1 CROSS JOIN table2 \
+             ORDER BY table1.col1, table2.col1",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(3), Value::Int64(1)]),
+                Row::new(vec![Value::Int64(4), Value::Int64(1)]),
+                Row::new(vec![Value::Int64(3), Value::Int64(2)]),
+                Row::new(vec![Value::Int64(4), Value::Int64(2)])
+            ]
+        );
+
+        let res = check_single_statement(
+            "SELECT table2.col1, table1.col1 FROM table1 CROSS JOIN table2 ORDER BY col1",
+            &mut vm,
+        );
+        assert_eq!(
+            res.unwrap_err(),
+            RuntimeError::AmbiguousColumn(ColumnRef {
+                schema_name: None,
+                table_name: None,
+                col_name: "col1".into()
+            })
+        );
+    }
+
+    #[test]
+    fn project_wildcard_with_other_columns() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table1 VALUES (1, 'a'), (2, 'b')", &mut vm).unwrap();
+
+        let res = check_single_statement("SELECT col1 * 10 AS big, * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(|c| c.name().as_str()).collect::<Vec<_>>(),
+            vec!["big", "col1", "col2"]
+        );
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(10),
+                    Value::Int64(1),
+                    Value::String("a".to_owned())
+                ]),
+                Row::new(vec![
+                    Value::Int64(20),
+                    Value::Int64(2),
+                    Value::String("b".to_owned())
+                ])
+            ]
+        );
+
+        let res = check_single_statement("SELECT *, col2 AS last, * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.num_columns(), 5);
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("a".to_owned()),
+                    Value::String("a".to_owned()),
+                    Value::Int64(1),
+                    Value::String("a".to_owned())
+                ]),
+                Row::new(vec![
+                    Value::Int64(2),
+                    Value::String("b".to_owned()),
+                    Value::String("b".to_owned()),
+                    Value::Int64(2),
+                    Value::String("b".to_owned())
+                ])
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_modes() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a'), (0, 'b'), (2, 'c')",
+            &mut vm,
+        )
+        .unwrap();
+
+        // strict by default
+        let res = check_single_statement("SELECT col2 FROM table1 WHERE col1", &mut vm);
+        assert_eq!(
+            res.unwrap_err(),
+            RuntimeError::FilterWithNonBoolean(
+                Expr::ColumnRef(ColumnRef {
+                    schema_name: None,
+                    table_name: None,
+                    col_name: "col1".into()
+                }),
+                Value::Int64(1)
+            )
+        );
+
+        vm.set_filter_mode(FilterMode::Coercing);
+
+        let res = check_single_statement("SELECT col2 FROM table1 WHERE col1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("a".to_owned())]),
+                Row::new(vec![Value::String("c".to_owned())])
+            ]
+        );
+
+        // booleans work the same in both modes
+        let res = check_single_statement("SELECT col2 FROM table1 WHERE col1 = 0", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![Row::new(vec![Value::String("b".to_owned())])]
+        );
+
+        // strings have no truth value
+        let res = check_single_statement("SELECT col2 FROM table1 WHERE col2", &mut vm);
+        assert!(matches!(
+            res.unwrap_err(),
+            RuntimeError::FilterWithNonBoolean(_, Value::String(_))
+        ));
+
+        vm.set_filter_mode(FilterMode::Strict);
+        let res = check_single_statement("SELECT col2 FROM table1 WHERE col1", &mut vm);
+        assert!(matches!(
+            res.unwrap_err(),
+            RuntimeError::FilterWithNonBoolean(_, Value::Int64(1))
+        ));
+    }
+
+    #[test]
+    fn group_by() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+
+        let table_reg_index = RegisterIndex::default();
+        let group_by = |expr: Expr| IntermediateCode {
+            instrs: vec![
+                Instruction::Source {
+                    index: table_reg_index,
+                    name: TableRef {
+                        schema_name: None,
+                        table_name: "table1".into(),
+                    },
+                },
+                Instruction::GroupBy {
+                    index: table_reg_index,
+                    expr,
+                },
+            ],
+        };
+        let col1 = Column::new("col1".into(), DataType::Int(None), vec![], false);
+        let col2 = Column::new("col2".into(), DataType::String, vec![], false);
+        let col1_ref = Expr::ColumnRef(ColumnRef {
+            schema_name: None,
+            table_name: None,
+            col_name: "col1".into(),
+        });
+
+        // no rows, no groups
+        vm.execute_ic(&group_by(col1_ref.clone())).unwrap();
+        assert_eq!(
+            vm.registers[&table_reg_index],
+            Register::GroupedTable {
+                grouped_col: col1.clone(),
+                other_cols: vec![col2.clone()],
+                data: vec![],
+            }
+        );
+
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a'), (2, 'b'), (1, 'c'), (3, 'd')",
+            &mut vm,
+        )
+        .unwrap();
+
+        vm.execute_ic(&group_by(col1_ref.clone())).unwrap();
+        assert_eq!(
+            vm.registers[&table_reg_index],
+            Register::GroupedTable {
+                grouped_col: col1.clone(),
+                other_cols: vec![col2.clone()],
+                data: vec![
+                    (
+                        Value::Int64(1),
+                        vec![
+                            Row::new(vec![Value::String("a".to_owned())]),
+                            Row::new(vec![Value::String("c".to_owned())])
+                        ]
+                    ),
+                    (
+                        Value::Int64(2),
+                        vec![Row::new(vec![Value::String("b".to_owned())])]
+                    ),
+                    (
+                        Value::Int64(3),
+                        vec![Row::new(vec![Value::String("d".to_owned())])]
+                    ),
+                ],
+            }
+        );
+
+        // grouping on an expression keeps all the columns in the rows
+        let expr = Expr::Binary {
+            left: Box::new(col1_ref.clone()),
+            op: BinOp::Modulo,
+            right: Box::new(Expr::Value(Value::Int64(2))),
+        };
+        vm.execute_ic(&group_by(expr.clone())).unwrap();
+        assert_eq!(
+            vm.registers[&table_reg_index],
+            Register::GroupedTable {
+                grouped_col: Column::new(
+                    expr.to_string().as_str().into(),
+                    DataType::Int(None),
+                    vec![],
+                    false
+                ),
+                other_cols: vec![col1, col2],
+                data: vec![
+                    (
+                        Value::Int64(1),
+                        vec![
+                            Row::new(vec![Value::Int64(1), Value::String("a".to_owned())]),
+                            Row::new(vec![Value::Int64(1), Value::String("c".to_owned())]),
+                            Row::new(vec![Value::Int64(3), Value::String("d".to_owned())])
+                        ]
+                    ),
+                    (
+                        Value::Int64(0),
+                        vec![Row::new(vec![
+                            Value::Int64(2),
+                            Value::String("b".to_owned())
+                        ])]
+                    ),
+                ],
+            }
+        );
+
+        // a grouped table cannot be grouped again
+        let mut ic = group_by(col1_ref);
+        ic.instrs.push(ic.instrs[1].clone());
+        assert!(matches!(
+            vm.execute_ic(&ic),
+            Err(RuntimeError::RegisterNotATable("group by", _))
+        ));
+    }
+
+    #[test]
+    fn cross_join() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE table2 (col1 INTEGER, col3 INTEGER)", &mut vm)
+            .unwrap();
+
+        // empty on one side
+        check_single_statement("INSERT INTO table1 VALUES (1, 'a'), (2, 'b')", &mut vm).unwrap();
+        let res = check_single_statement("SELECT * FROM table1, table2", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["table1.col1", "col2", "table2.col1", "col3"]
+        );
+        assert_eq!(res.all_data(), vec![]);
+
+        check_single_statement(
+            "INSERT INTO table2 VALUES (1, 10), (3, 30), (2, 20)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let res = check_single_statement("SELECT * FROM table1 CROSS JOIN table2", &mut vm)
+            .unwrap()
+            .unwrap();
+        let row = |col1: i64, col2: &str, col1_2: i64, col3: i64| {
+            Row::new(vec![
+                Value::Int64(col1),
+                Value::String(col2.to_owned()),
+                Value::Int64(col1_2),
+                Value::Int64(col3),
+            ])
+        };
+        assert_eq!(
+            res.all_data(),
+            vec![
+                row(1, "a", 1, 10),
+                row(1, "a", 3, 30),
+                row(1, "a", 2, 20),
+                row(2, "b", 1, 10),
+                row(2, "b", 3, 30),
+                row(2, "b", 2, 20),
+            ]
+        );
+
+        let res = check_single_statement(
+            "SELECT col2, col3 FROM table1, table2 WHERE table1.col1 = table2.col1",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("a".to_owned()), Value::Int64(10)]),
+                Row::new(vec![Value::String("b".to_owned()), Value::Int64(20)]),
+            ]
+        );
+
+        // the condition on only one of the tables is applied before joining them.
+        let res = vm
+            .execute(
+                "SELECT col2, col3 FROM table1, table2 \
+                WHERE table1.col1 = table2.col1 AND table2.col3 > 10",
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![Row::new(vec![
+                Value::String("b".to_owned()),
+                Value::Int64(20)
+            ])]
+        );
+
+        // an unqualified reference to a column in both tables is not resolved
+        let res = check_single_statement("SELECT * FROM table1, table2 WHERE col1 = 1", &mut vm);
+        assert!(matches!(res, Err(RuntimeError::ColumnNotFound(_))));
+    }
+
+    #[test]
+    fn search_path() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE SCHEMA schema1", &mut vm).unwrap();
+        check_single_statement("CREATE SCHEMA schema2", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE schema1.table1 (col1 INTEGER)", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE schema2.table1 (col1 INTEGER)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO schema1.table1 VALUES (1)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO schema2.table1 VALUES (2)", &mut vm).unwrap();
+
+        // only the default schema by default
+        assert_eq!(
+            check_single_statement("SELECT * FROM table1", &mut vm).unwrap_err(),
+            RuntimeError::TableNotFound(TableRef {
+                schema_name: None,
+                table_name: "table1".into()
+            })
+        );
+
+        vm.set_search_path(vec!["schema1".into(), "schema2".into()]);
+        let res = check_single_statement("SELECT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(1)])]);
+
+        // missing schemas are skipped
+        vm.set_search_path(vec!["schema3".into(), "schema2".into(), "schema1".into()]);
+        let res = check_single_statement("SELECT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(2)])]);
+
+        // qualified names ignore the search path
+        let res = check_single_statement("SELECT * FROM schema1.table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(1)])]);
+
+        // new tables go into the first existing schema
+        check_single_statement("CREATE TABLE table2 (col1 INTEGER)", &mut vm).unwrap();
+        let schema = vm.database.schema_by_name(&"schema2".into()).unwrap();
+        assert!(vm
+            .find_table(
+                schema,
+                &TableRef {
+                    schema_name: None,
+                    table_name: "table2".into()
+                }
+            )
+            .is_ok());
+        assert!(vm
+            .find_table(
+                vm.database.default_schema(),
+                &TableRef {
+                    schema_name: None,
+                    table_name: "table2".into()
+                }
+            )
+            .is_err());
+
+        // without any existing schemas in the search path, tables are created and looked up in
+        // the default schema
+        vm.set_search_path(vec!["schema3".into()]);
+        check_single_statement("CREATE TABLE table3 (col1 INTEGER)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table3 VALUES (3)", &mut vm).unwrap();
+        let res = check_single_statement("SELECT * FROM table3", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(3)])]);
+        vm.set_search_path(vec![]);
+        assert!(check_single_statement("SELECT * FROM table3", &mut vm).is_ok());
+    }
+
+    #[test]
+    fn inner_join() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (id INTEGER, col1 STRING)", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE table2 (id INTEGER, col2 INTEGER)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a'), (2, 'b'), (NULL, 'c')",
+            &mut vm,
+        )
+        .unwrap();
+        check_single_statement(
+            "INSERT INTO table2 VALUES (1, 10), (1, 11), (3, 30), (4, 40)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let res = check_single_statement(
+            "SELECT * FROM table1 JOIN table2 ON table1.id = table2.id",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["table1.id", "col1", "table2.id", "col2"]
+        );
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("a".to_owned()),
+                    Value::Int64(1),
+                    Value::Int64(10)
+                ]),
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("a".to_owned()),
+                    Value::Int64(1),
+                    Value::Int64(11)
+                ]),
+            ]
+        );
+
+        // unqualified refs to columns only one of the tables has
+        let res = check_single_statement(
+            "SELECT * FROM table1 INNER JOIN table2 ON col2 > 10 AND col1 = 'b'",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(res.all_data().len(), 3);
+
+        assert_eq!(
+            check_single_statement(
+                "SELECT * FROM table1 JOIN table2 ON table1.col3 = 1",
+                &mut vm
+            )
+            .map(|_| ()),
+            Err(RuntimeError::ColumnNotFound(ColumnRef {
+                schema_name: None,
+                table_name: Some("table1".into()),
+                col_name: "col3".into(),
+            }))
+        );
+        assert!(matches!(
+            check_single_statement("SELECT * FROM table1 JOIN table2 ON col2", &mut vm),
+            Err(RuntimeError::FilterWithNonBoolean(_, Value::Int64(_)))
+        ));
+    }
+
+    #[test]
+    fn outer_join() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE a (id INTEGER, col1 STRING)", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE b (a_id INTEGER, col2 INTEGER)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO a VALUES (1, 'x'), (2, 'y'), (3, 'z')", &mut vm)
+            .unwrap();
+        check_single_statement("INSERT INTO b VALUES (1, 10), (1, 11), (4, 40)", &mut vm).unwrap();
+
+        let res = check_single_statement("SELECT * FROM a LEFT JOIN b ON a.id = b.a_id", &mut vm)
+            .unwrap()
+            .unwrap();
+        // same columns as an inner join
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["id", "col1", "a_id", "col2"]
+        );
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("x".to_owned()),
+                    Value::Int64(1),
+                    Value::Int64(10)
+                ]),
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("x".to_owned()),
+                    Value::Int64(1),
+                    Value::Int64(11)
+                ]),
+                Row::new(vec![
+                    Value::Int64(2),
+                    Value::String("y".to_owned()),
+                    Value::Null,
+                    Value::Null
+                ]),
+                Row::new(vec![
+                    Value::Int64(3),
+                    Value::String("z".to_owned()),
+                    Value::Null,
+                    Value::Null
+                ]),
+            ]
+        );
+
+        let res = check_single_statement(
+            "SELECT * FROM a RIGHT OUTER JOIN b ON a.id = b.a_id",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["id", "col1", "a_id", "col2"]
+        );
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("x".to_owned()),
+                    Value::Int64(1),
+                    Value::Int64(10)
+                ]),
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("x".to_owned()),
+                    Value::Int64(1),
+                    Value::Int64(11)
+                ]),
+                Row::new(vec![
+                    Value::Null,
+                    Value::Null,
+                    Value::Int64(4),
+                    Value::Int64(40)
+                ]),
+            ]
+        );
+
+        // padded columns can be projected like any other
+        let res = check_single_statement(
+            "SELECT col1 AS c1, col2 AS c2 FROM a LEFT JOIN b ON a.id = b.a_id WHERE col2 IS NULL",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("y".to_owned()), Value::Null]),
+                Row::new(vec![Value::String("z".to_owned()), Value::Null]),
+            ]
+        );
+    }
+
+    #[test]
+    fn natural_join() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE table2 (col3 INTEGER, col1 INTEGER)", &mut vm)
+            .unwrap();
+        check_single_statement("CREATE TABLE table3 (col4 INTEGER)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a'), (2, 'b'), (NULL, 'c')",
+            &mut vm,
+        )
+        .unwrap();
+        check_single_statement(
+            "INSERT INTO table2 VALUES (10, 1), (30, 3), (11, 1), (40, NULL)",
+            &mut vm,
+        )
+        .unwrap();
+        check_single_statement("INSERT INTO table3 VALUES (5), (6)", &mut vm).unwrap();
+
+        let res = check_single_statement("SELECT * FROM table1 NATURAL JOIN table2", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["col1", "col2", "col3"]
+        );
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("a".to_owned()),
+                    Value::Int64(10)
+                ]),
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("a".to_owned()),
+                    Value::Int64(11)
+                ]),
+            ]
+        );
+
+        // no shared columns
+        let res = check_single_statement("SELECT * FROM table2 NATURAL JOIN table3", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["col3", "col1", "col4"]
+        );
+        assert_eq!(res.all_data().len(), 8);
+    }
+
+    #[test]
+    fn execute_traced() {
+        let query = "CREATE TABLE table1 (col1 INTEGER); \
+                     INSERT INTO table1 VALUES (1), (2); \
+                     SELECT col1 FROM table1 WHERE col1 > 1";
+
+        let mut vm = VirtualMachine::default();
+        let (res, ics) = vm.execute_traced(query).unwrap();
+        assert_eq!(ics, codegen_str(query).unwrap());
+
+        let mut vm = VirtualMachine::default();
+        assert_eq!(
+            vm.execute(query).unwrap().unwrap().all_data(),
+            res.unwrap().all_data()
+        );
+    }
+
+    #[test]
+    fn union() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE table2 (col3 INTEGER, col4 STRING)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a'), (2, 'b'), (1, 'a')",
+            &mut vm,
+        )
+        .unwrap();
+        check_single_statement("INSERT INTO table2 VALUES (2, 'b'), (3, 'c')", &mut vm).unwrap();
+
+        let row = |col1: i64, col2: &str| {
+            Row::new(vec![Value::Int64(col1), Value::String(col2.to_owned())])
+        };
+
+        let res = check_single_statement(
+            "SELECT col1, col2 FROM table1 UNION SELECT col3, col4 FROM table2",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(res.all_data(), vec![row(1, "a"), row(2, "b"), row(3, "c")]);
+
+        let res = check_single_statement(
+            "SELECT col1 AS a, col2 AS b FROM table1 UNION ALL SELECT * FROM table2 ORDER BY a",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            res.all_data(),
+            vec![
+                row(1, "a"),
+                row(1, "a"),
+                row(2, "b"),
+                row(2, "b"),
+                row(3, "c")
+            ]
+        );
+
+        assert_eq!(
+            check_single_statement(
+                "SELECT col1 FROM table1 UNION SELECT * FROM table2",
+                &mut vm
+            )
+            .unwrap_err(),
+            RuntimeError::UnionColumnCountMismatch(1, 2)
+        );
+        assert_eq!(
+            check_single_statement(
+                "SELECT col1 FROM table1 UNION SELECT col4 FROM table2",
+                &mut vm
+            )
+            .unwrap_err(),
+            RuntimeError::UnionColumnTypeMismatch {
+                position: 0,
+                left: DataType::Int(None),
+                right: DataType::String,
+            }
+        );
+    }
+
+    #[test]
+    fn drop_table() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE SCHEMA schema1", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER)", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE schema1.table1 (col1 INTEGER)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO schema1.table1 VALUES (1)", &mut vm).unwrap();
+
+        check_single_statement("DROP TABLE table1", &mut vm).unwrap();
+        let table1 = TableRef {
+            schema_name: None,
+            table_name: "table1".into(),
+        };
+        assert_eq!(
+            check_single_statement("SELECT * FROM table1", &mut vm).unwrap_err(),
+            RuntimeError::TableNotFound(table1)
+        );
+        assert_eq!(vm.database.default_schema().tables(), &vec![]);
+        assert_eq!(vm.tables.len(), 1);
+
+        // the other schema is untouched
+        let res = check_single_statement("SELECT * FROM schema1.table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(1)])]);
+
+        assert_eq!(
+            check_single_statement("DROP TABLE table1", &mut vm).unwrap_err(),
+            RuntimeError::TableNotFound(table1)
+        );
+        check_single_statement("DROP TABLE IF EXISTS table1", &mut vm).unwrap();
+        assert_eq!(
+            check_single_statement("DROP TABLE schema2.table1", &mut vm).unwrap_err(),
+            RuntimeError::SchemaNotFound("schema2".into())
+        );
+        check_single_statement("DROP TABLE IF EXISTS schema2.table1", &mut vm).unwrap();
+
+        // the name can be reused
+        check_single_statement("CREATE TABLE table1 (col2 INTEGER)", &mut vm).unwrap();
+        let res = check_single_statement("SELECT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["col2"]
+        );
+    }
+
+    #[test]
+    fn union_output_columns() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+        check_single_statement("CREATE TABLE table2 (col3 FLOAT, col4 STRING)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table1 VALUES (1, 'a'), (2, 'b')", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table2 VALUES (2.0, 'b'), (2.5, 'c')", &mut vm)
+            .unwrap();
+
+        let res = check_single_statement(
+            "SELECT col1 AS num, col2 AS str FROM table1 UNION SELECT * FROM table2",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.columns()
+                .map(|c| (c.name().as_str(), c.data_type()))
+                .collect::<Vec<_>>(),
+            vec![("num", &DataType::Float(None)), ("str", &DataType::String)]
+        );
+        // integers are promoted before removing duplicates
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Float64(1.0.into()),
+                    Value::String("a".to_owned())
+                ]),
+                Row::new(vec![
+                    Value::Float64(2.0.into()),
+                    Value::String("b".to_owned())
+                ]),
+                Row::new(vec![
+                    Value::Float64(2.5.into()),
+                    Value::String("c".to_owned())
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn drop_column() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement(
+            "CREATE TABLE table1 (col1 INTEGER, col2 STRING, col3 INTEGER)",
+            &mut vm,
+        )
+        .unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a', 10), (2, 'b', 20)",
+            &mut vm,
+        )
+        .unwrap();
+
+        check_single_statement("ALTER TABLE table1 DROP COLUMN col2", &mut vm).unwrap();
+
+        let res = check_single_statement("SELECT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["col1", "col3"]
+        );
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(1), Value::Int64(10)]),
+                Row::new(vec![Value::Int64(2), Value::Int64(20)]),
+            ]
+        );
+
+        // later columns are still read from the right position
+        let res = check_single_statement("SELECT col3 FROM table1 WHERE col3 > 10", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(20)])]);
+
+        // inserts use the new columns
+        check_single_statement("INSERT INTO table1 VALUES (3, 30)", &mut vm).unwrap();
+
+        assert_eq!(
+            check_single_statement("ALTER TABLE table1 DROP COLUMN col2", &mut vm).unwrap_err(),
+            RuntimeError::ColumnNotFound(ColumnRef {
+                schema_name: None,
+                table_name: Some("table1".into()),
+                col_name: "col2".into(),
+            })
+        );
+        assert!(matches!(
+            check_single_statement(
+                &format!("ALTER TABLE table1 DROP COLUMN {}", TABLE_UNIQUE_KEY_NAME),
+                &mut vm
+            ),
+            Err(RuntimeError::ColumnNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn rename_column() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table1 VALUES (1, 'a'), (2, 'b')", &mut vm).unwrap();
+
+        check_single_statement("ALTER TABLE table1 RENAME COLUMN col1 TO col3", &mut vm).unwrap();
+
+        let res = check_single_statement("SELECT col3 FROM table1 WHERE col3 = 2", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(2)])]);
+        assert_eq!(
+            check_single_statement("SELECT col1 FROM table1", &mut vm).unwrap_err(),
+            RuntimeError::ExprExecError(ExprExecError::NoSuchColumn("col1".into()))
+        );
+
+        assert_eq!(
+            check_single_statement("ALTER TABLE table1 RENAME COLUMN col1 TO col4", &mut vm)
+                .unwrap_err(),
+            RuntimeError::ColumnNotFound(ColumnRef {
+                schema_name: None,
+                table_name: Some("table1".into()),
+                col_name: "col1".into(),
+            })
+        );
+        assert_eq!(
+            check_single_statement("ALTER TABLE table1 RENAME COLUMN col3 TO col2", &mut vm)
+                .unwrap_err(),
+            RuntimeError::ColumnExists(ColumnRef {
+                schema_name: None,
+                table_name: Some("table1".into()),
+                col_name: "col2".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn boolean_column() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 BOOLEAN)", &mut vm)
+            .unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, true), (2, FALSE), (3, unknown), (4, NULL)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let res = check_single_statement("SELECT col2 FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Bool(true)]),
+                Row::new(vec![Value::Bool(false)]),
+                Row::new(vec![Value::Null]),
+                Row::new(vec![Value::Null]),
+            ]
+        );
+
+        let filtered = |query: &str, vm: &mut VirtualMachine| {
+            check_single_statement(query, vm)
+                .unwrap()
+                .unwrap()
+                .all_data()
+                .into_iter()
+                .map(|row| row.data()[0].clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            filtered("SELECT col1 FROM table1 WHERE col2", &mut vm),
+            vec![Value::Int64(1)]
+        );
+        assert_eq!(
+            filtered("SELECT col1 FROM table1 WHERE col2 = false", &mut vm),
+            vec![Value::Int64(2)]
+        );
+        assert_eq!(
+            filtered("SELECT col1 FROM table1 WHERE col2 IS TRUE", &mut vm),
+            vec![Value::Int64(1)]
+        );
+        assert_eq!(
+            filtered("SELECT col1 FROM table1 WHERE col2 IS FALSE", &mut vm),
+            vec![Value::Int64(2)]
+        );
+        assert_eq!(
+            filtered("SELECT col1 FROM table1 WHERE col2 IS NULL", &mut vm),
+            vec![Value::Int64(3), Value::Int64(4)]
+        );
+
+        // a quoted name is still a column
+        check_single_statement("CREATE TABLE table2 (\"unknown\" BOOLEAN)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table2 VALUES (true)", &mut vm).unwrap();
+        assert_eq!(
+            filtered("SELECT \"unknown\" FROM table2", &mut vm),
+            vec![Value::Bool(true)]
+        );
+    }
+
+    #[test]
+    fn update() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement(
+            "CREATE TABLE table1 (col1 INTEGER, col2 STRING, col3 FLOAT)",
+            &mut vm,
+        )
+        .unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a', 1.5), (2, 'b', 2.5), (3, 'c', 3.5)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let row = |col1: i64, col2: &str, col3: f64| {
+            Row::new(vec![
+                Value::Int64(col1),
+                Value::String(col2.to_owned()),
+                Value::Float64(col3.into()),
+            ])
+        };
+
+        check_single_statement("UPDATE table1 SET col3 = col3 * 2.0", &mut vm).unwrap();
+        let res = check_single_statement("SELECT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![row(1, "a", 3.0), row(2, "b", 5.0), row(3, "c", 7.0)]
+        );
+
+        // only the filtered rows, with every assignment seeing the old values
+        check_single_statement(
+            "UPDATE table1 SET col2 = 'z', col1 = col1 + 10, col3 = col1 WHERE col1 >= 2",
+            &mut vm,
+        )
+        .unwrap();
+        let res = check_single_statement("SELECT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![row(1, "a", 3.0), row(12, "z", 2.0), row(13, "z", 3.0)]
+        );
+
+        check_single_statement("UPDATE table1 SET col2 = NULL WHERE col1 = 1", &mut vm).unwrap();
+        let res = check_single_statement("SELECT col2 FROM table1 WHERE col1 = 1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Null])]);
+
+        assert_eq!(
+            check_single_statement("UPDATE table1 SET col1 = 'a'", &mut vm).unwrap_err(),
+            RuntimeError::ColumnTypeMismatch {
+                col_name: "col1".into(),
+                data_type: DataType::Int(None),
+                value: Value::String("a".to_owned()),
+            }
+        );
+        assert!(matches!(
+            check_single_statement("UPDATE table1 SET col4 = 1", &mut vm),
+            Err(RuntimeError::ColumnNotFound(_))
+        ));
+
+        // nothing changed by the failed updates
+        let res = check_single_statement("SELECT col1 FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(1)]),
+                Row::new(vec![Value::Int64(12)]),
+                Row::new(vec![Value::Int64(13)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn update_assignments_together() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE m (a INT, b INT); INSERT INTO m VALUES (1, 1), (2, 5)")
+            .unwrap();
+
+        let ints = |rows: &[[i64; 2]]| -> Vec<Row> {
+            rows.iter()
+                .map(|row| Row::new(row.iter().map(|v| Value::Int64(*v)).collect()))
+                .collect()
+        };
+
+        // later assignments do not see the values set by earlier ones
+        let res = vm
+            .execute("UPDATE m SET a = b + 1, b = a + 10; SELECT * FROM m")
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), ints(&[[2, 11], [6, 12]]));
+
+        let res = vm
+            .execute("UPDATE m SET a = b, b = a WHERE a = 2; SELECT * FROM m")
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), ints(&[[11, 2], [6, 12]]));
+
+        // a failing assignment leaves the earlier ones unwritten too
+        assert!(matches!(
+            vm.execute("UPDATE m SET a = 100, b = 'x'"),
+            Err(ExecutionError::RuntimeError(
+                RuntimeError::ColumnTypeMismatch { .. }
+            ))
+        ));
+        assert!(matches!(
+            vm.execute("UPDATE m SET a = 1, a = 2"),
+            Err(ExecutionError::RuntimeError(
+                RuntimeError::DuplicateUpdateColumn(_)
+            ))
+        ));
+        let res = vm.execute("SELECT * FROM m").unwrap().unwrap();
+        assert_eq!(res.all_data(), ints(&[[11, 2], [6, 12]]));
+    }
+
+    #[test]
+    fn filtered_tables_dropped() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (a INT); INSERT INTO t VALUES (1), (2), (3)")
+            .unwrap();
+        let num_tables = vm.tables.len();
+
+        for _ in 0..3 {
+            vm.execute("UPDATE t SET a = a + 1 WHERE a > 1").unwrap();
+            vm.execute("SELECT a FROM t WHERE a > 1").unwrap();
+            vm.execute("SELECT * FROM t WHERE a > 1").unwrap();
+        }
+        assert!(vm.derived_from.is_empty());
+        assert_eq!(vm.tables.len(), num_tables);
+
+        // also when the statement fails
+        vm.execute("UPDATE t SET a = 'x' WHERE a > 1").unwrap_err();
+        assert!(vm.derived_from.is_empty());
+    }
+
+    #[test]
+    fn list_tables() {
+        let table_ref = |schema: &str, table: &str| TableRef {
+            schema_name: Some(schema.into()),
+            table_name: table.into(),
+        };
+        let expected = vec![
+            table_ref("a", "x"),
+            table_ref("main", "t1"),
+            table_ref("main", "t2"),
+            table_ref("main", "t3"),
+        ];
+
+        // the listing does not depend on the order the tables were created in.
+        for sql in [
+            "CREATE TABLE t3 (c INT); CREATE TABLE t1 (c INT); CREATE SCHEMA a; \
+             CREATE TABLE a.x (c INT); CREATE TABLE t2 (c INT)",
+            "CREATE SCHEMA a; CREATE TABLE t1 (c INT); CREATE TABLE t2 (c INT); \
+             CREATE TABLE a.x (c INT); CREATE TABLE t3 (c INT)",
+        ] {
+            let mut vm = VirtualMachine::default();
+            vm.execute(sql).unwrap();
+            assert_eq!(vm.list_tables(), expected);
+            assert_eq!(vm.list_tables(), vm.list_tables());
+        }
+    }
+
+    #[test]
+    fn dump_schema() {
+        let mut vm = VirtualMachine::default();
+
+        vm.execute(
+            "CREATE TABLE table1 (col1 INTEGER DEFAULT 5 NOT NULL, col2 STRING); \
+             CREATE SCHEMA schema1; \
+             CREATE TABLE schema1.table2 (col3 INTEGER CHECK (col3 > 0), \
+             \"select\" BOOLEAN CHECK (\"select\" IS NOT NULL))",
+        )
+        .unwrap();
+
+        let dump = vm.dump_schema();
+        assert_eq!(
+            dump,
+            "CREATE TABLE \"table1\" (\"col1\" INT DEFAULT 5 NOT NULL, \"col2\" STRING);\n\
+             CREATE SCHEMA \"schema1\";\n\
+             CREATE TABLE \"schema1\".\"table2\" (\"col3\" INT CHECK (\"col3\" > 0), \
+             \"select\" BOOLEAN CHECK (\"select\" IS NOT NULL));\n"
+        );
+
+        let mut new_vm = VirtualMachine::default();
+        new_vm.execute(&dump).unwrap();
+        assert_eq!(new_vm.dump_schema(), dump);
+
+        for (table, schema) in [("table1", None), ("table2", Some("schema1".into()))] {
+            let table_ref = TableRef {
+                schema_name: schema,
+                table_name: table.into(),
+            };
+            let original = &vm.tables[&vm.resolve_table(&table_ref).unwrap()];
+            let recreated = &new_vm.tables[&new_vm.resolve_table(&table_ref).unwrap()];
+            // the options only differ in how the names in their expressions were quoted, which
+            // the identical dumps already cover.
+            let names_and_types = |table: &Table| {
+                table
+                    .columns()
+                    .map(|col| (*col.name(), col.data_type().clone()))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(names_and_types(original), names_and_types(recreated));
+        }
+
+        vm.set_quote_style(QuoteStyle::Backtick);
+        assert!(vm.dump_schema().ends_with(
+            "CREATE TABLE `schema1`.`table2` (`col3` INT CHECK (`col3` > 0), \
+             `select` BOOLEAN CHECK (`select` IS NOT NULL));\n"
+        ));
+        vm.set_quote_style(QuoteStyle::None);
+        assert!(vm.dump_schema().ends_with(
+            "CREATE TABLE schema1.table2 (col3 INT CHECK (col3 > 0), \
+             select BOOLEAN CHECK (select IS NOT NULL));\n"
+        ));
+    }
+
+    #[test]
+    fn count() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+
+        let count = |query: &str, vm: &mut VirtualMachine| {
+            check_single_statement(query, vm)
+                .unwrap()
+                .unwrap()
+                .all_data()
+        };
+        let ints = |values: &[i64]| Row::new(values.iter().map(|v| Value::Int64(*v)).collect());
+
+        // an empty table is a single empty group
+        assert_eq!(
+            count("SELECT COUNT(*) AS c FROM table1", &mut vm),
+            vec![ints(&[0])]
+        );
+        assert_eq!(
+            count(
+                "SELECT col1, COUNT(*) AS c FROM table1 GROUP BY col1",
+                &mut vm
+            ),
+            vec![]
+        );
+
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a'), (2, NULL), (1, NULL), (3, 'd')",
+            &mut vm,
+        )
+        .unwrap();
+
+        assert_eq!(
+            count(
+                "SELECT COUNT(*) AS c1, count(col2) AS c2, COUNT(*) + 1 AS c3 FROM table1",
+                &mut vm
+            ),
+            vec![ints(&[4, 2, 5])]
+        );
+        assert_eq!(
+            count(
+                "SELECT COUNT(col2) AS c FROM table1 WHERE col1 = 1",
+                &mut vm
+            ),
+            vec![ints(&[1])]
+        );
+        assert_eq!(
+            count(
+                "SELECT col1, COUNT(*) AS c1, COUNT(col2) AS c2 FROM table1 GROUP BY col1",
+                &mut vm
+            ),
+            vec![ints(&[1, 2, 1]), ints(&[2, 1, 0]), ints(&[3, 1, 1])]
+        );
+
+        let res = check_single_statement("SELECT COUNT(*) AS c FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(|c| c.data_type()).collect::<Vec<_>>(),
+            vec![&DataType::Int(None)]
+        );
+
+        assert!(matches!(
+            check_single_statement("SELECT col1 FROM table1 WHERE COUNT(*) > 1", &mut vm),
+            Err(RuntimeError::ExprExecError(
+                ExprExecError::MisplacedAggregate(_)
+            ))
+        ));
+        assert!(matches!(
+            check_single_statement("SELECT COUNT(col1, col2) AS c FROM table1", &mut vm),
+            Err(RuntimeError::ExprExecError(
+                ExprExecError::WrongArgumentCount {
+                    expected: 1,
+                    got: 2,
+                    ..
+                }
+            ))
+        ));
+
+        // columns next to aggregates must be grouped by, whatever the number of rows
+        let ungrouped = |res: Result<Option<Table>, ExecutionError>, expected: &str| {
+            matches!(
+                res,
+                Err(ExecutionError::CodegenError(CodegenError::UngroupedColumn(col_name)))
+                    if col_name == expected
+            )
+        };
+        assert!(ungrouped(
+            vm.execute("SELECT col1, COUNT(*) AS c FROM table1"),
+            "col1"
+        ));
+        assert!(ungrouped(
+            vm.execute("SELECT COUNT(*) + col2 FROM table1 WHERE col1 = 2"),
+            "col2"
+        ));
+        assert!(ungrouped(vm.execute("SELECT *, COUNT(*) FROM table1"), "*"));
+        assert!(ungrouped(
+            vm.execute("SELECT col1 FROM table1 HAVING COUNT(*) > 1"),
+            "col1"
+        ));
+        vm.execute("CREATE TABLE one (col1 INTEGER); INSERT INTO one VALUES (1)")
+            .unwrap();
+        assert!(ungrouped(
+            vm.execute("SELECT COUNT(*), col1 FROM one"),
+            "col1"
+        ));
+        vm.execute("CREATE TABLE none (col1 INTEGER)").unwrap();
+        assert!(ungrouped(
+            vm.execute("SELECT COUNT(*), col1 FROM none"),
+            "col1"
+        ));
+    }
+
+    #[test]
+    fn aggregates() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement(
+            "CREATE TABLE table1 (col1 INTEGER, col2 INTEGER, col3 FLOAT, col4 STRING)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let query = |query: &str, vm: &mut VirtualMachine| {
+            check_single_statement(query, vm)
+                .unwrap()
+                .unwrap()
+                .all_data()
+        };
+
+        // nothing to aggregate gives NULL
+        assert_eq!(
+            query(
+                "SELECT SUM(col2) AS s, AVG(col2) AS a, MIN(col2) AS mn, MAX(col2) AS mx FROM table1",
+                &mut vm
+            ),
+            vec![Row::new(vec![Value::Null; 4])]
+        );
+        // of a type which is not known, instead of any particular one
+        let res = check_single_statement("SELECT AVG(col2) AS a FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(|c| c.data_type()).collect::<Vec<_>>(),
+            vec![&crate::value::unknown_type()]
+        );
+        assert_eq!(
+            res.columns().next().unwrap().data_type().to_string(),
+            "unknown"
+        );
+        // which can be combined with any other type
+        let res = vm
+            .execute("SELECT AVG(col2) FROM table1 UNION SELECT 'x'")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(|c| c.data_type()).collect::<Vec<_>>(),
+            vec![&DataType::String]
+        );
+
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 1, 1.5, 'b'), (2, NULL, NULL, NULL), (1, 4, 2.0, 'a'), (2, 2, 0.5, 'c')",
+            &mut vm,
+        )
+        .unwrap();
+
+        assert_eq!(
+            query(
+                "SELECT SUM(col2) AS s, AVG(col2) AS a, SUM(col3) AS fs, AVG(col3) AS fa FROM table1",
+                &mut vm
+            ),
+            vec![Row::new(vec![
+                Value::Int64(7),
+                Value::Float64((7.0 / 3.0).into()),
+                Value::Float64(4.0.into()),
+                Value::Float64((4.0 / 3.0).into()),
+            ])]
+        );
+        assert_eq!(
+            query(
+                "SELECT MIN(col2) AS mn, MAX(col3) AS mx, MIN(col4) AS smn, MAX(col4) AS smx FROM table1",
+                &mut vm
+            ),
+            vec![Row::new(vec![
+                Value::Int64(1),
+                Value::Float64(2.0.into()),
+                Value::String("a".to_owned()),
+                Value::String("c".to_owned()),
+            ])]
+        );
+        assert_eq!(
+            query(
+                "SELECT col1, SUM(col2) AS s, MAX(col4) AS mx FROM table1 GROUP BY col1",
+                &mut vm
+            ),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::Int64(5),
+                    Value::String("b".to_owned())
+                ]),
+                Row::new(vec![
+                    Value::Int64(2),
+                    Value::Int64(2),
+                    Value::String("c".to_owned())
+                ]),
+            ]
+        );
+
+        assert!(matches!(
+            check_single_statement("SELECT SUM(col4) AS s FROM table1", &mut vm),
+            Err(RuntimeError::ExprExecError(
+                ExprExecError::NonNumericAggregate { .. }
+            ))
+        ));
+        assert!(matches!(
+            check_single_statement("SELECT AVG(col4) AS a FROM table1", &mut vm),
+            Err(RuntimeError::ExprExecError(
+                ExprExecError::NonNumericAggregate { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn limit_all() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table1 VALUES (3), (1), (2)", &mut vm).unwrap();
+
+        let ints = |values: &[i64]| {
+            values
+                .iter()
+                .map(|v| Row::new(vec![Value::Int64(*v)]))
+                .collect::<Vec<_>>()
+        };
+
+        let res = check_single_statement("SELECT * FROM table1 ORDER BY col1 LIMIT ALL", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), ints(&[1, 2, 3]));
+
+        let res = check_single_statement("SELECT * FROM table1 ORDER BY col1 LIMIT 2", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), ints(&[1, 2]));
+
+        let res = check_single_statement(
+            "SELECT * FROM table1 ORDER BY col1 LIMIT ALL OFFSET 1",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(res.all_data(), ints(&[2, 3]));
+    }
+
+    #[test]
+    fn offset() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER)", &mut vm).unwrap();
+        for i in 0..30 {
+            check_single_statement(&format!("INSERT INTO table1 VALUES ({})", i), &mut vm).unwrap();
+        }
+
+        let res = check_single_statement(
+            "SELECT * FROM table1 ORDER BY col1 LIMIT 10 OFFSET 20",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            (20..30)
+                .map(|v| Row::new(vec![Value::Int64(v)]))
+                .collect::<Vec<_>>()
+        );
+
+        let res = check_single_statement("SELECT * FROM table1 LIMIT 10 OFFSET 25", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data().len(), 5);
+
+        // skipping past the end leaves an empty table
+        let res = check_single_statement("SELECT * FROM table1 OFFSET 100", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![]);
+        assert_eq!(res.columns().count(), 1);
+    }
+
+    #[test]
+    fn lateral_aliases() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE t (a INTEGER)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO t VALUES (1), (5)", &mut vm).unwrap();
+
+        let query = "SELECT a * 2 AS d, d + 1 AS e FROM t";
+        assert!(matches!(
+            vm.execute(query),
+            Err(ExecutionError::RuntimeError(RuntimeError::ExprExecError(
+                ExprExecError::NoSuchColumn(_)
+            )))
+        ));
+
+        vm.set_lateral_aliases(true);
+        let res = vm.execute(query).unwrap().unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["d", "e"]
+        );
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(2), Value::Int64(3)]),
+                Row::new(vec![Value::Int64(10), Value::Int64(11)]),
+            ]
+        );
+
+        // aliases can be chained, and only earlier ones are visible
+        let res = vm
+            .execute("SELECT a AS d, d * 10 AS e, e + d AS f FROM t")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(1), Value::Int64(10), Value::Int64(11)]),
+                Row::new(vec![Value::Int64(5), Value::Int64(50), Value::Int64(55)]),
+            ]
+        );
+        assert!(matches!(
+            vm.execute("SELECT e + 1 AS d, a AS e FROM t"),
+            Err(ExecutionError::RuntimeError(RuntimeError::ExprExecError(
+                ExprExecError::NoSuchColumn(_)
+            )))
+        ));
+    }
+
+    #[test]
+    fn string_length_warnings() {
+        let mut vm = VirtualMachine::default();
+
+        vm.execute("CREATE TABLE table1 (col1 VARCHAR(3), col2 STRING)")
+            .unwrap();
+
+        // unchecked by default
+        let (_, warnings) = vm
+            .execute_with_warnings("INSERT INTO table1 VALUES ('abcdef', 'abcdef')")
+            .unwrap();
+        assert_eq!(warnings, vec![]);
+
+        vm.set_string_length_mode(StringLengthMode::Truncate);
+        let (_, warnings) = vm
+            .execute_with_warnings("INSERT INTO table1 VALUES ('ghijkl', 'ghijkl'), ('mn', 'mn')")
+            .unwrap();
+        assert_eq!(
+            warnings,
+            vec![Warning::StringTruncated {
+                col_name: "col1".into(),
+                max_len: 3,
+                value: Value::String("ghijkl".to_owned()),
+            }]
+        );
+
+        // rows are still returned along with the warnings
+        let (res, warnings) = vm
+            .execute_with_warnings(
+                "UPDATE table1 SET col1 = 'xyzw' WHERE col1 = 'mn'; SELECT * FROM table1",
+            )
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            res.unwrap().all_data(),
+            vec![
+                Row::new(vec![
+                    Value::String("abcdef".to_owned()),
+                    Value::String("abcdef".to_owned())
+                ]),
+                Row::new(vec![
+                    Value::String("ghi".to_owned()),
+                    Value::String("ghijkl".to_owned())
+                ]),
+                Row::new(vec![
+                    Value::String("xyz".to_owned()),
+                    Value::String("mn".to_owned())
+                ]),
+            ]
+        );
+
+        // warnings are not kept around for the next statement
+        let (_, warnings) = vm.execute_with_warnings("SELECT * FROM table1").unwrap();
+        assert_eq!(warnings, vec![]);
+
+        vm.set_string_length_mode(StringLengthMode::Strict);
+        assert!(matches!(
+            vm.execute("INSERT INTO table1 VALUES ('abcd', 'a')"),
+            Err(ExecutionError::RuntimeError(RuntimeError::StringTooLong {
+                max_len: 3,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn distinct() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 STRING)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (2, 'a'), (1, 'b'), (2, 'a'), (NULL, 'c'), (1, 'c'), (NULL, 'c')",
+            &mut vm,
+        )
+        .unwrap();
+
+        // first occurrences are kept, in order
+        let res = check_single_statement("SELECT DISTINCT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(2), Value::String("a".to_owned())]),
+                Row::new(vec![Value::Int64(1), Value::String("b".to_owned())]),
+                Row::new(vec![Value::Null, Value::String("c".to_owned())]),
+                Row::new(vec![Value::Int64(1), Value::String("c".to_owned())]),
+            ]
+        );
+
+        let res = check_single_statement("SELECT DISTINCT col1 AS c FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(2)]),
+                Row::new(vec![Value::Int64(1)]),
+                Row::new(vec![Value::Null]),
+            ]
+        );
+
+        // duplicates are removed before the limit
+        let res = check_single_statement(
+            "SELECT DISTINCT col2 AS c FROM table1 ORDER BY c LIMIT 2",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("a".to_owned())]),
+                Row::new(vec![Value::String("b".to_owned())]),
+            ]
+        );
+    }
+
+    #[test]
+    fn having() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE emp (dept STRING, salary INTEGER)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO emp VALUES ('a', 10), ('b', 20), ('a', 30), ('c', 5), ('b', 1), ('a', 2)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let res = check_single_statement(
+            "SELECT dept, COUNT(*) AS n FROM emp GROUP BY dept HAVING COUNT(*) > 1",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("a".to_owned()), Value::Int64(3)]),
+                Row::new(vec![Value::String("b".to_owned()), Value::Int64(2)]),
+            ]
+        );
+
+        // aggregates which are not projected, and the grouped column itself
+        let res = check_single_statement(
+            "SELECT dept, MAX(salary) AS m FROM emp GROUP BY dept HAVING SUM(salary) < 40 AND dept <> 'c'",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![Row::new(vec![
+                Value::String("b".to_owned()),
+                Value::Int64(20)
+            ])]
+        );
+
+        // aliases of projected aggregates
+        let res = check_single_statement(
+            "SELECT dept, MIN(salary) AS m FROM emp GROUP BY dept HAVING m >= 5",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![Row::new(vec![
+                Value::String("c".to_owned()),
+                Value::Int64(5)
+            ])]
+        );
+
+        // an alias does not shadow the grouped column, nor the columns in aggregate arguments
+        let res = check_single_statement(
+            "SELECT dept AS d, COUNT(*) AS dept FROM emp GROUP BY dept HAVING dept <> 'c'",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("a".to_owned()), Value::Int64(3)]),
+                Row::new(vec![Value::String("b".to_owned()), Value::Int64(2)]),
+            ]
+        );
+        let res = check_single_statement(
+            "SELECT dept, MAX(salary) AS salary FROM emp GROUP BY dept HAVING SUM(salary) > 20",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("a".to_owned()), Value::Int64(30)]),
+                Row::new(vec![Value::String("b".to_owned()), Value::Int64(20)]),
+            ]
+        );
+
+        assert!(matches!(
+            check_single_statement(
+                "SELECT dept, COUNT(*) AS n FROM emp GROUP BY dept HAVING COUNT(*)",
+                &mut vm
+            ),
+            Err(RuntimeError::FilterWithNonBoolean(_, Value::Int64(_)))
+        ));
+        assert!(matches!(
+            check_single_statement(
+                "SELECT dept, COUNT(*) AS n FROM emp GROUP BY dept HAVING MAX(bonus) > 1",
+                &mut vm
+            ),
+            Err(RuntimeError::ColumnNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn custom_aggregate() {
+        let mut vm = VirtualMachine::default();
+        vm.register_aggregate(
+            "product",
+            || Value::Int64(1),
+            |state, value| match (state, value) {
+                (Value::Int64(state), Value::Int64(value)) => Ok(Value::Int64(state * value)),
+                (_, value) => Err(ExprExecError::NonNumericAggregate {
+                    name: "PRODUCT".into(),
+                    value,
+                }),
+            },
+            |state| state,
+        );
+
+        vm.execute("CREATE TABLE t (grp STRING, x INTEGER)")
+            .unwrap();
+        vm.execute(
+            "INSERT INTO t VALUES ('a', 2), ('b', 5), ('a', 3), ('a', NULL), ('b', 5), ('a', 2)",
+        )
+        .unwrap();
+
+        let res = vm
+            .execute("SELECT grp, PRODUCT(x) AS p FROM t GROUP BY grp HAVING product(x) > 20")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![Row::new(vec![
+                Value::String("b".to_owned()),
+                Value::Int64(25)
+            ]),]
+        );
+        let res = vm
+            .execute("SELECT grp, product(DISTINCT x) + 1 AS p FROM t GROUP BY grp")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("a".to_owned()), Value::Int64(7)]),
+                Row::new(vec![Value::String("b".to_owned()), Value::Int64(6)]),
+            ]
+        );
+        // without a GROUP BY, the whole table is one group
+        let res = vm.execute("SELECT product(x) FROM t").unwrap().unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(300)])]);
+
+        assert!(matches!(
+            vm.execute("SELECT product(grp) FROM t"),
+            Err(ExecutionError::RuntimeError(RuntimeError::ExprExecError(
+                ExprExecError::NonNumericAggregate { .. }
+            )))
+        ));
+        assert!(matches!(
+            vm.execute("SELECT product(x, x) FROM t"),
+            Err(ExecutionError::RuntimeError(RuntimeError::ExprExecError(
+                ExprExecError::WrongArgumentCount { .. }
+            )))
+        ));
+        // registered aggregates are not known to other VMs
+        assert!(VirtualMachine::default()
+            .execute("CREATE TABLE t (x INTEGER); SELECT product(x) FROM t")
+            .is_err());
+    }
+
+    #[test]
+    fn fetch() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table1 VALUES (4), (2), (5), (1), (3)", &mut vm)
+            .unwrap();
+
+        let query = |query: &str, vm: &mut VirtualMachine| {
+            check_single_statement(query, vm)
+                .unwrap()
+                .unwrap()
+                .all_data()
+        };
+
+        for (offset, count) in [(0, 2), (1, 3), (3, 10), (5, 1), (7, 2)] {
+            let standard = query(
+                &format!(
+                    "SELECT * FROM table1 ORDER BY col1 OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+                    offset, count
+                ),
+                &mut vm,
+            );
+            let limit = query(
+                &format!(
+                    "SELECT * FROM table1 ORDER BY col1 LIMIT {} OFFSET {}",
+                    count, offset
+                ),
+                &mut vm,
+            );
+            assert_eq!(standard, limit);
+        }
+
+        assert_eq!(
+            query(
+                "SELECT * FROM table1 ORDER BY col1 OFFSET 1 ROW FETCH FIRST ROW ONLY",
+                &mut vm
+            ),
+            vec![Row::new(vec![Value::Int64(2)])]
+        );
+        assert_eq!(
+            query(
+                "SELECT * FROM table1 OFFSET 7 ROWS FETCH NEXT 2 ROWS ONLY",
+                &mut vm
+            ),
+            vec![]
+        );
+
+        assert!(matches!(
+            vm.execute("SELECT * FROM table1 FETCH FIRST 50 PERCENT ROWS ONLY"),
+            Err(ExecutionError::CodegenError(
+                CodegenError::UnsupportedStatementForm(..)
+            ))
+        ));
+    }
+
+    #[test]
+    fn order_by_multiple_keys() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement(
+            "CREATE TABLE people (first_name STRING, last_name STRING, age INTEGER)",
+            &mut vm,
+        )
+        .unwrap();
+        check_single_statement(
+            "INSERT INTO people VALUES
+                ('a', 'smith', 30),
+                ('b', 'jones', 25),
+                ('c', 'smith', 40),
+                ('d', 'jones', 25),
+                ('e', 'smith', 35),
+                ('f', 'brown', 50)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let first_names = |query: &str, vm: &mut VirtualMachine| {
+            check_single_statement(query, vm)
+                .unwrap()
+                .unwrap()
+                .all_data()
+                .into_iter()
+                .map(|row| row.data()[0].clone())
+                .collect::<Vec<_>>()
+        };
+        let strings = |values: &[&str]| {
+            values
+                .iter()
+                .map(|v| Value::String(v.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            first_names(
+                "SELECT * FROM people ORDER BY last_name ASC, age DESC",
+                &mut vm
+            ),
+            strings(&["f", "b", "d", "c", "e", "a"])
+        );
+        assert_eq!(
+            first_names("SELECT * FROM people ORDER BY last_name DESC, age", &mut vm),
+            strings(&["a", "e", "c", "b", "d", "f"])
+        );
+
+        // ties keep their order, even when descending
+        assert_eq!(
+            first_names("SELECT * FROM people ORDER BY age DESC", &mut vm),
+            strings(&["f", "c", "e", "a", "b", "d"])
+        );
+    }
+
+    #[test]
+    fn order_by_nulls() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (2), (NULL), (1), (3), (NULL)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let values = |query: &str, vm: &mut VirtualMachine| {
+            check_single_statement(query, vm)
+                .unwrap()
+                .unwrap()
+                .all_data()
+                .into_iter()
+                .map(|row| row.data()[0].clone())
+                .collect::<Vec<_>>()
+        };
+        let (one, two, three) = (Value::Int64(1), Value::Int64(2), Value::Int64(3));
+
+        // NULLs are larger than any other value by default
+        assert_eq!(
+            values("SELECT * FROM table1 ORDER BY col1", &mut vm),
+            vec![
+                one.clone(),
+                two.clone(),
+                three.clone(),
+                Value::Null,
+                Value::Null
+            ]
+        );
+        assert_eq!(
+            values("SELECT * FROM table1 ORDER BY col1 DESC", &mut vm),
+            vec![
+                Value::Null,
+                Value::Null,
+                three.clone(),
+                two.clone(),
+                one.clone()
+            ]
+        );
+
+        // the direction does not affect where NULLs are placed explicitly
+        assert_eq!(
+            values("SELECT * FROM table1 ORDER BY col1 NULLS FIRST", &mut vm),
+            vec![
+                Value::Null,
+                Value::Null,
+                one.clone(),
+                two.clone(),
+                three.clone()
+            ]
+        );
+        assert_eq!(
+            values(
+                "SELECT * FROM table1 ORDER BY col1 DESC NULLS FIRST",
+                &mut vm
+            ),
+            vec![
+                Value::Null,
+                Value::Null,
+                three.clone(),
+                two.clone(),
+                one.clone()
+            ]
+        );
+        assert_eq!(
+            values("SELECT * FROM table1 ORDER BY col1 ASC NULLS LAST", &mut vm),
+            vec![
+                one.clone(),
+                two.clone(),
+                three.clone(),
+                Value::Null,
+                Value::Null
+            ]
+        );
+        assert_eq!(
+            values(
+                "SELECT * FROM table1 ORDER BY col1 DESC NULLS LAST",
+                &mut vm
+            ),
+            vec![three, two, one, Value::Null, Value::Null]
+        );
+    }
+
+    #[test]
+    fn update_to_default() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement(
+            "CREATE TABLE table1 (col1 INTEGER DEFAULT 10 + 1, col2 STRING DEFAULT 'x', col3 INTEGER)",
+            &mut vm,
+        )
+        .unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a', 5), (2, 'b', 6)",
+            &mut vm,
+        )
+        .unwrap();
+
+        check_single_statement("UPDATE table1 SET col1 = DEFAULT WHERE col1 = 2", &mut vm).unwrap();
+        check_single_statement("UPDATE table1 SET col2 = default, col3 = DEFAULT", &mut vm)
+            .unwrap();
+
+        let res = check_single_statement("SELECT * FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("x".to_owned()),
+                    Value::Null
+                ]),
+                Row::new(vec![
+                    Value::Int64(11),
+                    Value::String("x".to_owned()),
+                    Value::Null
+                ]),
+            ]
+        );
+
+        // only meaningful as the whole value of an assignment
+        assert!(matches!(
+            check_single_statement("UPDATE table1 SET col1 = DEFAULT + 1", &mut vm),
+            Err(RuntimeError::ExprExecError(ExprExecError::CannotExecute(_)))
+        ));
+
+        // or of an inserted value
+        check_single_statement("INSERT INTO table1 VALUES (DEFAULT, DEFAULT, 2)", &mut vm).unwrap();
+        let res = check_single_statement("SELECT * FROM table1 WHERE col3 = 2", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![Row::new(vec![
+                Value::Int64(11),
+                Value::String("x".to_owned()),
+                Value::Int64(2)
+            ])]
+        );
+
+        // defaults must fit their columns
+        assert!(matches!(
+            check_single_statement("CREATE TABLE table2 (col1 INT DEFAULT 'x')", &mut vm),
+            Err(RuntimeError::ColumnTypeMismatch { col_name, .. }) if col_name.as_str() == "col1"
+        ));
+        check_single_statement("CREATE TABLE table2 (col1 FLOAT DEFAULT 1)", &mut vm).unwrap();
+    }
+
+    #[test]
+    fn nan_keys() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 FLOAT, col2 INTEGER)", &mut vm).unwrap();
+        // NaNs with different bit patterns, including a negative one.
+        check_single_statement(
+            "INSERT INTO table1 VALUES
+                (CAST('NaN' AS FLOAT), 1),
+                (1.5, 2),
+                (CAST('-nan' AS FLOAT), 3),
+                (0.0 / 0.0, 4),
+                (1.5, 5)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let res = check_single_statement(
+            "SELECT col1, COUNT(*) AS c, MIN(col2) AS m FROM table1 GROUP BY col1",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        let data = res.all_data();
+        assert_eq!(data.len(), 2);
+        assert!(matches!(data[0].clone().data()[0], Value::Float64(f) if f.is_nan()));
+        assert_eq!(
+            data[0].clone().data()[1..],
+            [Value::Int64(3), Value::Int64(1)]
+        );
+        assert_eq!(
+            data[1].clone().data(),
+            vec![Value::Float64(1.5.into()), Value::Int64(2), Value::Int64(2)]
+        );
+
+        // DISTINCT agrees with GROUP BY
+        let res = check_single_statement("SELECT DISTINCT col1 AS c FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        let data = res.all_data();
+        assert_eq!(data.len(), 2);
+        assert!(matches!(data[0].clone().data()[0], Value::Float64(f) if f.is_nan()));
+        assert_eq!(data[1].clone().data(), vec![Value::Float64(1.5.into())]);
+    }
+
+    #[test]
+    fn projected_column_names() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 INTEGER, col2 INTEGER)", &mut vm)
+            .unwrap();
+        check_single_statement("INSERT INTO table1 VALUES (2, 1), (1, 2)", &mut vm).unwrap();
+
+        let res = check_single_statement(
+            "SELECT col1 + 1, col2, col1 * 2, col1 - 1 AS d, col2 - 1 FROM table1 ORDER BY col2",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["?column?", "col2", "?column?2", "d", "?column?3"]
+        );
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(3),
+                    Value::Int64(1),
+                    Value::Int64(4),
+                    Value::Int64(1),
+                    Value::Int64(0)
+                ]),
+                Row::new(vec![
+                    Value::Int64(2),
+                    Value::Int64(2),
+                    Value::Int64(2),
+                    Value::Int64(0),
+                    Value::Int64(1)
+                ]),
+            ]
+        );
+
+        // generated names do not take the place of an alias
+        let res =
+            check_single_statement("SELECT col1 AS \"?column?\", col1 + 1 FROM table1", &mut vm)
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            res.columns().map(Column::name).collect::<Vec<_>>(),
+            vec!["?column?", "?column?2"]
+        );
+    }
+
+    #[test]
+    fn three_valued_logic() {
+        let mut vm = VirtualMachine::default();
+        check_single_statement("CREATE TABLE t (id INT, val INT)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO t VALUES (1, 10), (2, NULL), (3, 30)", &mut vm)
+            .unwrap();
+
+        let res = check_single_statement("SELECT id FROM t WHERE val > 5", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(1)]),
+                Row::new(vec![Value::Int64(3)])
+            ]
+        );
+
+        let res = check_single_statement("SELECT id FROM t WHERE val = NULL", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert!(res.all_data().is_empty());
+
+        let res = check_single_statement("SELECT id FROM t WHERE val = 10 OR id = 2", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(1)]),
+                Row::new(vec![Value::Int64(2)])
+            ]
+        );
+    }
+
+    #[test]
+    fn dates() {
+        let mut vm = VirtualMachine::default();
+        check_single_statement(
+            "CREATE TABLE events (id INT, day DATE, created_at TIMESTAMP)",
+            &mut vm,
+        )
+        .unwrap();
+        check_single_statement(
+            "INSERT INTO events VALUES
+                (1, '2022-12-31', '2022-12-31 23:59:59'),
+                (2, '2023-01-01', '2023-01-01 00:00:00'),
+                (3, '2023-03-15', '2023-03-15 12:30:00')",
+            &mut vm,
+        )
+        .unwrap();
+
+        let ids = |res: Table| -> Vec<Value> {
+            res.all_data()
+                .into_iter()
+                .map(|row| row.data()[0].clone())
+                .collect()
+        };
+
+        let res = check_single_statement(
+            "SELECT id FROM events WHERE created_at > '2023-01-01'",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(ids(res), vec![Value::Int64(3)]);
+
+        let res = check_single_statement(
+            "SELECT id FROM events WHERE day >= DATE '2023-01-01'",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(ids(res), vec![Value::Int64(2), Value::Int64(3)]);
+
+        let res = check_single_statement("SELECT id FROM events WHERE created_at = day", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ids(res), vec![Value::Int64(2)]);
+
+        let res = check_single_statement("SELECT day FROM events WHERE id = 3", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data()[0].clone().data(),
+            vec![Value::Date(
+                chrono::NaiveDate::from_ymd_opt(2023, 3, 15).unwrap()
+            )]
+        );
+
+        let res = check_single_statement(
+            "SELECT EXTRACT(YEAR FROM day), EXTRACT(MONTH FROM day), EXTRACT(DAY FROM day) \
+             FROM events WHERE id = 3",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data()[0].clone().data(),
+            vec![Value::Int64(2023), Value::Int64(3), Value::Int64(15)]
+        );
+
+        assert!(
+            check_single_statement("SELECT id FROM events WHERE day > 'soon'", &mut vm).is_err()
+        );
+
+        // values which are not dates are not stored in a date column
+        for value in ["'not a date'", "5"] {
+            assert!(matches!(
+                check_single_statement(
+                    &format!("INSERT INTO events VALUES (4, {}, NULL)", value),
+                    &mut vm
+                ),
+                Err(RuntimeError::ColumnTypeMismatch { col_name, .. }) if col_name.as_str() == "day"
+            ));
+        }
+        let res = check_single_statement("SELECT id FROM events", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data().len(), 3);
+    }
+
+    #[test]
+    fn snapshot_diff() {
+        let mut vm = VirtualMachine::default();
+        check_single_statement("CREATE TABLE t (id INT, name TEXT)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO t VALUES (1, 'one'), (2, 'two'), (3, 'three')",
+            &mut vm,
+        )
+        .unwrap();
+
+        let table_index = vm
+            .find_table(
+                vm.database.default_schema(),
+                &TableRef {
+                    schema_name: None,
+                    table_name: "t".into(),
+                },
+            )
+            .unwrap();
+        let snapshot = vm.table(&table_index).unwrap().snapshot();
+
+        check_single_statement("UPDATE t SET name = 'TWO' WHERE id >= 2", &mut vm).unwrap();
+        check_single_statement("UPDATE t SET name = 'three' WHERE id = 3", &mut vm).unwrap();
+
+        let diff = vm.table(&table_index).unwrap().diff(&snapshot);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            diff.changed,
+            vec![(
+                Row::new(vec![Value::Int64(2), Value::String("two".to_owned())]),
+                Row::new(vec![Value::Int64(2), Value::String("TWO".to_owned())])
+            )]
+        );
+    }
+
+    #[test]
+    fn max_columns() {
+        let mut vm = VirtualMachine::default();
+        vm.set_max_columns(3);
+
+        vm.execute("CREATE TABLE t1 (a INT, b INT, c INT)").unwrap();
+        assert!(matches!(
+            vm.execute("CREATE TABLE t2 (a INT, b INT, c INT, d INT)"),
+            Err(ExecutionError::RuntimeError(RuntimeError::TooManyColumns {
+                col_name,
+                max_columns: 3
+            })) if col_name.as_str() == "d"
+        ));
+        assert!(vm.execute("SELECT * FROM t2").is_err());
+    }
+
+    #[test]
+    fn autocommit() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (id INT); CREATE TABLE u (id INT)")
+            .unwrap();
+        vm.set_autocommit(false);
+
+        vm.execute("INSERT INTO t VALUES (1)").unwrap();
+        let committed = vm.execute_committed("SELECT * FROM t").unwrap().unwrap();
+        assert!(committed.all_data().is_empty());
+        // the VM sees its own changes
+        let res = vm.execute("SELECT * FROM t").unwrap().unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(1)])]);
+
+        vm.execute("COMMIT").unwrap();
+        let committed = vm.execute_committed("SELECT * FROM t").unwrap().unwrap();
+        assert_eq!(committed.all_data(), vec![Row::new(vec![Value::Int64(1)])]);
+
+        // a new transaction is started after the commit
+        vm.execute("INSERT INTO t VALUES (2)").unwrap();
+        let committed = vm.execute_committed("SELECT * FROM t").unwrap().unwrap();
+        assert_eq!(committed.all_data().len(), 1);
+
+        // changes made through `execute_committed` are discarded, and the transaction in progress
+        // is kept as it was
+        vm.execute_committed("INSERT INTO t VALUES (3)").unwrap();
+        vm.execute("DROP TABLE u").unwrap();
+        let committed = vm.execute_committed("SELECT * FROM t").unwrap().unwrap();
+        assert_eq!(committed.all_data().len(), 1);
+        vm.execute_committed("SELECT * FROM u").unwrap();
+        let res = vm.execute("SELECT * FROM t").unwrap().unwrap();
+        assert_eq!(res.all_data().len(), 2);
+        assert!(vm.execute("SELECT * FROM u").is_err());
+        assert!(matches!(
+            vm.execute_committed("COMMIT"),
+            Err(ExecutionError::CodegenError(
+                CodegenError::UnsupportedStatement(..)
+            ))
+        ));
+
+        vm.set_autocommit(true);
+        let committed = vm.execute_committed("SELECT * FROM t").unwrap().unwrap();
+        assert_eq!(committed.all_data().len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "persistence")]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("otter-sql-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("save_and_load.json");
+
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE SCHEMA s").unwrap();
+        vm.execute("CREATE TABLE s.t (id INT, name TEXT, score DOUBLE)")
+            .unwrap();
+        vm.execute("INSERT INTO s.t VALUES (1, 'one', 1.5), (2, NULL, 2.5)")
+            .unwrap();
+        vm.execute("CREATE TABLE u (d DATE)").unwrap();
+        vm.execute("INSERT INTO u VALUES ('2023-01-31')").unwrap();
+        vm.save_to_path(&path).unwrap();
+
+        let mut loaded = VirtualMachine::load_from_path(&path).unwrap();
+        let expected = vm.execute("SELECT * FROM s.t").unwrap().unwrap();
+        let res = loaded.execute("SELECT * FROM s.t").unwrap().unwrap();
+        assert!(res.rows_equal_ordered(&expected));
+        let expected = vm.execute("SELECT * FROM u").unwrap().unwrap();
+        let res = loaded.execute("SELECT * FROM u").unwrap().unwrap();
+        assert!(res.rows_equal_ordered(&expected));
+
+        // the loaded tables can be changed like any other
+        loaded
+            .execute("INSERT INTO s.t VALUES (3, 'three', 3.5)")
+            .unwrap();
+        loaded.execute("CREATE TABLE v (id INT)").unwrap();
+        let res = loaded.execute("SELECT * FROM s.t").unwrap().unwrap();
+        assert_eq!(res.all_data().len(), 3);
+
+        assert!(matches!(
+            VirtualMachine::load_from_path(dir.join("missing.json")),
+            Err(super::PersistenceError::Io(_))
+        ));
+
+        std::fs::write(&path, "{ not a database").unwrap();
+        assert!(matches!(
+            VirtualMachine::load_from_path(&path),
+            Err(super::PersistenceError::Corrupt(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn transactions() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (id INT, name TEXT)").unwrap();
+        vm.execute("INSERT INTO t VALUES (1, 'one')").unwrap();
+        let count = |vm: &mut VirtualMachine| {
+            vm.execute("SELECT * FROM t")
+                .unwrap()
+                .unwrap()
+                .all_data()
+                .len()
+        };
+
+        vm.execute("BEGIN").unwrap();
+        vm.execute("INSERT INTO t VALUES (2, 'two')").unwrap();
+        assert_eq!(count(&mut vm), 2);
+        vm.execute("ROLLBACK").unwrap();
+        assert_eq!(count(&mut vm), 1);
+
+        // a failed statement does not undo the earlier ones
+        vm.execute("START TRANSACTION").unwrap();
+        vm.execute("INSERT INTO t VALUES (2, 'two')").unwrap();
+        assert!(vm.execute("INSERT INTO t VALUES (3)").is_err());
+        vm.execute("CREATE TABLE u (id INT)").unwrap();
+        assert_eq!(count(&mut vm), 2);
+        vm.execute("ROLLBACK").unwrap();
+        assert_eq!(count(&mut vm), 1);
+        assert!(vm.execute("SELECT * FROM u").is_err());
+
+        vm.execute("BEGIN; UPDATE t SET name = 'uno'; COMMIT")
+            .unwrap();
+        vm.execute("ROLLBACK").unwrap();
+        let res = vm.execute("SELECT name FROM t").unwrap().unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![Row::new(vec![Value::String("uno".to_owned())])]
+        );
+
+        // only the tables which a transaction changes are copied, and dropped tables come back
+        vm.execute("CREATE TABLE other (id INT)").unwrap();
+        vm.execute("BEGIN; INSERT INTO t VALUES (2, 'two')")
+            .unwrap();
+        assert_eq!(vm.transaction.as_ref().unwrap().tables.len(), 1);
+        vm.execute("DROP TABLE other").unwrap();
+        assert_eq!(vm.transaction.as_ref().unwrap().tables.len(), 2);
+        vm.execute("ROLLBACK").unwrap();
+        assert_eq!(count(&mut vm), 1);
+        vm.execute("SELECT * FROM other").unwrap();
+
+        assert!(matches!(
+            vm.execute("BEGIN ISOLATION LEVEL SERIALIZABLE"),
+            Err(ExecutionError::CodegenError(
+                CodegenError::UnsupportedStatementForm(..)
+            ))
+        ));
+    }
+
+    #[test]
+    fn bool_int_coercion() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (id INT, active BOOLEAN)")
+            .unwrap();
+        vm.execute("INSERT INTO t VALUES (1, true), (2, false), (3, NULL)")
+            .unwrap();
+
+        assert!(matches!(
+            vm.execute("SELECT id FROM t WHERE active = 1"),
+            Err(ExecutionError::RuntimeError(RuntimeError::ExprExecError(
+                ExprExecError::ValueBinaryOpError(_)
+            )))
+        ));
+
+        vm.set_bool_int_coercion(true);
+        let res = vm
+            .execute("SELECT id FROM t WHERE active = 1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(1)])]);
+        let res = vm
+            .execute("SELECT id FROM t WHERE 0 >= active")
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(2)])]);
+    }
+
+    #[test]
+    fn primary_key() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (id INT PRIMARY KEY, name TEXT)")
+            .unwrap();
+        vm.execute("INSERT INTO t VALUES (1, 'one')").unwrap();
+
+        assert_eq!(
+            vm.execute("INSERT INTO t VALUES (1, 'uno')")
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Err("Duplicate primary key (1) in table 't'".to_owned())
+        );
+        assert!(matches!(
+            vm.execute("INSERT INTO t VALUES (1, 'uno')"),
+            Err(ExecutionError::RuntimeError(RuntimeError::DuplicateKey { key, .. }))
+                if key == Value::Int64(1)
+        ));
+        // duplicates within the same statement are caught too, and nothing is inserted
+        assert!(matches!(
+            vm.execute("INSERT INTO t VALUES (2, 'two'), (2, 'dos')"),
+            Err(ExecutionError::RuntimeError(
+                RuntimeError::DuplicateKey { .. }
+            ))
+        ));
+        assert!(matches!(
+            vm.execute("INSERT INTO t VALUES (NULL, 'none')"),
+            Err(ExecutionError::RuntimeError(RuntimeError::NullPrimaryKey(
+                ColumnRef { col_name, .. }
+            ))) if col_name.as_str() == "id"
+        ));
+
+        vm.execute("INSERT INTO t VALUES (2, 'two')").unwrap();
+        let res = vm.execute("SELECT id FROM t").unwrap().unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(1)]),
+                Row::new(vec![Value::Int64(2)])
+            ]
+        );
+
+        // updates are checked against the rest of the table and each other
+        assert!(matches!(
+            vm.execute("UPDATE t SET id = 1 WHERE id = 2"),
+            Err(ExecutionError::RuntimeError(RuntimeError::DuplicateKey { key, .. }))
+                if key == Value::Int64(1)
+        ));
+        assert!(matches!(
+            vm.execute("UPDATE t SET id = 5"),
+            Err(ExecutionError::RuntimeError(
+                RuntimeError::DuplicateKey { .. }
+            ))
+        ));
+        assert!(matches!(
+            vm.execute("UPDATE t SET id = NULL WHERE id = 1"),
+            Err(ExecutionError::RuntimeError(RuntimeError::NullPrimaryKey(
+                _
+            )))
+        ));
+        // the old keys of the updated rows are free to be reused
+        vm.execute("UPDATE t SET id = id + 1").unwrap();
+        vm.execute("UPDATE t SET id = 1, name = 'uno' WHERE id = 2")
+            .unwrap();
+        let res = vm
+            .execute("SELECT id FROM t WHERE id = 1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Int64(1)])]);
+        let res = vm.execute("SELECT id FROM t").unwrap().unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(1)]),
+                Row::new(vec![Value::Int64(3)])
+            ]
+        );
+
+        // a single column key can be given as a table constraint as well
+        vm.execute("CREATE TABLE k (a INT, b INT, PRIMARY KEY (b))")
+            .unwrap();
+        vm.execute("INSERT INTO k VALUES (1, 1), (1, 2)").unwrap();
+        assert!(matches!(
+            vm.execute("INSERT INTO k VALUES (2, 1)"),
+            Err(ExecutionError::RuntimeError(RuntimeError::DuplicateKey { key, .. }))
+                if key == Value::Int64(1)
+        ));
+        assert!(matches!(
+            vm.execute("CREATE TABLE c (a INT, PRIMARY KEY (b))"),
+            Err(ExecutionError::CodegenError(
+                CodegenError::UnsupportedStatementForm(..)
+            ))
+        ));
+        assert!(matches!(
+            vm.execute("CREATE TABLE c (a INT PRIMARY KEY, b INT, PRIMARY KEY (b))"),
+            Err(ExecutionError::CodegenError(
+                CodegenError::MultiplePrimaryKeys(..)
+            ))
+        ));
+        assert!(matches!(
+            vm.execute("CREATE TABLE c (a INT, b INT, PRIMARY KEY (a, b))"),
+            Err(ExecutionError::CodegenError(
+                CodegenError::UnsupportedStatementForm(..)
+            ))
+        ));
+        assert_eq!(
+            vm.execute("CREATE TABLE c (a INT PRIMARY KEY, b INT PRIMARY KEY)")
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Err("Multiple primary keys for table 'c' are not allowed".to_owned())
+        );
+    }
+
+    #[test]
+    fn mixed_integer_widths() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (small TINYINT, medium SMALLINT, big BIGINT)")
+            .unwrap();
+        vm.execute("INSERT INTO t VALUES (1, 300, 1), (2, 2, 3000000000), (3, 3, 3)")
+            .unwrap();
+
+        let res = vm
+            .execute("SELECT small FROM t WHERE small = medium OR small = big")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(1)]),
+                Row::new(vec![Value::Int64(2)]),
+                Row::new(vec![Value::Int64(3)])
+            ]
+        );
+
+        let res = vm
+            .execute("SELECT small AS n FROM t UNION SELECT big FROM t ORDER BY n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(1)]),
+                Row::new(vec![Value::Int64(2)]),
+                Row::new(vec![Value::Int64(3)]),
+                Row::new(vec![Value::Int64(3000000000)])
+            ]
+        );
+    }
+
+    #[test]
+    fn distinct_aggregates() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (grp INTEGER, name TEXT)")
+            .unwrap();
+        vm.execute("INSERT INTO t VALUES (1, 'a'), (1, 'b'), (1, 'a'), (2, 'c'), (2, NULL)")
+            .unwrap();
+
+        let res = vm
+            .execute(
+                "SELECT grp, STRING_AGG(name, ',') AS a, STRING_AGG(DISTINCT name, ',') AS b, \
+                COUNT(DISTINCT name) AS c FROM t GROUP BY grp ORDER BY grp",
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::String("a,b,a".to_owned()),
+                    Value::String("a,b".to_owned()),
+                    Value::Int64(2)
+                ]),
+                Row::new(vec![
+                    Value::Int64(2),
+                    Value::String("c".to_owned()),
+                    Value::String("c".to_owned()),
+                    Value::Int64(1)
+                ]),
+            ]
+        );
+
+        assert!(vm.execute("SELECT STRING_AGG(name) AS a FROM t").is_err());
+        assert!(vm
+            .execute("SELECT STRING_AGG(grp, ',') AS a FROM t")
+            .is_err());
+        assert!(vm
+            .execute("SELECT UPPER(DISTINCT name) AS a FROM t")
+            .is_err());
+    }
+
+    #[test]
+    fn not_null() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (id INTEGER, name TEXT NOT NULL)")
+            .unwrap();
+
+        let is_not_null_error = |res: Result<Option<Table>, ExecutionError>| {
+            matches!(
+                res,
+                Err(ExecutionError::RuntimeError(RuntimeError::NullInNotNullColumn(
+                    ColumnRef { col_name, .. }
+                ))) if col_name.as_str() == "name"
+            )
+        };
+
+        // the values of an explicit column list are matched to the table's columns by name.
+        vm.execute("INSERT INTO t (name, id) VALUES ('a', 1)")
+            .unwrap();
+        assert!(is_not_null_error(
+            vm.execute("INSERT INTO t (id) VALUES (2)")
+        ));
+        assert!(is_not_null_error(
+            vm.execute("INSERT INTO t (name, id) VALUES (NULL, 2)")
+        ));
+        assert!(is_not_null_error(
+            vm.execute("INSERT INTO t VALUES (2, 'b'), (3, NULL)")
+        ));
+        assert!(is_not_null_error(vm.execute("UPDATE t SET name = NULL")));
+        // the other assignments of a rejected update are not written either
+        assert!(is_not_null_error(
+            vm.execute("UPDATE t SET id = 100, name = NULL")
+        ));
+
+        let res = vm.execute("SELECT id, name FROM t").unwrap().unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![Row::new(vec![
+                Value::Int64(1),
+                Value::String("a".to_owned())
+            ])]
+        );
+    }
+
+    #[test]
+    fn array_agg() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (grp INTEGER, n INTEGER)")
+            .unwrap();
+        vm.execute("INSERT INTO t VALUES (1, 3), (2, NULL), (1, 1), (2, 2), (1, 3), (3, NULL)")
+            .unwrap();
+
+        let res = vm
+            .execute(
+                "SELECT grp, ARRAY_AGG(n) AS a, ARRAY_AGG(DISTINCT n) AS b FROM t \
+                GROUP BY grp ORDER BY grp",
+            )
+            .unwrap()
+            .unwrap();
+        let array = |values: &[Option<i64>]| {
+            Value::Array(
+                values
+                    .iter()
+                    .map(|v| v.map(Value::Int64).unwrap_or(Value::Null))
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    array(&[Some(3), Some(1), Some(3)]),
+                    array(&[Some(3), Some(1)])
+                ]),
+                Row::new(vec![
+                    Value::Int64(2),
+                    array(&[None, Some(2)]),
+                    array(&[None, Some(2)])
+                ]),
+                Row::new(vec![Value::Int64(3), array(&[None]), array(&[None])]),
+            ]
+        );
+
+        let res = vm
+            .execute("SELECT ARRAY_AGG(n) AS a FROM t WHERE grp = 4")
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![Value::Null])]);
+    }
+
+    #[test]
+    fn insert_defaults() {
+        let mut vm = VirtualMachine::default();
+        vm.execute(
+            "CREATE TABLE t (id INTEGER, created_at TIMESTAMP DEFAULT '1970-01-01', \
+            name TEXT DEFAULT 'none' NOT NULL, note TEXT)",
+        )
+        .unwrap();
+
+        vm.execute("INSERT INTO t (id) VALUES (1)").unwrap();
+        vm.execute("INSERT INTO t (note, id, name) VALUES ('x', 2, 'b'), ('y', 3, 'c')")
+            .unwrap();
+        vm.execute("INSERT INTO t VALUES (4, '2023-01-31 10:00:00', 'd', NULL)")
+            .unwrap();
+        assert!(matches!(
+            vm.execute("INSERT INTO t (id, name) VALUES (5)"),
+            Err(ExecutionError::RuntimeError(
+                RuntimeError::NotEnoughValuesToInsert(_, 1, 2)
+            ))
+        ));
+
+        let epoch = chrono::NaiveDate::from_ymd_opt(1970, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let res = vm.execute("SELECT * FROM t").unwrap().unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(1),
+                    Value::Timestamp(epoch),
+                    Value::String("none".to_owned()),
+                    Value::Null
+                ]),
+                Row::new(vec![
+                    Value::Int64(2),
+                    Value::Timestamp(epoch),
+                    Value::String("b".to_owned()),
+                    Value::String("x".to_owned())
+                ]),
+                Row::new(vec![
+                    Value::Int64(3),
+                    Value::Timestamp(epoch),
+                    Value::String("c".to_owned()),
+                    Value::String("y".to_owned())
+                ]),
+                Row::new(vec![
+                    Value::Int64(4),
+                    Value::Timestamp(
+                        chrono::NaiveDate::from_ymd_opt(2023, 1, 31)
+                            .unwrap()
+                            .and_hms_opt(10, 0, 0)
+                            .unwrap()
+                    ),
+                    Value::String("d".to_owned()),
+                    Value::Null
+                ]),
+            ]
+        );
+
+        // an explicit `DEFAULT`, with or without a column list
+        vm.execute("INSERT INTO t VALUES (5, DEFAULT, DEFAULT, DEFAULT)")
+            .unwrap();
+        vm.execute("INSERT INTO t (name, id, note) VALUES (DEFAULT, 6, 'z')")
+            .unwrap();
+        let res = vm.execute("SELECT * FROM t WHERE id > 4").unwrap().unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![
+                    Value::Int64(5),
+                    Value::Timestamp(epoch),
+                    Value::String("none".to_owned()),
+                    Value::Null
+                ]),
+                Row::new(vec![
+                    Value::Int64(6),
+                    Value::Timestamp(epoch),
+                    Value::String("none".to_owned()),
+                    Value::String("z".to_owned())
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn explain() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (a INTEGER, b TEXT)").unwrap();
+
+        let res = vm
+            .execute("EXPLAIN SELECT b AS x FROM t WHERE a > 1 ORDER BY x DESC")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.columns().map(|c| c.name().as_str()).collect::<Vec<_>>(),
+            vec!["instruction"]
+        );
+        assert_eq!(
+            res.all_data(),
+            [
+                "Source { index: %0, name: t }",
+                "Filter { index: %0, expr: (column 'a' > 1) }",
+                "Empty { index: %1 }",
+                "Project { input: %0, output: %1, expr: column 'b', alias: x }",
+                "Order { index: %1, keys: [column 'x' DESC NULLS FIRST] }",
+                "Return { index: %1 }",
+            ]
+            .into_iter()
+            .map(|instr| Row::new(vec![Value::String(instr.to_owned())]))
+            .collect::<Vec<_>>()
+        );
+
+        // the explained statement is not run.
+        vm.execute("EXPLAIN INSERT INTO t VALUES (1, 'a')").unwrap();
+        vm.execute("EXPLAIN SELECT * FROM missing").unwrap();
+        assert!(vm
+            .execute("SELECT * FROM t")
+            .unwrap()
+            .unwrap()
+            .all_data()
+            .is_empty());
+
+        assert!(vm.execute("EXPLAIN ANALYZE SELECT * FROM t").is_err());
+    }
+
+    #[test]
+    fn empty_groups() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE empty (dept INTEGER, salary INTEGER)")
+            .unwrap();
+
+        let data = |vm: &mut VirtualMachine, sql| vm.execute(sql).unwrap().unwrap().all_data();
+
+        // without a GROUP BY, all the rows make one group, even if there are none.
+        assert_eq!(
+            data(&mut vm, "SELECT COUNT(*) AS c FROM empty"),
+            vec![Row::new(vec![Value::Int64(0)])]
+        );
+        assert_eq!(
+            data(
+                &mut vm,
+                "SELECT COUNT(salary) AS c, SUM(salary) AS s, MAX(salary) AS m FROM empty"
+            ),
+            vec![Row::new(vec![Value::Int64(0), Value::Null, Value::Null])]
+        );
+        // with one, there are no groups at all.
+        assert_eq!(
+            data(
+                &mut vm,
+                "SELECT dept, COUNT(*) AS c FROM empty GROUP BY dept"
+            ),
+            vec![]
+        );
+
+        // HAVING without a GROUP BY keeps or drops the one summary row.
+        assert_eq!(
+            data(
+                &mut vm,
+                "SELECT COUNT(*) AS c FROM empty HAVING COUNT(*) = 0"
+            ),
+            vec![Row::new(vec![Value::Int64(0)])]
+        );
+        assert_eq!(
+            data(
+                &mut vm,
+                "SELECT COUNT(*) AS c FROM empty HAVING COUNT(*) > 0"
+            ),
+            vec![]
+        );
+
+        vm.execute("INSERT INTO empty VALUES (1, 10), (1, 20), (2, 30)")
+            .unwrap();
+        assert_eq!(
+            data(
+                &mut vm,
+                "SELECT SUM(salary) AS s FROM empty HAVING COUNT(*) > 2"
+            ),
+            vec![Row::new(vec![Value::Int64(60)])]
+        );
+        assert_eq!(
+            data(
+                &mut vm,
+                "SELECT SUM(salary) AS s FROM empty HAVING COUNT(*) > 3"
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn indexes() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, grp INTEGER, score FLOAT, name TEXT)")
+            .unwrap();
+        vm.execute(
+            "INSERT INTO t VALUES (1, 10, 1.5, 'a'), (2, 20, 2, 'b'), (3, 10, 3, 'c'), \
+            (4, NULL, 4, 'd')",
+        )
+        .unwrap();
+
+        let table_index = vm
+            .resolve_table(&TableRef {
+                schema_name: None,
+                table_name: "t".into(),
+            })
+            .unwrap();
+        let indexed = |vm: &VirtualMachine| {
+            vm.tables[&table_index]
+                .indexed_columns()
+                .map(|c| c.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        // the primary key is indexed on its own.
+        assert_eq!(indexed(&vm), vec!["id"]);
+
+        vm.execute("CREATE INDEX t_grp ON t (grp); CREATE INDEX t_score ON t (score)")
+            .unwrap();
+        vm.execute("CREATE INDEX t_grp2 ON t (grp)").unwrap();
+        assert_eq!(indexed(&vm), vec!["id", "grp", "score"]);
+
+        let names = |vm: &mut VirtualMachine, cond: &str| {
+            vm.execute(&format!("SELECT name FROM t WHERE {}", cond))
+                .unwrap()
+                .unwrap()
+                .all_data()
+                .into_iter()
+                .map(|row| row.data()[0].to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&mut vm, "id = 3"), vec!["c"]);
+        assert_eq!(names(&mut vm, "10 = grp"), vec!["a", "c"]);
+        assert_eq!(names(&mut vm, "grp = 30"), Vec::<String>::new());
+        assert_eq!(names(&mut vm, "grp = NULL"), Vec::<String>::new());
+        assert_eq!(names(&mut vm, "score = 2"), vec!["b"]);
+        assert_eq!(names(&mut vm, "score = 1.5"), vec!["a"]);
+
+        // the indexes follow changes to the rows.
+        vm.execute("INSERT INTO t VALUES (5, 20, 5, 'e')").unwrap();
+        vm.execute("UPDATE t SET grp = 30 WHERE id = 2").unwrap();
+        assert_eq!(names(&mut vm, "grp = 20"), vec!["e"]);
+        assert_eq!(names(&mut vm, "grp = 30"), vec!["b"]);
+        vm.execute("ALTER TABLE t DROP COLUMN grp").unwrap();
+        assert_eq!(indexed(&vm), vec!["id", "score"]);
+        assert_eq!(names(&mut vm, "score = 5"), vec!["e"]);
+
+        assert!(vm.execute("CREATE UNIQUE INDEX u ON t (id)").is_err());
+        assert!(vm.execute("CREATE INDEX u ON t (id, score)").is_err());
+        assert!(vm.execute("CREATE INDEX u ON t (missing)").is_err());
+    }
+
+    /// Times equality lookups on a large table without and with an index. Run with
+    /// `cargo test --release -- --ignored --nocapture index_lookup_timing`.
+    #[test]
+    #[ignore]
+    fn index_lookup_timing() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (id INT, name TEXT)").unwrap();
+        for chunk in 0..100 {
+            let values: Vec<_> = (0..1000)
+                .map(|i| format!("({}, 'name{}')", chunk * 1000 + i, i))
+                .collect();
+            vm.execute(&format!("INSERT INTO t VALUES {}", values.join(", ")))
+                .unwrap();
+        }
+
+        let time_lookups = |vm: &mut VirtualMachine| {
+            let start = std::time::Instant::now();
+            for n in 0..100 {
+                let res = vm
+                    .execute(&format!("SELECT name FROM t WHERE id = {}", n * 997))
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(res.num_rows(), 1);
+            }
+            start.elapsed() / 100
+        };
+        let scan = time_lookups(&mut vm);
+        vm.execute("CREATE INDEX t_id ON t (id)").unwrap();
+        let indexed = time_lookups(&mut vm);
+        println!("100k rows, average of 100 lookups by id");
+        println!("  before CREATE INDEX  {:?}", scan);
+        println!("  after  CREATE INDEX  {:?}", indexed);
+    }
+
+    #[test]
+    fn presized_temp_tables() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (a INT)").unwrap();
+        let values: Vec<_> = (0..1025).map(|i| format!("({})", i)).collect();
+        vm.execute(&format!("INSERT INTO t VALUES {}", values.join(", ")))
+            .unwrap();
+
+        // growing row by row would double the capacity past the number of rows. The allocator
+        // may still give a bit more than was asked for.
+        let res = vm.execute("SELECT a, a + 1 FROM t").unwrap().unwrap();
+        assert_eq!(res.raw_data.len(), 1025);
+        assert!((1025..2048).contains(&res.raw_data.capacity()));
+        let rows = res.all_data();
+        assert_eq!(rows[0].data_shared(), [&Value::Int64(0), &Value::Int64(1)]);
+        assert_eq!(
+            rows[1024].data_shared(),
+            [&Value::Int64(1024), &Value::Int64(1025)]
+        );
+
+        vm.execute("CREATE TABLE s (b INT)").unwrap();
+        let values: Vec<_> = (0..30).map(|i| format!("({})", i)).collect();
+        vm.execute(&format!("INSERT INTO s VALUES {}", values.join(", ")))
+            .unwrap();
+
+        let res = vm
+            .execute("SELECT * FROM s AS x CROSS JOIN s AS y")
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.raw_data.len(), 900);
+        assert!((900..1024).contains(&res.raw_data.capacity()));
+    }
+
+    #[test]
+    fn null_safe_join() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE a (id INT, key INT)").unwrap();
+        vm.execute("CREATE TABLE b (id INT, key INT)").unwrap();
+        vm.execute("INSERT INTO a VALUES (1, 10), (2, NULL)")
+            .unwrap();
+        vm.execute("INSERT INTO b VALUES (3, 10), (4, NULL)")
+            .unwrap();
+
+        let mut ids = |query| {
+            vm.execute(query)
+                .unwrap()
+                .unwrap()
+                .all_data()
+                .into_iter()
+                .map(|row| row.data())
+                .collect::<Vec<_>>()
+        };
+        // `=` never matches NULL keys, not even with each other.
+        assert_eq!(
+            ids("SELECT a.id, b.id FROM a JOIN b ON a.key = b.key"),
+            vec![vec![Value::Int64(1), Value::Int64(3)]]
+        );
+        assert_eq!(
+            ids("SELECT a.id, b.id FROM a JOIN b ON a.key IS NOT DISTINCT FROM b.key"),
+            vec![
+                vec![Value::Int64(1), Value::Int64(3)],
+                vec![Value::Int64(2), Value::Int64(4)],
+            ]
+        );
+    }
+
+    #[test]
+    fn columnar_storage() {
+        let mut vm = VirtualMachine::default();
+        for (name, with) in [("r", ""), ("c", " WITH (storage = 'columnar')")] {
+            vm.execute(&format!(
+                "CREATE TABLE {} (id INT PRIMARY KEY, grp TEXT, val INT){}",
+                name, with
+            ))
+            .unwrap();
+            vm.execute(&format!(
+                "INSERT INTO {} VALUES (1, 'a', 10), (2, 'b', NULL), (3, 'a', 30), (4, 'b', 40)",
+                name
+            ))
+            .unwrap();
+            vm.execute(&format!("UPDATE {} SET val = val + 1 WHERE id = 4", name))
+                .unwrap();
+        }
+
+        vm.execute("CREATE TABLE x (val INT, grp TEXT)").unwrap();
+        vm.execute("INSERT INTO x VALUES (10, 'p'), (41, 'q')")
+            .unwrap();
+
+        let layout = |vm: &VirtualMachine, name: &str| {
+            let index = vm.resolve_table(&TableRef {
+                schema_name: None,
+                table_name: name.into(),
+            });
+            vm.tables[&index.unwrap()].layout()
+        };
+        assert_eq!(layout(&vm, "r"), StorageLayout::Rows);
+        assert_eq!(layout(&vm, "c"), StorageLayout::Columnar);
+
+        // every query gives the same result with either layout.
+        for query in [
+            "SELECT * FROM {}",
+            "SELECT val, id FROM {} WHERE id = 4",
+            "SELECT id, val FROM {} WHERE val > 5 ORDER BY val DESC LIMIT 2 OFFSET 1",
+            "SELECT DISTINCT grp FROM {}",
+            "SELECT COUNT(*), SUM(val), MAX(val), COUNT(DISTINCT grp) FROM {}",
+            "SELECT grp, SUM(val) FROM {} GROUP BY grp",
+            "SELECT {0}.id, x.grp FROM {0} JOIN x ON {0}.val = x.val",
+        ] {
+            let run = |vm: &mut VirtualMachine, name: &str| {
+                vm.execute(&query.replace("{0}", name).replace("{}", name))
+                    .unwrap()
+                    .unwrap()
+                    .all_data()
+            };
+            let rows = run(&mut vm, "r");
+            assert!(!rows.is_empty(), "{}", query);
+            assert_eq!(run(&mut vm, "c"), rows, "{}", query);
+        }
+    }
+
+    /// Times a few queries on a large table in each storage layout. Run with
+    /// `cargo test --release -- --ignored --nocapture storage_layout_timing`.
+    #[test]
+    #[ignore]
+    fn storage_layout_timing() {
+        let queries = [
+            "SELECT a FROM t",
+            "SELECT SUM(a) FROM t",
+            "SELECT COUNT(*) FROM t",
+            "SELECT a + 1 FROM t",
+        ];
+        println!("200k rows with 5 columns, average of 5 runs");
+        for layout in ["rows", "columnar"] {
+            let mut vm = VirtualMachine::default();
+            vm.execute(&format!(
+                "CREATE TABLE t (a INT, b INT, c INT, d INT, e TEXT) WITH (storage = '{}')",
+                layout
+            ))
+            .unwrap();
+            for chunk in 0..200 {
+                let values: Vec<_> = (0..1000)
+                    .map(|i| format!("({}, {}, {}, {}, 'x{}')", chunk * 1000 + i, i, i, i, i))
+                    .collect();
+                vm.execute(&format!("INSERT INTO t VALUES {}", values.join(", ")))
+                    .unwrap();
+            }
+
+            for query in queries {
+                let start = std::time::Instant::now();
+                for _ in 0..5 {
+                    vm.execute(query).unwrap();
+                }
+                println!("  {:<10} {:<24} {:?}", layout, query, start.elapsed() / 5);
+            }
+        }
+    }
+
+    #[test]
+    fn in_list() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (id INTEGER, status STRING)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (1, 'a'), (2, 'b'), (3, 'c'), (4, NULL)",
+            &mut vm,
+        )
+        .unwrap();
+
+        let ids = |query: &str, vm: &mut VirtualMachine| {
+            check_single_statement(query, vm)
+                .unwrap()
+                .unwrap()
+                .all_data()
+                .into_iter()
+                .map(|row| row.data()[0].clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ids("SELECT * FROM table1 WHERE status IN ('a', 'b')", &mut vm),
+            vec![Value::Int64(1), Value::Int64(2)]
+        );
+        // the NULL status is unknown either way, so it is never kept
+        assert_eq!(
+            ids(
+                "SELECT * FROM table1 WHERE status NOT IN ('a', 'b')",
+                &mut vm
+            ),
+            vec![Value::Int64(3)]
+        );
+        assert_eq!(
+            ids(
+                "SELECT * FROM table1 WHERE status NOT IN ('a', NULL)",
+                &mut vm
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn string_functions() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (name STRING)", &mut vm).unwrap();
+        check_single_statement("INSERT INTO table1 VALUES ('Alice'), (NULL)", &mut vm).unwrap();
+
+        let res = check_single_statement(
+            "SELECT UPPER(name) AS upper, LENGTH(name) AS len FROM table1",
+            &mut vm,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::String("ALICE".to_owned()), Value::Int64(5)]),
+                Row::new(vec![Value::Null, Value::Null]),
+            ]
+        );
+    }
+
+    #[test]
+    fn execute_batch() {
+        let mut vm = VirtualMachine::default();
+
+        vm.execute("CREATE TABLE table1 (col1 INTEGER, col2 STRING)")
+            .unwrap();
+
+        let params: Vec<Vec<Value>> = (0..100)
+            .map(|i| vec![Value::Int64(i), Value::String(format!("row {}", i))])
+            .collect();
+        vm.execute_batch("INSERT INTO table1 VALUES (?, ?)", &params)
+            .unwrap();
+
+        let res = vm.execute("SELECT * FROM table1").unwrap().unwrap();
+        assert_eq!(
+            res.all_data(),
+            params.into_iter().map(Row::new).collect::<Vec<_>>()
+        );
+
+        // numbered placeholders can be reused and reordered
+        vm.execute_batch(
+            "UPDATE table1 SET col2 = $2 WHERE col1 = $1 OR col1 = $1 + 1",
+            &[vec![Value::Int64(0), Value::String("first".to_owned())]],
+        )
+        .unwrap();
+        let res = vm
+            .execute("SELECT * FROM table1 WHERE col1 < 3")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Int64(0), Value::String("first".to_owned())]),
+                Row::new(vec![Value::Int64(1), Value::String("first".to_owned())]),
+                Row::new(vec![Value::Int64(2), Value::String("row 2".to_owned())]),
+            ]
+        );
+
+        // nothing is inserted if any row has the wrong number of values
+        assert!(matches!(
+            vm.execute_batch(
+                "INSERT INTO table1 VALUES (?, ?)",
+                &[
+                    vec![Value::Int64(100), Value::String("a".to_owned())],
+                    vec![Value::Int64(101)]
+                ],
+            ),
+            Err(ExecutionError::RuntimeError(
+                RuntimeError::ParameterCountMismatch {
+                    expected: 2,
+                    got: 1
+                }
+            ))
+        ));
+        assert_eq!(
+            vm.execute("SELECT * FROM table1")
+                .unwrap()
+                .unwrap()
+                .all_data()
+                .len(),
+            100
+        );
+
+        // placeholders are not allowed outside of batches
+        assert!(matches!(
+            vm.execute("INSERT INTO table1 VALUES (?, 'a')"),
+            Err(ExecutionError::RuntimeError(RuntimeError::ExprExecError(
+                ExprExecError::UnboundPlaceholder(1)
+            )))
+        ));
+    }
+
+    #[test]
+    fn execute_prepared() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (id INTEGER, name TEXT)")
+            .unwrap();
+
+        let name = Value::String("x'); DROP TABLE t; --".to_owned());
+        vm.execute_prepared(
+            "INSERT INTO t VALUES (?, ?)",
+            &[Value::Int64(1), name.clone()],
+        )
+        .unwrap();
+        vm.execute_prepared(
+            "INSERT INTO t VALUES ($1, $2)",
+            &[Value::Int64(2), Value::String("b".to_owned())],
+        )
+        .unwrap();
+
+        let res = vm
+            .execute_prepared("SELECT name FROM t WHERE id = ?", &[Value::Int64(1)])
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.all_data(), vec![Row::new(vec![name])]);
+
+        assert!(matches!(
+            vm.execute_prepared("SELECT name FROM t WHERE id = ?", &[]),
+            Err(ExecutionError::RuntimeError(
+                RuntimeError::ParameterCountMismatch {
+                    expected: 1,
+                    got: 0
+                }
+            ))
+        ));
+        // `?` can not be told apart from `$1` when they are used together
+        assert!(matches!(
+            vm.execute_prepared("SELECT $1, ?", &[Value::Int64(1), Value::Int64(2)]),
+            Err(ExecutionError::ParseError(_))
+        ));
+
+        // placeholders of an explained statement are bound too
+        let res = vm
+            .execute_prepared(
+                "EXPLAIN SELECT name FROM t WHERE id = ?",
+                &[Value::Int64(1)],
+            )
+            .unwrap()
+            .unwrap();
+        assert!(res.all_data().contains(&Row::new(vec![Value::String(
+            "Filter { index: %0, expr: (column 'id' = 1) }".to_owned()
+        )])));
+
+        assert!(matches!(
+            vm.execute_prepared("SELECT name FROM t", &[Value::Int64(1)]),
+            Err(ExecutionError::RuntimeError(
+                RuntimeError::ParameterCountMismatch {
+                    expected: 0,
+                    got: 1
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn execute_iter() {
+        let mut vm = VirtualMachine::default();
+        vm.execute("CREATE TABLE t (id INTEGER, name TEXT)")
+            .unwrap();
+        vm.execute("INSERT INTO t VALUES (1, 'a'), (2, 'b'), (3, 'c')")
+            .unwrap();
+
+        let num_tables = vm.tables.len();
+        let rows = vm.execute_iter("SELECT name FROM t WHERE id > 1").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows.collect::<Vec<_>>(),
+            vec![
+                Row::new(vec![Value::String("b".to_owned())]),
+                Row::new(vec![Value::String("c".to_owned())]),
+            ]
+        );
+        // the result table was moved out of the VM, not copied, and the filtered table dropped.
+        assert_eq!(vm.tables.len(), num_tables);
+
+        assert_eq!(
+            vm.execute_iter("INSERT INTO t VALUES (4, 'd')")
+                .unwrap()
+                .count(),
+            0
+        );
+        assert!(vm.execute_iter("SELECT * FROM missing").is_err());
+
+        // the source table itself is left alone.
+        assert_eq!(vm.execute_iter("SELECT * FROM t").unwrap().count(), 4);
+        assert_eq!(vm.execute_iter("SELECT * FROM t").unwrap().count(), 4);
+    }
+
+    #[test]
+    fn error_messages() {
+        let mut vm = VirtualMachine::default();
+
+        let err = vm.execute("SELECT * FROM table1").unwrap_err();
+        assert_eq!(err.to_string(), "Table not found: 'table1'");
+
+        vm.execute("CREATE SCHEMA schema1").unwrap();
+        let err = vm.execute("SELECT * FROM schema1.table1").unwrap_err();
+        assert_eq!(err.to_string(), "Table not found: 'schema1.table1'");
+
+        vm.execute("CREATE TABLE table1 (col1 INTEGER)").unwrap();
+        let err = check_single_statement("SELECT COUNT(*) AS c FROM table1 GROUP BY col2", &mut vm)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Column not found: 'table1.col2'");
+    }
+
+    #[test]
+    fn coalesce() {
+        let mut vm = VirtualMachine::default();
+
+        check_single_statement("CREATE TABLE table1 (col1 STRING, col2 STRING)", &mut vm).unwrap();
+        check_single_statement(
+            "INSERT INTO table1 VALUES (NULL, NULL), (NULL, 'b'), ('a', 'c')",
+            &mut vm,
+        )
+        .unwrap();
+
+        let res = check_single_statement("SELECT COALESCE(col1, col2) AS c FROM table1", &mut vm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.all_data(),
+            vec![
+                Row::new(vec![Value::Null]),
+                Row::new(vec![Value::String("b".to_owned())]),
+                Row::new(vec![Value::String("a".to_owned())]),
+            ]
+        );
+        // the type comes from the first non-NULL value
+        assert_eq!(
+            res.columns().map(|c| c.data_type()).collect::<Vec<_>>(),
+            vec![&DataType::String]
+        );
+    }
+
+    #[test]
+    fn strict_create() {
+        let mut vm = VirtualMachine::default();
+
+        let query = "CREATE TABLE table1 (col1 INTEGER NOT NULL DEFAULT NULL)";
+        vm.set_strict_create(true);
+        assert_eq!(
+            check_single_statement(query, &mut vm).map(|_| ()),
+            Err(RuntimeError::NotNullColumnWithNullDefault(ColumnRef {
+                schema_name: None,
+                table_name: Some("table1".into()),
+                col_name: "col1".into(),
+            }))
+        );
+        assert_eq!(
+            check_single_statement(
+                "CREATE TABLE table1 (col1 INTEGER NOT NULL DEFAULT COALESCE(NULL, NULL))",
+                &mut vm
+            )
+            .map(|_| ()),
+            Err(RuntimeError::NotNullColumnWithNullDefault(ColumnRef {
+                schema_name: None,
+                table_name: Some("table1".into()),
+                col_name: "col1".into(),
+            }))
+        );
+
+        // fine without a default, or with one that is not NULL
+        check_single_statement(
+            "CREATE TABLE table2 (col1 INTEGER NOT NULL, col2 INTEGER NOT NULL DEFAULT 1, col3 INTEGER DEFAULT NULL)",
+            &mut vm,
+        )
+        .unwrap();
+
+        // left to inserts when not strict
+        vm.set_strict_create(false);
+        check_single_statement(query, &mut vm).unwrap();
+    }
 }